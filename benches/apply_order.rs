@@ -0,0 +1,66 @@
+//! A write-heavy last-writer-wins map, with batches applied in FIFO order versus in LIFO order
+//! with superseded assignments skipped.
+
+mod common;
+
+use reft_light::{Apply, ApplyOrder};
+use std::collections::BTreeMap;
+use std::hint::black_box;
+
+const OPS: u32 = 16_384;
+const RUNS: usize = 50;
+
+type Map = BTreeMap<u32, String>;
+
+struct Set(u32, String);
+impl Apply<Map, ()> for Set {
+    fn apply_first(&mut self, first: &mut Map, _: &Map, _: &mut ()) {
+        first.insert(self.0, self.1.clone());
+    }
+
+    fn apply_second(self, _: &Map, second: &mut Map, _: &mut ()) {
+        second.insert(self.0, self.1);
+    }
+
+    fn conflicts_with(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+
+    fn supersedes(&self, earlier: &Self) -> bool {
+        self.0 == earlier.0
+    }
+}
+
+/// Time the publish that applies a batch of assignments to one copy, and the previous batch to
+/// the other.
+fn bench(order: ApplyOrder, keys: u32) -> (common::Samples, Map) {
+    let mut w = reft_light::new::<Set, _, _>(Map::new(), ());
+    w.set_apply_order(order);
+    let mut round = 0;
+    let samples = common::sample(RUNS, || {
+        round += 1;
+        w.extend((0..OPS).map(|i| Set(i * 7 % keys, format!("{}:{}", round, i))));
+        let elapsed = common::time(|| {
+            w.publish();
+        });
+        black_box(w.enter().unwrap().len());
+        elapsed
+    });
+    w.publish();
+    (samples, *w.take())
+}
+
+fn main() {
+    // skipping an assignment saves an insert, but finding out that it can be skipped means
+    // checking it against every key assigned later in the batch
+    for keys in [16, 256] {
+        let (fifo, fifo_map) = bench(ApplyOrder::Fifo, keys);
+        let (lifo, lifo_map) = bench(ApplyOrder::Lifo, keys);
+        assert_eq!(fifo_map, lifo_map);
+        common::report(&format!("publish, {} keys, FIFO", keys), fifo);
+        common::report(
+            &format!("publish, {} keys, LIFO with supersession", keys),
+            lifo,
+        );
+    }
+}
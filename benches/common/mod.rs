@@ -0,0 +1,61 @@
+//! Helpers shared by the benchmarks.
+//!
+//! The benchmarks only depend on the standard library, so each of them is a plain binary
+//! (`harness = false`) that times its workloads and prints a line per measurement. Run one with
+//! `cargo bench --bench <name>`.
+#![allow(dead_code)]
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// The durations of repeated runs of a workload.
+pub struct Samples(Vec<Duration>);
+
+impl Samples {
+    pub fn median(&self) -> Duration {
+        let mut sorted = self.0.clone();
+        sorted.sort();
+        sorted[sorted.len() / 2]
+    }
+
+    pub fn mean(&self) -> Duration {
+        self.0.iter().sum::<Duration>() / self.0.len() as u32
+    }
+}
+
+impl fmt::Display for Samples {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "median {:>12?}  mean {:>12?}  ({} runs)",
+            self.median(),
+            self.mean(),
+            self.0.len()
+        )
+    }
+}
+
+/// Run `f` once to warm up, and then `runs` more times.
+///
+/// `f` times the part of the run that is being measured itself, so that it can prepare each run
+/// without the preparation showing up in the results.
+pub fn sample<F>(runs: usize, mut f: F) -> Samples
+where
+    F: FnMut() -> Duration,
+{
+    assert!(runs > 0);
+    f();
+    Samples((0..runs).map(|_| f()).collect())
+}
+
+/// Time a single call to `f`.
+pub fn time<F: FnOnce()>(f: F) -> Duration {
+    let start = Instant::now();
+    f();
+    start.elapsed()
+}
+
+/// Print one measurement.
+pub fn report(name: &str, result: impl fmt::Display) {
+    println!("{:<48} {}", name, result);
+}
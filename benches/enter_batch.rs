@@ -0,0 +1,48 @@
+//! A reader doing 1000 short reads in a row, entering once per read versus once per batch.
+//!
+//! Every `enter` and every dropped guard bumps the reader's epoch, so the loop that enters per
+//! read performs 2000 atomic writes, while the batch performs 2.
+
+mod common;
+
+use reft_light::Apply;
+use std::hint::black_box;
+
+const READS: usize = 1000;
+const RUNS: usize = 1000;
+
+struct Push(u64);
+impl Apply<Vec<u64>, ()> for Push {
+    fn apply_first(&mut self, first: &mut Vec<u64>, _: &Vec<u64>, _: &mut ()) {
+        first.push(self.0);
+    }
+}
+
+fn main() {
+    let mut w = reft_light::new::<Push, _, _>(Vec::new(), ());
+    w.extend((0..READS as u64).map(Push));
+    w.publish();
+    let r = w.clone();
+
+    let per_read = common::sample(RUNS, || {
+        common::time(|| {
+            for i in 0..READS {
+                black_box(r.enter().unwrap()[black_box(i)]);
+            }
+        })
+    });
+    common::report(
+        &format!("enter per read ({} epoch writes)", 2 * READS),
+        per_read,
+    );
+
+    let batched = common::sample(RUNS, || {
+        common::time(|| {
+            let batch = r.enter_batch().unwrap();
+            for i in 0..READS {
+                black_box(batch.read(|v| v[black_box(i)]));
+            }
+        })
+    });
+    common::report("enter_batch (2 epoch writes)", batched);
+}
@@ -0,0 +1,85 @@
+//! A sum-of-adds workload over a numeric array, applied one operation at a time versus in runs
+//! through the homogeneous batch hooks.
+
+mod common;
+
+use reft_light::{Apply, Reclaim};
+use std::hint::black_box;
+
+const LEN: usize = 4096;
+const OPS: usize = 1000;
+const RUNS: usize = 100;
+
+/// Adds a value to every element, one operation at a time.
+struct Add(u64);
+impl Apply<Vec<u64>, ()> for Add {
+    fn apply_first(&mut self, first: &mut Vec<u64>, _: &Vec<u64>, _: &mut ()) {
+        for x in first.iter_mut() {
+            *x = x.wrapping_add(self.0);
+        }
+    }
+}
+
+/// Adds a value to every element, but applies a run of additions in a single pass.
+struct BatchedAdd(u64);
+
+impl BatchedAdd {
+    fn add_all<'a>(ops: impl Iterator<Item = &'a BatchedAdd>, data: &mut [u64]) {
+        let sum = ops.fold(0u64, |sum, op| sum.wrapping_add(op.0));
+        for x in data.iter_mut() {
+            *x = x.wrapping_add(sum);
+        }
+    }
+}
+
+impl Apply<Vec<u64>, ()> for BatchedAdd {
+    fn apply_first(&mut self, first: &mut Vec<u64>, _: &Vec<u64>, _: &mut ()) {
+        Self::add_all(std::iter::once(&*self), first);
+    }
+
+    fn is_homogeneous_with(&self, _: &Self) -> bool {
+        true
+    }
+
+    fn apply_first_homogeneous(
+        ops: &mut [Self],
+        first: &mut Vec<u64>,
+        _: &Vec<u64>,
+        _: &mut (),
+        _: &mut Reclaim,
+    ) {
+        Self::add_all(ops.iter(), first);
+    }
+
+    fn apply_second_homogeneous(
+        ops: std::vec::Drain<'_, Self>,
+        _: &Vec<u64>,
+        second: &mut Vec<u64>,
+        _: &mut (),
+        _: &mut Reclaim,
+    ) {
+        Self::add_all(ops.as_slice().iter(), second);
+    }
+}
+
+/// Time publishing `OPS` additions, including applying the previous batch to the other copy.
+fn bench<O, F>(op: F) -> common::Samples
+where
+    O: Apply<Vec<u64>, ()>,
+    F: Fn(u64) -> O,
+{
+    let mut w = reft_light::new::<O, _, _>(vec![0; LEN], ());
+    common::sample(RUNS, || {
+        w.extend((0..OPS as u64).map(&op));
+        let elapsed = common::time(|| {
+            w.publish();
+        });
+        black_box(w.enter().unwrap()[0]);
+        elapsed
+    })
+}
+
+fn main() {
+    common::report("publish, one operation at a time", bench(Add));
+    common::report("publish, homogeneous runs", bench(BatchedAdd));
+}
@@ -0,0 +1,87 @@
+//! Replaying a batch of inserts into a 16-partition map, sequentially versus in parallel across
+//! partitions.
+
+mod common;
+
+use reft_light::{Apply, ApplyPartitioned, Partitioned};
+use std::collections::HashMap;
+use std::hint::black_box;
+
+const PARTITIONS: usize = 16;
+const OPS: u64 = 1 << 16;
+const RUNS: usize = 20;
+
+#[derive(Clone)]
+struct Sharded(Vec<HashMap<u64, u64>>);
+
+impl Partitioned for Sharded {
+    type Partition = HashMap<u64, u64>;
+
+    fn partitions(&self) -> Vec<&Self::Partition> {
+        self.0.iter().collect()
+    }
+
+    fn partitions_mut(&mut self) -> Vec<&mut Self::Partition> {
+        self.0.iter_mut().collect()
+    }
+}
+
+struct Insert(u64);
+
+impl Insert {
+    fn partition(&self) -> usize {
+        (self.0 % PARTITIONS as u64) as usize
+    }
+
+    fn insert(&self, partition: &mut HashMap<u64, u64>) {
+        // stand in for an operation that does some work besides the insert
+        let value = (0..64).fold(self.0, |v, i| v.rotate_left(5) ^ i);
+        partition.insert(self.0, value);
+    }
+}
+
+impl Apply<Sharded, ()> for Insert {
+    fn apply_first(&mut self, first: &mut Sharded, _: &Sharded, _: &mut ()) {
+        self.insert(&mut first.0[Insert::partition(self)]);
+    }
+}
+
+impl ApplyPartitioned<Sharded, ()> for Insert {
+    fn partition(&self) -> usize {
+        Insert::partition(self)
+    }
+
+    fn apply_first_partitioned(&mut self, first: &mut HashMap<u64, u64>, _: &HashMap<u64, u64>) {
+        self.insert(first);
+    }
+}
+
+/// Time the publish that applies a batch of inserts to one copy, and the previous batch to the
+/// other.
+fn bench(mut w: reft_light::WriteHandle<Insert, Sharded, ()>) -> common::Samples {
+    let mut next = 0;
+    common::sample(RUNS, || {
+        w.extend((next..next + OPS).map(Insert));
+        next += OPS;
+        let elapsed = common::time(|| {
+            w.publish();
+        });
+        black_box(w.enter().unwrap().0.len());
+        elapsed
+    })
+}
+
+fn main() {
+    let empty = || Sharded(vec![HashMap::new(); PARTITIONS]);
+    common::report(
+        "publish, sequential",
+        bench(reft_light::new::<Insert, _, _>(empty(), ())),
+    );
+    common::report(
+        &format!(
+            "publish, parallel ({} threads available)",
+            std::thread::available_parallelism().map_or(1, |n| n.get())
+        ),
+        bench(reft_light::new_partitioned::<Insert, _, _>(empty(), ())),
+    );
+}
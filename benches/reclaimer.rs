@@ -0,0 +1,86 @@
+//! Publish latency for removals of large values, with the removed values dropped inline versus
+//! handed to a reclaimer that drops them on a background thread.
+//!
+//! Every value is made up of many small allocations, so that dropping it takes a while.
+
+mod common;
+
+use reft_light::{Apply, Reclaim, Reclaimed};
+use std::collections::HashMap;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+const VALUES: u32 = 64;
+const VALUE_LEN: usize = 4096;
+const RUNS: usize = 50;
+
+type Map = HashMap<u32, Vec<String>>;
+
+enum Op {
+    Insert(u32),
+    Remove(u32),
+}
+
+impl Op {
+    fn apply(&self, map: &mut Map, reclaim: Option<&mut Reclaim>) {
+        match *self {
+            Op::Insert(k) => {
+                map.insert(k, (0..VALUE_LEN).map(|i| i.to_string()).collect());
+            }
+            Op::Remove(k) => {
+                let removed = map.remove(&k);
+                if let (Some(value), Some(reclaim)) = (removed, reclaim) {
+                    reclaim.defer(value);
+                }
+            }
+        }
+    }
+}
+
+impl Apply<Map, ()> for Op {
+    fn apply_first(&mut self, first: &mut Map, _: &Map, _: &mut ()) {
+        self.apply(first, None);
+    }
+
+    fn apply_first_deferred(&mut self, first: &mut Map, _: &Map, _: &mut (), r: &mut Reclaim) {
+        self.apply(first, Some(r));
+    }
+
+    fn apply_second_deferred(self, _: &Map, second: &mut Map, _: &mut (), r: &mut Reclaim) {
+        self.apply(second, Some(r));
+    }
+}
+
+/// Time the two publishes that remove `VALUES` large values from both copies.
+fn bench(deferred: bool) -> common::Samples {
+    let mut w = reft_light::new::<Op, _, _>(Map::new(), ());
+    let dropper = if deferred {
+        let (tx, rx) = mpsc::channel::<Reclaimed>();
+        w.set_reclaimer(move |batch| tx.send(batch).unwrap());
+        Some(thread::spawn(move || rx.iter().for_each(drop)))
+    } else {
+        None
+    };
+
+    let samples = common::sample(RUNS, || {
+        w.extend((0..VALUES).map(Op::Insert));
+        w.publish().publish();
+        // give the dropper a chance to catch up, so that runs do not pile up on one another
+        thread::sleep(Duration::from_millis(20));
+        w.extend((0..VALUES).map(Op::Remove));
+        common::time(|| {
+            w.publish().publish();
+        })
+    });
+    drop(w);
+    if let Some(dropper) = dropper {
+        dropper.join().unwrap();
+    }
+    samples
+}
+
+fn main() {
+    common::report("remove and publish twice, dropped inline", bench(false));
+    common::report("remove and publish twice, reclaimer", bench(true));
+}
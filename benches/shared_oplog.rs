@@ -0,0 +1,91 @@
+//! Memory held by a backlog of operations that already live in a shared log segment, appended by
+//! reference to a `SharedOplog` versus copied into the default in-memory oplog.
+
+mod common;
+
+use reft_light::{Apply, SharedOplog};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Keeps track of how many bytes are currently allocated.
+struct Counting;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const SEGMENT: usize = 100_000;
+const RANGES: usize = 100;
+const RUNS: usize = 20;
+
+#[derive(Clone)]
+struct Add([u64; 4]);
+impl Apply<u64, ()> for Add {
+    fn apply_first(&mut self, first: &mut u64, _: &u64, _: &mut ()) {
+        *first = first.wrapping_add(self.0.iter().sum());
+    }
+}
+
+/// Append the whole segment in `RANGES` ranges with `append`, and return the bytes this left
+/// allocated along with how long it took.
+fn backlog<F>(segment: &Arc<Vec<Add>>, append: F) -> (usize, std::time::Duration)
+where
+    F: FnOnce(&Arc<Vec<Add>>) -> Box<dyn std::any::Any>,
+{
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    let start = std::time::Instant::now();
+    let writer = append(segment);
+    let elapsed = start.elapsed();
+    let held = ALLOCATED.load(Ordering::Relaxed) - before;
+    drop(writer);
+    (held, elapsed)
+}
+
+fn ranges() -> impl Iterator<Item = std::ops::Range<usize>> {
+    let step = SEGMENT / RANGES;
+    (0..RANGES).map(move |i| i * step..(i + 1) * step)
+}
+
+fn main() {
+    let segment: Arc<Vec<Add>> = Arc::new((0..SEGMENT as u64).map(|i| Add([i; 4])).collect());
+
+    for (name, shared) in [("copied into the oplog", false), ("SharedOplog", true)] {
+        let mut held = 0;
+        let time = common::sample(RUNS, || {
+            let (bytes, elapsed) = backlog(&segment, |segment| {
+                if shared {
+                    let mut w =
+                        reft_light::new_with_oplog::<Add, _, _, _>(0, (), SharedOplog::new());
+                    for range in ranges() {
+                        w.append_range(Arc::clone(segment), range);
+                    }
+                    Box::new(w)
+                } else {
+                    let mut w = reft_light::new::<Add, _, _>(0, ());
+                    for range in ranges() {
+                        w.append_range(Arc::clone(segment), range);
+                    }
+                    Box::new(w)
+                }
+            });
+            held = bytes;
+            elapsed
+        });
+        common::report(&format!("{}: bytes held", name), held);
+        common::report(&format!("{}: append", name), time);
+    }
+}
@@ -0,0 +1,88 @@
+//! Replaying operations out of a ring buffer that wraps around, versus out of a contiguous one.
+//!
+//! The first half iterates over the same operations laid out both ways in a bare `VecDeque`. The
+//! second half publishes through a writer whose oplog wrapped around under `ShrinkPolicy::Never`,
+//! but was compacted under `ShrinkPolicy::Contiguous`.
+
+mod common;
+
+use reft_light::{Apply, ShrinkPolicy};
+use std::collections::VecDeque;
+use std::hint::black_box;
+use std::time::Instant;
+
+const OPS: usize = 1 << 16;
+const RUNS: usize = 200;
+
+struct Add(u64);
+impl Apply<u64, ()> for Add {
+    fn apply_first(&mut self, first: &mut u64, _: &u64, _: &mut ()) {
+        *first = first.wrapping_add(self.0);
+    }
+}
+
+/// A deque holding `OPS` values whose ring buffer wraps around in the middle.
+fn wrapped() -> VecDeque<u64> {
+    let mut deque = VecDeque::with_capacity(OPS);
+    deque.extend(0..OPS as u64 / 2);
+    deque.drain(..OPS / 4);
+    deque.extend(0..OPS as u64 / 4 * 3);
+    assert_eq!(deque.len(), OPS);
+    deque
+}
+
+fn iterate(deque: &VecDeque<u64>) -> u64 {
+    deque.iter().fold(0, |sum, v| sum.wrapping_add(*v))
+}
+
+/// Bring a writer's oplog into a state where the next publish replays `OPS` operations that were
+/// appended after the log's head moved, and time that publish.
+fn replay(policy: ShrinkPolicy) -> std::time::Duration {
+    let mut w = reft_light::new::<Add, _, _>(0, ());
+    w.set_shrink_policy(policy);
+    w.extend((0..OPS as u64).map(Add));
+    w.publish();
+    w.extend((0..OPS as u64 / 2).map(Add));
+    // drains the first batch, which moves the head of the ring buffer
+    w.publish();
+    w.extend((0..OPS as u64).map(Add));
+    let start = Instant::now();
+    w.publish();
+    let elapsed = start.elapsed();
+    black_box(*w.enter().unwrap());
+    elapsed
+}
+
+fn main() {
+    let fragmented = wrapped();
+    let mut contiguous = fragmented.clone();
+    contiguous.make_contiguous();
+    assert!(!fragmented.as_slices().1.is_empty());
+    assert!(contiguous.as_slices().1.is_empty());
+
+    common::report(
+        "iterate wrapped deque",
+        common::sample(RUNS, || {
+            common::time(|| {
+                black_box(iterate(&fragmented));
+            })
+        }),
+    );
+    common::report(
+        "iterate contiguous deque",
+        common::sample(RUNS, || {
+            common::time(|| {
+                black_box(iterate(&contiguous));
+            })
+        }),
+    );
+
+    common::report(
+        "publish, ShrinkPolicy::Never",
+        common::sample(RUNS, || replay(ShrinkPolicy::Never)),
+    );
+    common::report(
+        "publish, ShrinkPolicy::Contiguous { ratio: 1 }",
+        common::sample(RUNS, || replay(ShrinkPolicy::Contiguous { ratio: 1 })),
+    );
+}
@@ -0,0 +1,40 @@
+//! Publish latency of a single-threaded double buffer, through the synchronized writer versus
+//! through `new_unsync`.
+
+mod common;
+
+use reft_light::Apply;
+use std::hint::black_box;
+
+const RUNS: usize = 100_000;
+
+struct Add(u64);
+impl Apply<u64, ()> for Add {
+    fn apply_first(&mut self, first: &mut u64, _: &u64, _: &mut ()) {
+        *first += self.0;
+    }
+}
+
+fn main() {
+    let mut w = reft_light::new::<Add, _, _>(0, ());
+    let synced = common::sample(RUNS, || {
+        w.append(Add(1));
+        let elapsed = common::time(|| {
+            w.publish();
+        });
+        black_box(*w.enter().unwrap());
+        elapsed
+    });
+    common::report("publish, synchronized", synced);
+
+    let mut w = reft_light::new_unsync::<Add, _, _>(0, ());
+    let unsynced = common::sample(RUNS, || {
+        w.append(Add(1));
+        let elapsed = common::time(|| {
+            w.publish();
+        });
+        black_box(*w.enter());
+        elapsed
+    });
+    common::report("publish, unsync", unsynced);
+}
@@ -0,0 +1,63 @@
+//! Publish latency with many registered readers of which only a few are active.
+//!
+//! The writer only rescans the readers that were active at the last swap, so idle readers should
+//! add next to nothing to the cost of a publish.
+
+mod common;
+
+use reft_light::Apply;
+use std::hint::black_box;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+const ACTIVE: usize = 5;
+const RUNS: usize = 10_000;
+
+struct Add(u64);
+impl Apply<u64, ()> for Add {
+    fn apply_first(&mut self, first: &mut u64, _: &u64, _: &mut ()) {
+        *first += self.0;
+    }
+}
+
+fn bench(readers: usize) -> common::Samples {
+    let mut w = reft_light::new::<Add, _, _>(0, ());
+    let idle: Vec<_> = (ACTIVE..readers).map(|_| w.clone()).collect();
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let active: Vec<_> = (0..ACTIVE)
+        .map(|_| {
+            let r = w.clone();
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    black_box(*r.enter().unwrap());
+                }
+            })
+        })
+        .collect();
+
+    let samples = common::sample(RUNS, || {
+        w.append(Add(1));
+        common::time(|| {
+            w.publish();
+        })
+    });
+
+    stop.store(true, Ordering::Relaxed);
+    active.into_iter().for_each(|t| t.join().unwrap());
+    drop(idle);
+    samples
+}
+
+fn main() {
+    common::report(
+        &format!("publish, {} readers, all active", ACTIVE),
+        bench(ACTIVE),
+    );
+    common::report(
+        &format!("publish, 1000 readers, {} active", ACTIVE),
+        bench(1000),
+    );
+}
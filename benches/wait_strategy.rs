@@ -0,0 +1,95 @@
+//! How much CPU the writer burns while a publish waits out a reader that stalls for 100ms, with
+//! each of the built-in wait strategies.
+
+mod common;
+
+use reft_light::{Apply, ExponentialBackoff, SpinThenYield, WaitStrategy};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+const STALL: Duration = Duration::from_millis(100);
+const RUNS: usize = 5;
+
+struct Add(u64);
+impl Apply<u64, ()> for Add {
+    fn apply_first(&mut self, first: &mut u64, _: &u64, _: &mut ()) {
+        *first += self.0;
+    }
+}
+
+/// Returns how long the current thread has run on a CPU, if the platform tells us.
+fn thread_cpu_time() -> Option<Duration> {
+    let stat = std::fs::read_to_string("/proc/thread-self/schedstat").ok()?;
+    let nanos = stat.split_whitespace().next()?.parse().ok()?;
+    Some(Duration::from_nanos(nanos))
+}
+
+/// Publish over a reader that holds on to the stale copy for `STALL`, and return the wall-clock
+/// time and the CPU time the publish took.
+fn stalled_publish<W>(strategy: W) -> (Duration, Option<Duration>)
+where
+    W: WaitStrategy + Send + 'static,
+{
+    let mut w = reft_light::new::<Add, _, _>(0, ());
+    w.set_wait_strategy(strategy);
+    let r = w.clone();
+
+    let (entered_tx, entered_rx) = mpsc::channel();
+    let reader = thread::spawn(move || {
+        let guard = r.enter().unwrap();
+        entered_tx.send(()).unwrap();
+        thread::sleep(STALL);
+        drop(guard);
+    });
+    entered_rx.recv().unwrap();
+
+    // the first publish swaps the reader's copy out, the second one has to reuse it
+    w.append(Add(1)).publish();
+    let cpu = thread_cpu_time();
+    let wall = common::time(|| {
+        w.append(Add(1)).publish();
+    });
+    let cpu = cpu.and_then(|before| Some(thread_cpu_time()? - before));
+    reader.join().unwrap();
+    (wall, cpu)
+}
+
+fn bench<W>(name: &str, strategy: W)
+where
+    W: WaitStrategy + Clone + Send + 'static,
+{
+    let mut cpu = Vec::new();
+    let wall = common::sample(RUNS, || {
+        let (wall, used) = stalled_publish(strategy.clone());
+        cpu.extend(used);
+        wall
+    });
+    common::report(&format!("{}: wall", name), wall);
+    // leave out the warm-up run, just like `sample` does
+    if !cpu.is_empty() {
+        cpu.remove(0);
+    }
+    if let Some(max) = cpu.iter().max() {
+        let mean = cpu.iter().sum::<Duration>() / cpu.len() as u32;
+        common::report(
+            &format!("{}: writer cpu", name),
+            format!("mean {:>12?}  max {:>12?}", mean, max),
+        );
+    }
+}
+
+fn main() {
+    bench("spin-then-yield", SpinThenYield);
+    bench(
+        "exponential backoff (default)",
+        ExponentialBackoff::default(),
+    );
+    bench(
+        "exponential backoff (10ms cap)",
+        ExponentialBackoff {
+            max_spin: 64,
+            max_sleep: Duration::from_millis(10),
+        },
+    );
+}
@@ -0,0 +1,6 @@
+fn main() {
+    // loom tests are built with `RUSTFLAGS="--cfg loom"`. declared here rather than in a
+    // `[lints]` table, which the oldest toolchains the crate supports do not understand.
+    println!("cargo:rustc-check-cfg=cfg(loom)");
+    println!("cargo:rerun-if-changed=build.rs");
+}
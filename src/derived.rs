@@ -0,0 +1,125 @@
+use std::fmt;
+use std::sync::Arc;
+
+/// Expensive data derived from `T` that both copies share, rather than each rebuilding it.
+///
+/// Some data structures carry derived data that is expensive to compute, like an index or a
+/// sorted view, and rebuild it in [`Apply::finalize_first`](crate::Apply::finalize_first) after
+/// each batch. By default, [`Apply::finalize_second`](crate::Apply::finalize_second) then repeats
+/// that work on the other copy a publish later, even though it arrives at exactly the same
+/// result. A `DerivedCache` kept in the auxiliary avoids the second build: the first copy builds
+/// the derived data into an `Arc`, and the second copy is handed a clone of that `Arc`.
+///
+/// The copies keep the derived data in a field of type `Option<Arc<C>>`, and the rules are:
+///
+///  - Every operation that changes what the derived data is derived from calls
+///    [`invalidate`](Self::invalidate) from `apply_first`. Invalidations from `apply_second` are
+///    ignored, so `apply_second` may well default to calling `apply_first`.
+///  - `finalize_first` stores [`refresh`](Self::refresh) in the first copy. This rebuilds the
+///    derived data if anything invalidated it since the last build.
+///  - `finalize_second` stores [`current`](Self::current) in the second copy. By the time the
+///    second copy is finalized, it has seen exactly the operations that the first copy had seen
+///    when the derived data was last built, so it can share that build.
+///  - The derived data is never modified in place, since readers of both copies may be looking
+///    at it. Derive it anew instead.
+///
+/// # Examples
+///
+/// ```
+/// use reft_light::{Apply, DerivedCache};
+/// use std::collections::BTreeSet;
+/// use std::sync::Arc;
+///
+/// #[derive(Clone, Default)]
+/// struct Words {
+///     words: Vec<String>,
+///     // expensive to build, so both copies share it
+///     sorted: Option<Arc<BTreeSet<String>>>,
+/// }
+///
+/// struct Add(String);
+/// impl Apply<Words, DerivedCache<BTreeSet<String>>> for Add {
+///     fn apply_first(&mut self, first: &mut Words, _: &Words, cache: &mut DerivedCache<BTreeSet<String>>) {
+///         first.words.push(self.0.clone());
+///         cache.invalidate();
+///     }
+///     fn finalize_first(first: &mut Words, _: &Words, cache: &mut DerivedCache<BTreeSet<String>>) {
+///         first.sorted = Some(cache.refresh(|| first.words.iter().cloned().collect()));
+///     }
+///     fn finalize_second(_: &Words, second: &mut Words, cache: &mut DerivedCache<BTreeSet<String>>) {
+///         second.sorted = cache.current();
+///     }
+/// }
+///
+/// let mut w = reft_light::new::<Add, _, _>(Words::default(), DerivedCache::new());
+/// let r = w.clone();
+/// w.append(Add("b".into())).append(Add("a".into())).publish();
+/// w.append(Add("c".into())).publish();
+///
+/// let words = r.enter().unwrap();
+/// let sorted: Vec<_> = words.sorted.as_ref().unwrap().iter().collect();
+/// assert_eq!(sorted, ["a", "b", "c"]);
+/// ```
+pub struct DerivedCache<C> {
+    current: Option<Arc<C>>,
+    stale: bool,
+}
+
+impl<C> DerivedCache<C> {
+    /// Make a cache that builds the derived data on the first refresh.
+    pub fn new() -> Self {
+        Self {
+            current: None,
+            stale: true,
+        }
+    }
+
+    /// Mark the derived data as out of date, so that the next refresh rebuilds it.
+    pub fn invalidate(&mut self) {
+        self.stale = true;
+    }
+
+    /// Returns the derived data, built anew with `build` if it has been invalidated since it was
+    /// last built.
+    pub fn refresh<F>(&mut self, build: F) -> Arc<C>
+    where
+        F: FnOnce() -> C,
+    {
+        match &self.current {
+            Some(current) if !self.stale => Arc::clone(current),
+            _ => {
+                let current = Arc::new(build());
+                self.current = Some(Arc::clone(&current));
+                self.stale = false;
+                current
+            }
+        }
+    }
+
+    /// Returns the derived data as it was last built, without building it.
+    ///
+    /// This also drops invalidations that have happened since the last build, since they can
+    /// only have come from operations being applied to the second copy.
+    pub fn current(&mut self) -> Option<Arc<C>> {
+        self.stale = false;
+        self.current.clone()
+    }
+}
+
+impl<C> Default for DerivedCache<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C> fmt::Debug for DerivedCache<C>
+where
+    C: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DerivedCache")
+            .field("current", &self.current)
+            .field("stale", &self.stale)
+            .finish()
+    }
+}
@@ -0,0 +1,51 @@
+use std::fmt;
+
+/// One place where the two copies of the data differ.
+///
+/// Produced by [`Apply::diff`](crate::Apply::diff) when a checked write handle (see
+/// [`new_checked`](crate::new_checked) and [`new_checked_hash`](crate::new_checked_hash)) finds
+/// that the copies have diverged. The entries end up in the message of the resulting panic, so
+/// they only need to be readable, not machine-processable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffEntry {
+    /// Where in the data the copies differ, like an index or a key.
+    pub location: String,
+    /// What the first copy holds at `location`.
+    pub first: String,
+    /// What the second copy holds at `location`.
+    pub second: String,
+}
+
+impl DiffEntry {
+    /// Describe a difference at `location` using the `Debug` representations of both values.
+    pub fn new<L, V>(location: L, first: V, second: V) -> Self
+    where
+        L: fmt::Display,
+        V: fmt::Debug,
+    {
+        Self {
+            location: location.to_string(),
+            first: format!("{:?}", first),
+            second: format!("{:?}", second),
+        }
+    }
+}
+
+impl fmt::Display for DiffEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "at {}: {} != {}", self.location, self.first, self.second)
+    }
+}
+
+/// Describe how two diverged copies differ, for the panic that reports it.
+pub(crate) fn describe(entries: &[DiffEntry]) -> String {
+    if entries.is_empty() {
+        return String::from("copies differ (implement Apply::diff for details)");
+    }
+    let mut description = String::new();
+    for entry in entries {
+        description.push_str("\n  ");
+        description.push_str(&entry.to_string());
+    }
+    description
+}
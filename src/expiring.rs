@@ -0,0 +1,250 @@
+use crate::{Apply, Delta, DiffEntry, Reclaim};
+use std::ptr;
+use std::time::{Duration, Instant};
+
+/// An operation that is skipped if it is not published before its deadline.
+///
+/// Some operations are only worth applying if they take effect soon enough, like a rate-limited
+/// update that is stale once it has been delayed. Wrapping such an operation in an `Expiring`
+/// attaches a deadline to it. When the writer applies the operation to the first copy, it checks
+/// the deadline against [`Reclaim::now`], the time the writer captured when it started applying
+/// the batch, and skips the operation if the deadline has passed.
+///
+/// The second copy is brought up to date during the following publish, at a later time. So that
+/// both copies end up the same, the decision made for the first copy is remembered, and the
+/// operation is skipped on the second copy if and only if it was skipped on the first.
+///
+/// # Examples
+///
+/// ```
+/// use reft_light::{Apply, Expiring};
+/// use std::time::{Duration, Instant};
+///
+/// struct Add(u64);
+/// impl Apply<u64, ()> for Add {
+///     fn apply_first(&mut self, first: &mut u64, _: &u64, _: &mut ()) {
+///         *first += self.0;
+///     }
+/// }
+///
+/// let mut w = reft_light::new::<Expiring<Add>, _, _>(0, ());
+/// let r = w.clone();
+/// w.append(Expiring::new(Add(1), Instant::now()));
+/// w.append(Expiring::within(Add(2), Duration::from_secs(60)));
+/// w.publish();
+///
+/// // the first operation expired before it was published
+/// assert_eq!(*r.enter().unwrap(), 2);
+/// ```
+#[derive(Debug)]
+pub struct Expiring<O> {
+    op: O,
+    deadline: Instant,
+    expired: Option<bool>,
+}
+
+impl<O> Expiring<O> {
+    /// Wrap `op` so that it is skipped unless it is applied before `deadline`.
+    pub fn new(op: O, deadline: Instant) -> Self {
+        Self {
+            op,
+            deadline,
+            expired: None,
+        }
+    }
+
+    /// Wrap `op` so that it is skipped unless it is applied within `ttl` from now.
+    pub fn within(op: O, ttl: Duration) -> Self {
+        Self::new(op, Instant::now() + ttl)
+    }
+
+    /// Returns the deadline of the operation.
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    /// Returns whether the operation was skipped, or `None` if it has not been applied yet.
+    pub fn expired(&self) -> Option<bool> {
+        self.expired
+    }
+
+    /// Returns the wrapped operation.
+    pub fn into_inner(self) -> O {
+        self.op
+    }
+
+    fn expires(&mut self, now: Instant) -> bool {
+        *self.expired.get_or_insert(now >= self.deadline)
+    }
+}
+
+impl<O, T, A> Apply<T, A> for Expiring<O>
+where
+    O: Apply<T, A>,
+{
+    fn apply_first(&mut self, first: &mut T, second: &T, auxiliary: &mut A) {
+        // the writer always applies through the deferred hooks, which see the time of the batch.
+        // called directly, there is no batch, so check against the time of the call.
+        if !self.expires(Instant::now()) {
+            self.op.apply_first(first, second, auxiliary);
+        }
+    }
+
+    fn apply_second(self, first: &T, second: &mut T, auxiliary: &mut A) {
+        if self.expired != Some(true) {
+            self.op.apply_second(first, second, auxiliary);
+        }
+    }
+
+    fn apply_first_deferred(
+        &mut self,
+        first: &mut T,
+        second: &T,
+        auxiliary: &mut A,
+        reclaim: &mut Reclaim,
+    ) {
+        if !self.expires(reclaim.now()) {
+            self.op
+                .apply_first_deferred(first, second, auxiliary, reclaim);
+        }
+    }
+
+    fn apply_second_deferred(
+        self,
+        first: &T,
+        second: &mut T,
+        auxiliary: &mut A,
+        reclaim: &mut Reclaim,
+    ) {
+        if self.expired != Some(true) {
+            self.op
+                .apply_second_deferred(first, second, auxiliary, reclaim);
+        }
+    }
+
+    fn is_homogeneous_with(&self, next: &Self) -> bool {
+        self.op.is_homogeneous_with(&next.op)
+    }
+
+    fn conflicts_with(&self, other: &Self) -> bool {
+        // the writer checks every operation of a batch against the same time, so expiry does not
+        // depend on the order of the operations
+        self.op.conflicts_with(&other.op)
+    }
+
+    fn supersedes(&self, earlier: &Self) -> bool {
+        // an operation that was skipped leaves the earlier one in effect
+        self.expired == Some(false) && self.op.supersedes(&earlier.op)
+    }
+
+    fn apply_first_homogeneous(
+        ops: &mut [Self],
+        first: &mut T,
+        second: &T,
+        auxiliary: &mut A,
+        reclaim: &mut Reclaim,
+    ) {
+        if let [op] = ops {
+            return op.apply_first_deferred(first, second, auxiliary, reclaim);
+        }
+        let now = reclaim.now();
+        let mut live = Unexpired::new(ops, now);
+        O::apply_first_homogeneous(&mut live.ops, first, second, auxiliary, reclaim);
+    }
+
+    fn apply_second_homogeneous(
+        ops: std::vec::Drain<'_, Self>,
+        first: &T,
+        second: &mut T,
+        auxiliary: &mut A,
+        reclaim: &mut Reclaim,
+    ) {
+        if ops.len() == 1 {
+            for op in ops {
+                op.apply_second_deferred(first, second, auxiliary, reclaim);
+            }
+            return;
+        }
+        let mut live: Vec<O> = ops
+            .filter(|op| op.expired != Some(true))
+            .map(|op| op.op)
+            .collect();
+        O::apply_second_homogeneous(live.drain(..), first, second, auxiliary, reclaim);
+    }
+
+    fn validate(
+        &self,
+        current: &T,
+        auxiliary: &A,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.op.validate(current, auxiliary)
+    }
+
+    fn is_redundant(&self, current: &T) -> bool {
+        self.op.is_redundant(current)
+    }
+
+    fn finalize_first(first: &mut T, second: &T, auxiliary: &mut A) {
+        O::finalize_first(first, second, auxiliary);
+    }
+
+    fn finalize_second(first: &T, second: &mut T, auxiliary: &mut A) {
+        O::finalize_second(first, second, auxiliary);
+    }
+
+    fn diff(first: &T, second: &T) -> Vec<DiffEntry> {
+        O::diff(first, second)
+    }
+
+    fn summarize<'a, I>(ops: I, auxiliary: &A) -> Option<Delta>
+    where
+        I: Iterator<Item = &'a Self>,
+        Self: 'a,
+    {
+        // summaries are taken after the first copy was applied, so expiry has been decided
+        O::summarize(
+            ops.filter(|op| op.expired != Some(true)).map(|op| &op.op),
+            auxiliary,
+        )
+    }
+}
+
+/// The operations of a run that have not expired, moved out of their wrappers so that they can
+/// be handed to the batch hooks of the inner operation as a single slice.
+///
+/// The operations are moved back into their wrappers on drop, even if a batch hook panics.
+struct Unexpired<'a, O> {
+    wrappers: &'a mut [Expiring<O>],
+    indices: Vec<usize>,
+    ops: Vec<O>,
+}
+
+impl<'a, O> Unexpired<'a, O> {
+    fn new(wrappers: &'a mut [Expiring<O>], now: Instant) -> Self {
+        let indices: Vec<usize> = (0..wrappers.len())
+            .filter(|&i| !wrappers[i].expires(now))
+            .collect();
+        // allocate up front, so that nothing can panic between reading an operation out and
+        // pushing it
+        let mut ops = Vec::with_capacity(indices.len());
+        for &i in &indices {
+            // safety: the operation is written back on drop, and the wrapper is not touched in
+            // the meantime, since we hold the only reference to it.
+            ops.push(unsafe { ptr::read(&wrappers[i].op) });
+        }
+        Self {
+            wrappers,
+            indices,
+            ops,
+        }
+    }
+}
+
+impl<'a, O> Drop for Unexpired<'a, O> {
+    fn drop(&mut self) {
+        for (&i, op) in self.indices.iter().zip(self.ops.drain(..)) {
+            // safety: the operation at `i` was moved out in `new`, and has not been dropped.
+            unsafe { ptr::write(&mut self.wrappers[i].op, op) };
+        }
+    }
+}
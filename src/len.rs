@@ -0,0 +1,57 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+
+/// Data structures that can report how many elements they hold.
+///
+/// Used by [`WriteHandle::debug_copy_lens`](crate::WriteHandle::debug_copy_lens) to compare the
+/// sizes of the two copies.
+pub trait HasLen {
+    /// Returns the number of elements.
+    fn len(&self) -> usize;
+
+    /// Returns true if there are no elements.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T> HasLen for Vec<T> {
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+}
+
+impl<T> HasLen for VecDeque<T> {
+    fn len(&self) -> usize {
+        VecDeque::len(self)
+    }
+}
+
+impl HasLen for String {
+    fn len(&self) -> usize {
+        String::len(self)
+    }
+}
+
+impl<K, V, S> HasLen for HashMap<K, V, S> {
+    fn len(&self) -> usize {
+        HashMap::len(self)
+    }
+}
+
+impl<T, S> HasLen for HashSet<T, S> {
+    fn len(&self) -> usize {
+        HashSet::len(self)
+    }
+}
+
+impl<K, V> HasLen for BTreeMap<K, V> {
+    fn len(&self) -> usize {
+        BTreeMap::len(self)
+    }
+}
+
+impl<T> HasLen for BTreeSet<T> {
+    fn len(&self) -> usize {
+        BTreeSet::len(self)
+    }
+}
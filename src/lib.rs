@@ -22,19 +22,20 @@
 //! primitive are:
 //!
 //!  - **Increased memory use**: since we keep two copies of the backing data structure, we are
-//!  effectively doubling the memory use of the underlying data. With some clever de-duplication,
-//!  this cost can be ameliorated to some degree, but it's something to be aware of. Furthermore,
-//!  if writers only call `publish` infrequently despite adding many writes to the operational log,
-//!  the operational log itself may grow quite large, which adds additional overhead.
+//!    effectively doubling the memory use of the underlying data. With some clever de-duplication,
+//!    this cost can be ameliorated to some degree, but it's something to be aware of. Furthermore,
+//!    if writers only call `publish` infrequently despite adding many writes to the operational log,
+//!    the operational log itself may grow quite large, which adds additional overhead.
 //!  - **Deterministic operations**: as the entries in the operational log are applied twice, once
-//!  to each copy of the data, it is essential that the operations are deterministic. If they are
-//!  not, the two copies will no longer mirror one another, and will continue to diverge over time.
+//!    to each copy of the data, it is essential that the operations are deterministic. If they are
+//!    not, the two copies will no longer mirror one another, and will continue to diverge over time.
 //!  - **Single writer**: left-right only supports a single writer. To have multiple writers, you
-//!  need to ensure exclusive access to the [`WriteHandle`] through something like a
-//!  [`Mutex`](std::sync::Mutex).
+//!    need to ensure exclusive access to the [`WriteHandle`] through something like a
+//!    [`Mutex`](std::sync::Mutex). In debug builds, the `WriteHandle` checks that it is not
+//!    written to from another thread without being handed off; see [`WriteHandle::handoff`].
 //!  - **Slow writes**: Writes through left-right are slower than they would be directly against
-//!  the backing datastructure. This is both because they have to go through the operational log,
-//!  and because they must each be applied twice.
+//!    the backing datastructure. This is both because they have to go through the operational log,
+//!    and because they must each be applied twice.
 //!
 //! # How does it work?
 //!
@@ -161,6 +162,16 @@
 //! closure instead. Instead, consider using [`ReadGuard::map`] and [`ReadGuard::try_map`], which
 //! (like `RefCell`'s [`Ref::map`](std::cell::Ref::map)) allow you to provide a guarded reference
 //! deeper into your data structure.
+//!
+//! # Interior mutability
+//!
+//! Readers only get shared access to the read copy, and a [`ReadHandle`] can only be sent to
+//! another thread if `T` is `Sync`. That rules out `Cell` or `RefCell` based caches inside `T`,
+//! but it is still sound for reads to fill in caches that use `Sync` interior mutability, such as
+//! atomics. [`MemoizedRead`] packages the common case of memoizing an expensive result computed
+//! from the data. Whatever the cache holds must be derived purely from the rest of the copy, and
+//! must be cleared whenever an operation changes the copy, or the two copies will no longer be
+//! equivalent.
 #![warn(
     missing_docs,
     rust_2018_idioms,
@@ -171,15 +182,242 @@
 
 mod sync;
 
-use crate::sync::{Arc, AtomicUsize, Mutex};
+use crate::sync::{fence, Arc, AtomicBool, AtomicUsize, Condvar, Mutex, MutexGuard, Ordering};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::task::Waker;
 
 type Epochs = Arc<Mutex<slab::Slab<Arc<AtomicUsize>>>>;
 
+/// Lock the reader epochs, even if another thread panicked while holding the lock.
+///
+/// The slab is never left half-modified, so a panic elsewhere while the lock was held (say, in an
+/// `Apply` implementation during a publish) must not take every other handle down with it.
+fn lock_epochs(epochs: &Epochs) -> MutexGuard<'_, slab::Slab<Arc<AtomicUsize>>> {
+    epochs.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// The generation readers currently see, for readers that want to wait for a publish.
+///
+/// Shared by the writer and all of its readers. The writer updates it and wakes up any waiting
+/// readers after every swap, and when it is dropped. In the other direction, a writer that polls
+/// for readers to depart leaves a waker here for the departing readers to wake.
+type Published = Arc<Generation>;
+
+#[derive(Debug)]
+struct Generation {
+    state: Mutex<GenerationState>,
+    changed: Condvar,
+    writer_waiting: AtomicBool,
+    writer: Mutex<Option<Waker>>,
+}
+
+#[derive(Debug)]
+struct GenerationState {
+    generation: u64,
+    retired: bool,
+    // set once the retired writer has freed both copies
+    dropped: bool,
+    readers: Vec<Waker>,
+}
+
+impl Default for Generation {
+    fn default() -> Self {
+        Generation {
+            state: Mutex::new(GenerationState {
+                generation: 0,
+                retired: false,
+                dropped: false,
+                readers: Vec::new(),
+            }),
+            changed: Condvar::new(),
+            writer_waiting: AtomicBool::new(false),
+            writer: Mutex::new(None),
+        }
+    }
+}
+
+impl Generation {
+    fn lock(&self) -> MutexGuard<'_, GenerationState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Record a change to the published state, and wake up any readers waiting for one.
+    fn update<F: FnOnce(&mut GenerationState)>(&self, f: F) {
+        let readers = {
+            let mut state = self.lock();
+            f(&mut state);
+            std::mem::take(&mut state.readers)
+        };
+        self.changed.notify_all();
+        readers.into_iter().for_each(Waker::wake);
+    }
+
+    /// Leave `waker` to be woken by the next reader to depart.
+    ///
+    /// The caller must check for departed readers again after this returns, as readers that
+    /// departed before it did not see the waker. That check must read reader epochs with a
+    /// read-modify-write, see `reader_departed` for why.
+    fn wait_for_reader(&self, waker: &Waker) {
+        *self.writer.lock().unwrap_or_else(|e| e.into_inner()) = Some(waker.clone());
+        self.writer_waiting.store(true, Ordering::Relaxed);
+        // pairs with the fence a fresh reader issues after releasing its lock: either the reader
+        // sees the flag, or our subsequent check sees the lock released.
+        fence(Ordering::SeqCst);
+    }
+
+    /// Stop waiting for readers to depart.
+    fn stop_waiting_for_readers(&self) {
+        self.writer_waiting.store(false, Ordering::Relaxed);
+        self.writer.lock().unwrap_or_else(|e| e.into_inner()).take();
+    }
+
+    /// Wake the writer if it is polling for readers to depart.
+    ///
+    /// Must be called after the departing reader has bumped its epoch, released its pin, or
+    /// released its fresh lock and issued a `SeqCst` fence.
+    ///
+    /// The flag is checked with a plain load, and yet a writer that waits for this reader is
+    /// never left asleep. Say the writer set the flag and then checks for departed readers:
+    ///
+    ///  - the writer reads the reader's epoch with a read-modify-write. if that comes after the
+    ///    reader's bump, the writer sees the bump and does not go to sleep. otherwise, the bump
+    ///    reads from the writer's (Release) read-modify-write, and so acquires the flag along with
+    ///    it. either way, one of the two sees the other.
+    ///  - pins are only changed and checked under the pins lock. if the writer takes the lock
+    ///    after the reader released it, the writer sees the pin gone. otherwise, the reader's
+    ///    lock acquires the flag.
+    ///  - fresh readers release a lock that the writer only ever tries to take, which does not
+    ///    synchronize, so they fence instead, pairing with the fence in `wait_for_reader`.
+    ///
+    /// A reader may also see the flag cleared by another reader that took the waker first, or by
+    /// the writer once it stopped waiting. Either way, the writer has already been woken.
+    fn reader_departed(&self) {
+        if self.writer_waiting.load(Ordering::Relaxed)
+            && self.writer_waiting.swap(false, Ordering::Relaxed)
+        {
+            if let Some(waker) = self.writer.lock().unwrap_or_else(|e| e.into_inner()).take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+/// The copies pinned by [`OwnedReadGuard`]s, identified by the address of their [`Meta`].
+///
+/// Owned guards cannot register an epoch of their own, since they are created while their
+/// reader's epoch is pinned, and taking the epochs lock then could deadlock with a writer waiting
+/// for that very epoch. Instead, they record which copy they pin here, and the writer never
+/// holds this lock while it waits.
+type Pins = Arc<Mutex<slab::Slab<usize>>>;
+
+fn lock_pins(pins: &Pins) -> MutexGuard<'_, slab::Slab<usize>> {
+    pins.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// A summary of the changes exposed by a call to [`WriteHandle::publish`].
+///
+/// Deltas are produced by [`Apply::summarize`] and handed out to readers through
+/// [`ReadHandle::last_delta`] and [`ReadGuard::delta`], which downcast them back to the concrete
+/// type the operator produced.
+pub type Delta = std::sync::Arc<dyn std::any::Any + Send + Sync>;
+
+/// One of the two copies of the data, along with what the writer published alongside it.
+///
+/// Readers only ever reach a `Slot` through the same pointer (and under the same epoch) as the
+/// data itself, so the metadata is always consistent with the `T` next to it.
+///
+/// The data comes first, so that a pointer to a `Slot` is also a pointer to its data.
+#[repr(C)]
+pub(crate) struct Slot<T> {
+    pub(crate) data: T,
+    pub(crate) meta: Meta,
+}
+
+impl<T> Slot<T> {
+    pub(crate) fn new(data: T) -> Self {
+        Self {
+            data,
+            meta: Meta::default(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Meta {
+    pub(crate) delta: Option<Delta>,
+    /// The number of publishes that had happened when this copy was exposed to readers.
+    pub(crate) generation: u64,
+    /// When this copy was exposed to readers, or created if it has not been published yet.
+    pub(crate) published_at: std::time::Instant,
+}
+
+impl Default for Meta {
+    fn default() -> Self {
+        Self {
+            delta: None,
+            generation: 0,
+            published_at: std::time::Instant::now(),
+        }
+    }
+}
+
 mod write;
-pub use crate::write::WriteHandle;
+pub use crate::write::{
+    ApplyOrder, BatchTrend, LocalWriteHandle, PendingPublish, PublishFuture, PublishGroup,
+    Rejected, Transaction, UnsyncWriteHandle, WriteHandle,
+};
+#[cfg(feature = "metrics")]
+pub use crate::write::{HighWaterMarks, PhaseTimings};
+
+mod len;
+pub use crate::len::HasLen;
+
+mod slices;
+pub use crate::slices::AsSlices;
+
+mod memo;
+pub use crate::memo::MemoizedRead;
+
+mod oplog;
+#[cfg(feature = "file-oplog")]
+pub use crate::oplog::{FileOplog, Spill};
+pub use crate::oplog::{OplogStore, SharedOplog, ShrinkPolicy};
+
+mod reclaim;
+pub use crate::reclaim::{Reclaim, Reclaimed};
+
+mod expiring;
+pub use crate::expiring::Expiring;
+
+mod with_prev;
+pub use crate::with_prev::{ApplyWithPrev, WithPrev};
+
+mod op_id;
+pub use crate::op_id::{ApplyWithId, Identified};
+
+mod derived;
+pub use crate::derived::DerivedCache;
+
+mod diff;
+pub use crate::diff::DiffEntry;
+
+mod partition;
+#[cfg(feature = "parallel-apply")]
+pub use crate::partition::{ApplyPartitioned, Partitioned};
+
+mod wait;
+pub use crate::wait::{ExponentialBackoff, SpinThenYield, WaitStrategy};
 
 mod read;
-pub use crate::read::{ReadGuard, ReadHandle, ReadHandleFactory};
+#[cfg(feature = "rkyv")]
+pub use crate::read::InvalidArchive;
+#[cfg(feature = "arc-swap-compat")]
+pub use crate::read::{ArcGuard, ArcLoader};
+pub use crate::read::{
+    BatchGuard, CowGuard, FreshGuard, OwnedReadGuard, ReadGuard, ReadHandle, ReadHandleFactory,
+    SendReadGuard, StaleToken, WaitForGeneration, WriterGone,
+};
 
 /// Types that can incorporate operations of type `O`.
 ///
@@ -200,15 +438,18 @@ pub use crate::read::{ReadGuard, ReadHandle, ReadHandleFactory};
 pub trait Apply<T, A>: Sized {
     /// Apply `O` to the first of the two copies.
     ///
-    /// `other` is a reference to the other copy of the data, which has seen all operations up
-    /// until the previous call to [`WriteHandle::publish`]. That is, `other` is one "publish
-    /// cycle" behind.
+    /// `second` is a reference to the other copy of the data, which has seen all operations up
+    /// until the previous call to [`WriteHandle::publish`]. That is, `second` is one "publish
+    /// cycle" behind: it is exactly the data readers see until the publish that exposes this
+    /// `O`. Since the other copy will have moved on by the time this `O` is applied to it, an
+    /// implementation that computes its update from `second` must remember what it computed, see
+    /// [`ApplyWithPrev`].
     fn apply_first(&mut self, first: &mut T, second: &T, auxiliary: &mut A);
 
     /// Apply `O` to the second of the two copies.
     ///
-    /// `other` is a reference to the other copy of the data, which has seen all operations up to
-    /// the call to [`WriteHandle::publish`] that initially exposed this `O`. That is, `other` is
+    /// `first` is a reference to the other copy of the data, which has seen all operations up to
+    /// the call to [`WriteHandle::publish`] that initially exposed this `O`. That is, `first` is
     /// one "publish cycle" ahead.
     ///
     /// Note that this method should modify the underlying data in _exactly_ the same way as
@@ -221,6 +462,234 @@ pub trait Apply<T, A>: Sized {
     fn apply_second(mut self, first: &T, second: &mut T, auxiliary: &mut A) {
         Self::apply_first(&mut self, second, first, auxiliary);
     }
+
+    /// Apply `O` to the first of the two copies, deferring drops of what it removes.
+    ///
+    /// Called instead of `apply_first` during [`WriteHandle::publish`]. Values that the operation
+    /// removes from `first` can be handed to `reclaim` rather than dropped inline, see
+    /// [`Reclaim`]. This is mostly useful for map-like data, where a removal may drop an
+    /// arbitrarily large value.
+    ///
+    /// Defaults to calling `apply_first`.
+    fn apply_first_deferred(
+        &mut self,
+        first: &mut T,
+        second: &T,
+        auxiliary: &mut A,
+        reclaim: &mut Reclaim,
+    ) {
+        let _ = reclaim;
+        self.apply_first(first, second, auxiliary);
+    }
+
+    /// Apply `O` to the second of the two copies, deferring drops of what it removes.
+    ///
+    /// Called instead of `apply_second` during [`WriteHandle::publish`]. See
+    /// [`apply_first_deferred`](Self::apply_first_deferred).
+    ///
+    /// Defaults to calling `apply_second`.
+    fn apply_second_deferred(
+        self,
+        first: &T,
+        second: &mut T,
+        auxiliary: &mut A,
+        reclaim: &mut Reclaim,
+    ) {
+        let _ = reclaim;
+        self.apply_second(first, second, auxiliary);
+    }
+
+    /// Returns true if `next` can be applied in the same run as this operation.
+    ///
+    /// During [`WriteHandle::publish`], consecutive operations for which this returns true are
+    /// grouped into runs, and each run is handed to
+    /// [`apply_first_homogeneous`](Self::apply_first_homogeneous) and
+    /// [`apply_second_homogeneous`](Self::apply_second_homogeneous) in one go. This is meant for
+    /// operations that do element-wise math on numeric data: if a run only holds one variant of
+    /// the operation, the batch hooks can apply it in a tight loop that the compiler can
+    /// vectorize, rather than dispatching on every single operation.
+    ///
+    /// Runs are only ever split, never merged, so the batch hooks must produce the same result as
+    /// applying the operations of a run one by one. The in-memory oplog, for example, splits a run
+    /// where its ring buffer wraps around.
+    ///
+    /// Defaults to `false`, in which case every operation is applied on its own.
+    fn is_homogeneous_with(&self, next: &Self) -> bool {
+        let _ = next;
+        false
+    }
+
+    /// Returns true if this operation and `other` may not be reordered with respect to each
+    /// other.
+    ///
+    /// Before applying a batch of newly appended operations, [`WriteHandle::publish`] moves
+    /// operations towards earlier operations they are [homogeneous](Self::is_homogeneous_with)
+    /// with, so that longer runs can be handed to the batch hooks. An operation only ever moves
+    /// past operations that it does not conflict with, so the relative order of conflicting
+    /// operations is preserved. Two operations must therefore be reported as conflicting unless
+    /// applying them in either order leaves the data, the auxiliary and any
+    /// [summary](Self::summarize) in the same state. The check is made in both directions, so it
+    /// is enough for either operation to report the conflict.
+    ///
+    /// Reordering only happens with oplog stores that can hand out the pending operations as a
+    /// single slice, such as the default in-memory one, and costs up to quadratic time in the
+    /// number of operations of a batch.
+    ///
+    /// Defaults to `true`, in which case operations are never reordered.
+    fn conflicts_with(&self, other: &Self) -> bool {
+        let _ = other;
+        true
+    }
+
+    /// Returns true if applying this operation makes applying `earlier` beforehand pointless.
+    ///
+    /// Only consulted when the writer applies operations in [`ApplyOrder::Lifo`], where an
+    /// operation is skipped if a later operation that has already been applied supersedes it.
+    /// This must only return true if applying `earlier` and then this operation leaves the data
+    /// and the auxiliary in the same state as applying this operation alone, as is the case for
+    /// two last-writer-wins assignments to the same key.
+    ///
+    /// Defaults to `false`, in which case no operation is skipped.
+    fn supersedes(&self, earlier: &Self) -> bool {
+        let _ = earlier;
+        false
+    }
+
+    /// Apply a run of homogeneous operations to the first of the two copies.
+    ///
+    /// Called instead of [`apply_first_deferred`](Self::apply_first_deferred) for every run of
+    /// operations grouped by [`is_homogeneous_with`](Self::is_homogeneous_with).
+    ///
+    /// Defaults to calling `apply_first_deferred` for each operation in `ops`.
+    fn apply_first_homogeneous(
+        ops: &mut [Self],
+        first: &mut T,
+        second: &T,
+        auxiliary: &mut A,
+        reclaim: &mut Reclaim,
+    ) {
+        for op in ops {
+            op.apply_first_deferred(first, second, auxiliary, reclaim);
+        }
+    }
+
+    /// Apply a run of homogeneous operations to the second of the two copies.
+    ///
+    /// Called instead of [`apply_second_deferred`](Self::apply_second_deferred) for every run of
+    /// operations grouped by [`is_homogeneous_with`](Self::is_homogeneous_with). The runs are the
+    /// same ones `apply_first_homogeneous` saw, unless the oplog store split them differently.
+    /// `ops` can be iterated to take ownership of the operations, or read through
+    /// [`Drain::as_slice`](std::vec::Drain::as_slice).
+    ///
+    /// Defaults to calling `apply_second_deferred` for each operation in `ops`.
+    fn apply_second_homogeneous(
+        ops: std::vec::Drain<'_, Self>,
+        first: &T,
+        second: &mut T,
+        auxiliary: &mut A,
+        reclaim: &mut Reclaim,
+    ) {
+        for op in ops {
+            op.apply_second_deferred(first, second, auxiliary, reclaim);
+        }
+    }
+
+    /// Check whether this operation may be published.
+    ///
+    /// Called for every pending operation by [`WriteHandle::try_publish`], before any of them is
+    /// applied. `current` is the data readers currently see, and `auxiliary` is the auxiliary
+    /// data. Returning an error aborts the publish, and leaves the operations pending. This is the
+    /// place to check operations against state that the writer does not control, like a limit
+    /// that is managed elsewhere. Note that `current` does not include the effects of the pending
+    /// operations before this one, see `try_publish` for how to check their combined effect.
+    ///
+    /// Defaults to `Ok(())`.
+    fn validate(
+        &self,
+        current: &T,
+        auxiliary: &A,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let _ = (current, auxiliary);
+        Ok(())
+    }
+
+    /// Returns true if applying this operation to `current` would not change it.
+    ///
+    /// [`WriteHandle::append`] drops redundant operations rather than adding them to the oplog,
+    /// which keeps idempotent operations (like "ensure this key exists") from piling up. The
+    /// writer can only tell whether an operation is redundant if it knows the state the operation
+    /// would be applied to, so this is only consulted while there are no operations pending
+    /// publication. `current` is then the data readers currently see.
+    ///
+    /// This means that only the first operation appended after a publish is ever dropped.
+    /// Appending the same operation twice in a row keeps the second one, since the first one has
+    /// not been applied to anything yet when the second one arrives.
+    ///
+    /// Defaults to `false`.
+    fn is_redundant(&self, current: &T) -> bool {
+        let _ = current;
+        false
+    }
+
+    /// Restore invariants of the first copy after a batch of operations has been applied to it.
+    ///
+    /// Called once per call to [`WriteHandle::publish`], after `apply_first` has been called for
+    /// every operation in the batch, and only if the batch was not empty. This is the place for
+    /// work that would be wasteful to repeat after every operation, like recomputing a cached
+    /// total or re-sorting a collection. [`WriteHandle::current`] applies operations without
+    /// finalizing them, so this still runs exactly once per batch when it is used.
+    ///
+    /// Defaults to doing nothing.
+    fn finalize_first(first: &mut T, second: &T, auxiliary: &mut A) {
+        let _ = (first, second, auxiliary);
+    }
+
+    /// Restore invariants of the second copy after a batch of operations has been applied to it.
+    ///
+    /// Called once per call to [`WriteHandle::publish`], after `apply_second` has been called for
+    /// every operation of the batch that the first copy was finalized for in the previous
+    /// publish. As with `apply_second`, this must leave the second copy in _exactly_ the same
+    /// state as `finalize_first` left the first one.
+    ///
+    /// Defaults to calling `finalize_first`.
+    fn finalize_second(first: &T, second: &mut T, auxiliary: &mut A) {
+        Self::finalize_first(second, first, auxiliary);
+    }
+
+    /// Describe where two copies of the data differ.
+    ///
+    /// Only called by checked write handles (see [`new_checked`] and [`new_checked_hash`]) once
+    /// they have found that the copies diverged. The entries are included in the message of the
+    /// resulting panic, which turns a bare "the copies diverged" into a pointer at what exactly
+    /// differs.
+    ///
+    /// Defaults to returning no entries, in which case the panic only reports that the copies
+    /// differ.
+    fn diff(first: &T, second: &T) -> Vec<DiffEntry> {
+        let _ = (first, second);
+        Vec::new()
+    }
+
+    /// Summarize the operations that a call to [`WriteHandle::publish`] is about to expose.
+    ///
+    /// `ops` yields the newly published operations in the order they were appended, after they
+    /// have been applied to the first copy. The returned [`Delta`] is published together with the
+    /// data, so a reader that observes the new data also observes the matching delta (see
+    /// [`ReadGuard::delta`]). This lets readers that maintain derived views update them
+    /// incrementally rather than recomputing them from scratch.
+    ///
+    /// If the operation type is `Clone`, the simplest summary is the list of operations itself:
+    /// `Some(Arc::new(ops.cloned().collect::<Vec<_>>()))`.
+    ///
+    /// Defaults to `None`, in which case no delta is published.
+    fn summarize<'a, I>(ops: I, auxiliary: &A) -> Option<Delta>
+    where
+        I: Iterator<Item = &'a Self>,
+        Self: 'a,
+    {
+        let _ = (ops, auxiliary);
+        None
+    }
 }
 
 /// Construct a new write handle from an initial swapping value and an auxiliary value.
@@ -234,6 +703,291 @@ where
     let epochs = Default::default();
 
     let r = ReadHandle::new(init.clone(), Arc::clone(&epochs));
-    let w = WriteHandle::new(init, epochs, r, auxiliary);
+    WriteHandle::new(init, epochs, r, auxiliary, Default::default())
+}
+
+/// Construct a new write handle whose auxiliary value is derived from the initial data.
+///
+/// This behaves exactly like [`new`], except that the auxiliary value is built by `make_aux`
+/// from a reference to `init`. This is convenient for auxiliaries like an index over the data,
+/// which must start out consistent with it.
+///
+/// # Examples
+///
+/// ```
+/// use reft_light::Apply;
+/// use std::collections::HashSet;
+///
+/// // the auxiliary tracks which values are present, so duplicates can be skipped
+/// struct Insert(u32);
+/// impl Apply<Vec<u32>, HashSet<u32>> for Insert {
+///     fn apply_first(&mut self, first: &mut Vec<u32>, _: &Vec<u32>, seen: &mut HashSet<u32>) {
+///         if seen.insert(self.0) {
+///             first.push(self.0);
+///         }
+///     }
+///
+///     fn apply_second(self, _: &Vec<u32>, second: &mut Vec<u32>, _: &mut HashSet<u32>) {
+///         if !second.contains(&self.0) {
+///             second.push(self.0);
+///         }
+///     }
+/// }
+///
+/// let mut w = reft_light::new_with_aux_from::<Insert, _, _, _>(vec![1, 2], |init| {
+///     init.iter().copied().collect()
+/// });
+/// assert_eq!(w.auxiliary().len(), 2);
+///
+/// w.append(Insert(2)).append(Insert(3)).publish();
+/// assert_eq!(*w.enter().unwrap(), [1, 2, 3]);
+/// ```
+pub fn new_with_aux_from<O, T, A, F>(init: T, make_aux: F) -> WriteHandle<O, T, A>
+where
+    O: Apply<T, A>,
+    T: Clone,
+    F: FnOnce(&T) -> A,
+{
+    let auxiliary = make_aux(&init);
+    new(init, auxiliary)
+}
+
+/// Construct a new write handle that keeps its operational log in the given store.
+///
+/// This behaves exactly like [`new`], except that operations are stored in `oplog` rather than in
+/// memory. See [`OplogStore`] for when that is useful.
+pub fn new_with_oplog<O, T, A, S>(init: T, auxiliary: A, oplog: S) -> WriteHandle<O, T, A, S>
+where
+    O: Apply<T, A>,
+    T: Clone,
+    S: OplogStore<O>,
+{
+    let epochs = Default::default();
+
+    let r = ReadHandle::new(init.clone(), Arc::clone(&epochs));
+    WriteHandle::new(init, epochs, r, auxiliary, oplog)
+}
+
+/// Construct a write handle in the middle of a publish, with its two copies at different
+/// frontiers of the operational log.
+///
+/// This behaves like [`new`], followed by appending `ops` and applying the first `applied` of
+/// them to the write copy. Readers see `read_copy`, and every operation in `ops` is pending,
+/// exactly as for a writer that had called [`WriteHandle::current`] after appending the first
+/// `applied` operations, and then appended the rest. The next publish exposes all of them,
+/// without applying the first `applied` operations to the write copy a second time.
+///
+/// This is meant for restoring a writer from persisted state that was captured while a publish
+/// was in flight: `read_copy` is the data readers saw, and `ops` are the operations that had not
+/// been published yet, in order. Unlike [`WriteHandle::extend`](Extend::extend), this does not
+/// drop [redundant](Apply::is_redundant) operations, so the oplog is restored exactly as
+/// persisted. Note that applying the first `applied` operations also applies their effects to
+/// `auxiliary`, so it should be the auxiliary as it was before they were applied.
+///
+/// # Panics
+///
+/// Panics if `applied` is greater than the number of operations in `ops`.
+///
+/// # Examples
+///
+/// ```
+/// use reft_light::Apply;
+///
+/// #[derive(Clone)]
+/// struct Push(u32);
+/// impl Apply<Vec<u32>, ()> for Push {
+///     fn apply_first(&mut self, first: &mut Vec<u32>, _: &Vec<u32>, _: &mut ()) {
+///         first.push(self.0);
+///     }
+/// }
+///
+/// let mut w = reft_light::restore_at_frontiers(vec![1, 2], vec![Push(3), Push(4), Push(5)], 2, ());
+/// let r = w.clone();
+/// assert_eq!(*r.enter().unwrap(), [1, 2]);
+/// assert!(w.has_pending_operations());
+///
+/// w.publish();
+/// assert_eq!(*r.enter().unwrap(), [1, 2, 3, 4, 5]);
+/// ```
+pub fn restore_at_frontiers<O, T, A>(
+    read_copy: T,
+    ops: Vec<O>,
+    applied: usize,
+    auxiliary: A,
+) -> WriteHandle<O, T, A>
+where
+    O: Apply<T, A>,
+    T: Clone,
+{
+    let mut w = new(read_copy, auxiliary);
+    w.restore_oplog(ops, applied);
     w
 }
+
+/// Construct a new left-right whose data is only ever read through its writer.
+///
+/// See [`LocalWriteHandle`] for when this is useful.
+pub fn new_local<O, T, A>(init: T, auxiliary: A) -> LocalWriteHandle<O, T, A>
+where
+    O: Apply<T, A>,
+    T: Clone,
+{
+    LocalWriteHandle::new(new(init, auxiliary))
+}
+
+/// Construct a new left-right that is only ever used from a single thread.
+///
+/// See [`UnsyncWriteHandle`] for when this is useful.
+pub fn new_unsync<O, T, A>(init: T, auxiliary: A) -> UnsyncWriteHandle<O, T, A>
+where
+    O: Apply<T, A>,
+    T: Clone,
+{
+    UnsyncWriteHandle::new(new(init, auxiliary))
+}
+
+/// Construct a new write handle whose readers can opt into seeing unpublished operations.
+///
+/// This behaves exactly like [`new`], except that [`ReadHandle::enter_fresh`] hands out the copy
+/// that has seen the most operations, rather than returning `None`. Fresh readers share a lock
+/// with the writer, which it takes whenever it applies operations or publishes, so only enable
+/// this if some readers need it. See `enter_fresh` for the tradeoffs.
+pub fn new_with_fresh_reads<O, T, A>(init: T, auxiliary: A) -> WriteHandle<O, T, A>
+where
+    O: Apply<T, A>,
+    T: Clone,
+{
+    new(init, auxiliary).fresh_reads()
+}
+
+/// Construct a new write handle that must not be dropped with unpublished operations.
+///
+/// This behaves exactly like [`new`], except for what happens when the [`WriteHandle`] is dropped
+/// while it still has [pending operations](WriteHandle::has_pending_operations). A regular handle
+/// publishes them, which means waiting for readers to depart once more during teardown. That is
+/// both easy to miss and a potential source of surprising hangs. A strict handle instead discards
+/// the unpublished operations and then panics, in release builds too, to flag the missing call to
+/// [`publish`](WriteHandle::publish) or [`flush`](WriteHandle::flush). The panic is skipped if the
+/// handle is dropped while the thread is already panicking. Note that dropping the handle still
+/// waits for readers to depart from the copy they are reading, since it is about to be freed.
+///
+/// [`WriteHandle::take`] is an explicit request for the fully up-to-date data, and so still applies
+/// pending operations.
+///
+/// # Examples
+///
+/// ```should_panic
+/// use reft_light::Apply;
+///
+/// struct Add(u64);
+/// impl Apply<u64, ()> for Add {
+///     fn apply_first(&mut self, first: &mut u64, _: &u64, _: &mut ()) {
+///         *first += self.0;
+///     }
+/// }
+///
+/// let mut w = reft_light::new_strict::<Add, _, _>(0, ());
+/// w.append(Add(1));
+/// // forgot to publish!
+/// drop(w);
+/// ```
+pub fn new_strict<O, T, A>(init: T, auxiliary: A) -> WriteHandle<O, T, A>
+where
+    O: Apply<T, A>,
+    T: Clone,
+{
+    new(init, auxiliary).strict_drop()
+}
+
+/// Construct a new write handle that applies operations to disjoint partitions in parallel.
+///
+/// This behaves exactly like [`new`], except that publishes apply operations through
+/// [`ApplyPartitioned`] rather than [`Apply`], spreading the partitions over several threads. See
+/// [`ApplyPartitioned`] for details. Only oplog stores that can hand out their operations as a
+/// slice support this, which includes the default one.
+///
+/// Only available with the `parallel-apply` feature, which needs Rust 1.73 or newer.
+#[cfg(feature = "parallel-apply")]
+pub fn new_partitioned<O, T, A>(init: T, auxiliary: A) -> WriteHandle<O, T, A>
+where
+    O: ApplyPartitioned<T, A>,
+    T: Clone + Partitioned,
+{
+    new(init, auxiliary).apply_partitioned()
+}
+
+/// Construct a new write handle that checks the two copies for divergence by comparing them.
+///
+/// This behaves exactly like [`new`], except that every call to [`WriteHandle::publish`] also
+/// compares both copies at the point where they have seen the same operations, and panics if they
+/// differ. A mismatch means that the [`Apply`] implementation is not deterministic (or that
+/// `apply_first` and `apply_second` disagree), and that the copies have drifted apart. The panic
+/// message includes the differences reported by [`Apply::diff`].
+///
+/// Comparing walks both copies on every publish, so this is meant for tests and debugging rather
+/// than for production use. See [`new_checked_hash`] for a cheaper check.
+///
+/// # Examples
+///
+/// ```should_panic
+/// use reft_light::{Apply, DiffEntry};
+///
+/// struct Set(usize, i32);
+/// impl Apply<Vec<i32>, ()> for Set {
+///     fn apply_first(&mut self, first: &mut Vec<i32>, _: &Vec<i32>, _: &mut ()) {
+///         first[self.0] = self.1;
+///     }
+///
+///     // deliberately broken: the second copy gets a different value
+///     fn apply_second(self, _: &Vec<i32>, second: &mut Vec<i32>, _: &mut ()) {
+///         second[self.0] = -self.1;
+///     }
+///
+///     fn diff(first: &Vec<i32>, second: &Vec<i32>) -> Vec<DiffEntry> {
+///         let pairs = first.iter().zip(second).enumerate();
+///         pairs
+///             .filter(|(_, (a, b))| a != b)
+///             .map(|(i, (a, b))| DiffEntry::new(format!("[{}]", i), a, b))
+///             .collect()
+///     }
+/// }
+///
+/// let mut w = reft_light::new_checked::<Set, _, _>(vec![0; 4], ());
+/// w.append(Set(2, 7)).publish();
+/// // panics with "... at [2]: 7 != -7"
+/// w.publish();
+/// ```
+pub fn new_checked<O, T, A>(init: T, auxiliary: A) -> WriteHandle<O, T, A>
+where
+    O: Apply<T, A>,
+    T: Clone + PartialEq,
+{
+    new(init, auxiliary).check_copies_with(|first, second| first == second)
+}
+
+/// Construct a new write handle that checks the two copies for divergence using their hashes.
+///
+/// This behaves exactly like [`new`], except that every call to [`WriteHandle::publish`] also
+/// hashes both copies at the point where they have seen the same operations, and panics if the
+/// hashes differ. A mismatch means that the [`Apply`] implementation is not deterministic (or that
+/// `apply_first` and `apply_second` disagree), and that the copies have drifted apart. The panic
+/// message includes the differences reported by [`Apply::diff`].
+///
+/// Hashing is considerably cheaper than comparing the copies element by element, but it has to
+/// walk both copies on every publish, so this is meant for tests and debugging rather than for
+/// production use. Both copies are hashed with the same fixed hasher, so the check never reports
+/// divergence for copies that are equal. It can, however, miss divergence in the (extremely rare)
+/// case that two different copies produce the same hash.
+pub fn new_checked_hash<O, T, A>(init: T, auxiliary: A) -> WriteHandle<O, T, A>
+where
+    O: Apply<T, A>,
+    T: Clone + Hash,
+{
+    fn hash<T: Hash>(t: &T) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        t.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    new(init, auxiliary).check_copies_with(|first, second| hash(first) == hash(second))
+}
@@ -0,0 +1,225 @@
+use crate::sync::{AtomicPtr, Ordering};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::ptr;
+
+/// A value with a lazily computed, memoized result that can be filled in through a shared
+/// reference.
+///
+/// Readers only ever get `&T` to the read copy, so it is tempting to put a `Cell` or `RefCell`
+/// cache inside `T` to memoize expensive reads. That does not work: those types are not `Sync`,
+/// and a `ReadHandle<T>` can only be sent to other threads if `T` is `Sync`. `MemoizedRead`
+/// provides the same convenience soundly, by publishing the cached result through an atomic
+/// pointer. The first reader to ask for the result computes it; concurrent readers may race to
+/// compute it as well, in which case exactly one result wins and the others are discarded.
+///
+/// The memoized result must be derived only from the wrapped value, and the wrapped value can
+/// only be changed through [`get_mut`](Self::get_mut), which clears the cache. Since the cache
+/// is not part of the value, the two copies of a left-right stay equal regardless of which of
+/// them have computed their result: `Clone`, `PartialEq`, and `Hash` all ignore it.
+///
+/// # Examples
+///
+/// ```
+/// use reft_light::{Apply, MemoizedRead};
+///
+/// struct Push(u64);
+/// impl Apply<MemoizedRead<Vec<u64>, u64>, ()> for Push {
+///     fn apply_first(
+///         &mut self,
+///         first: &mut MemoizedRead<Vec<u64>, u64>,
+///         _: &MemoizedRead<Vec<u64>, u64>,
+///         _: &mut (),
+///     ) {
+///         first.get_mut().push(self.0);
+///     }
+/// }
+///
+/// let mut w = reft_light::new::<Push, _, _>(MemoizedRead::new(vec![]), ());
+/// let r = w.clone();
+/// w.append(Push(1)).append(Push(2)).publish();
+///
+/// let sum = |v: &Vec<u64>| v.iter().sum::<u64>();
+/// let guard = r.enter().unwrap();
+/// assert_eq!(*guard.get_or_compute(sum), 3);
+/// // the second read is served from the cache
+/// assert_eq!(guard.cached(), Some(&3));
+/// drop(guard);
+///
+/// // the next publish replaces the copy readers see, and its cache starts out empty
+/// w.append(Push(3)).publish();
+/// let guard = r.enter().unwrap();
+/// assert_eq!(guard.cached(), None);
+/// assert_eq!(*guard.get_or_compute(sum), 6);
+/// ```
+pub struct MemoizedRead<T, V> {
+    value: T,
+    cache: AtomicPtr<V>,
+    // we own the boxed V behind `cache`.
+    _cache: PhantomData<Box<V>>,
+}
+
+// safety: the cached V is owned by us, so moving us moves it along.
+unsafe impl<T, V> Send for MemoizedRead<T, V>
+where
+    T: Send,
+    V: Send,
+{
+}
+
+// safety: sharing us hands out `&V` to every thread, and may compute (and so allocate) the V on
+// any of them, while it is freed by whichever thread owns us at the time.
+unsafe impl<T, V> Sync for MemoizedRead<T, V>
+where
+    T: Sync,
+    V: Send + Sync,
+{
+}
+
+impl<T, V> MemoizedRead<T, V> {
+    /// Wrap `value` with an empty cache.
+    pub fn new(value: T) -> Self {
+        Self {
+            value,
+            cache: AtomicPtr::new(ptr::null_mut()),
+            _cache: PhantomData,
+        }
+    }
+
+    /// Returns the memoized result, computing it with `f` if it has not been computed yet.
+    ///
+    /// If several threads call this concurrently on an empty cache, each of them may run `f`,
+    /// but all of them return the same result.
+    pub fn get_or_compute<F>(&self, f: F) -> &V
+    where
+        F: FnOnce(&T) -> V,
+    {
+        if let Some(v) = self.cached() {
+            return v;
+        }
+
+        let new = Box::into_raw(Box::new(f(&self.value)));
+        match self
+            .cache
+            .compare_exchange(ptr::null_mut(), new, Ordering::AcqRel, Ordering::Acquire)
+        {
+            // safety: we just published the box, and it lives until we have &mut self.
+            Ok(_) => unsafe { &*new },
+            Err(winner) => {
+                // safety: we never shared `new`, so we still own it.
+                drop(unsafe { Box::from_raw(new) });
+                // safety: the winner lives until we have &mut self.
+                unsafe { &*winner }
+            }
+        }
+    }
+
+    /// Returns the memoized result, if it has been computed.
+    pub fn cached(&self) -> Option<&V> {
+        // safety: the cache is only ever cleared through &mut self.
+        unsafe { self.cache.load(Ordering::Acquire).as_ref() }
+    }
+
+    /// Returns a mutable reference to the wrapped value, clearing the cache.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.invalidate();
+        &mut self.value
+    }
+
+    /// Clears the cache.
+    pub fn invalidate(&mut self) {
+        let old = self.cache.swap(ptr::null_mut(), Ordering::AcqRel);
+        if !old.is_null() {
+            // safety: we have &mut self, so no references to the cached value remain.
+            drop(unsafe { Box::from_raw(old) });
+        }
+    }
+
+    /// Unwraps the value, discarding the cache.
+    pub fn into_inner(mut self) -> T {
+        self.invalidate();
+        let this = std::mem::ManuallyDrop::new(self);
+        // safety: the cache is empty, and we never touch `this` again.
+        unsafe { ptr::read(&this.value) }
+    }
+}
+
+impl<T, V> Drop for MemoizedRead<T, V> {
+    fn drop(&mut self) {
+        self.invalidate();
+    }
+}
+
+impl<T, V> Deref for MemoizedRead<T, V> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl<T, V> Clone for MemoizedRead<T, V>
+where
+    T: Clone,
+{
+    fn clone(&self) -> Self {
+        Self::new(self.value.clone())
+    }
+}
+
+impl<T, V> PartialEq for MemoizedRead<T, V>
+where
+    T: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl<T, V> Eq for MemoizedRead<T, V> where T: Eq {}
+
+impl<T, V> Hash for MemoizedRead<T, V>
+where
+    T: Hash,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.value.hash(state)
+    }
+}
+
+impl<T, V> fmt::Debug for MemoizedRead<T, V>
+where
+    T: fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoizedRead")
+            .field("value", &self.value)
+            .field("cached", &self.cached())
+            .finish()
+    }
+}
+
+/// `MemoizedRead` can be shared across threads, and so be read through a `ReadHandle` on another
+/// thread:
+///
+/// ```
+/// use reft_light::{MemoizedRead, ReadHandle};
+///
+/// fn is_send<T: Send>() {}
+///
+/// is_send::<ReadHandle<MemoizedRead<Vec<u64>, u64>>>()
+/// ```
+///
+/// But only if the cached value can be shared, too:
+///
+/// ```compile_fail
+/// use reft_light::{MemoizedRead, ReadHandle};
+///
+/// fn is_send<T: Send>() {}
+///
+/// is_send::<ReadHandle<MemoizedRead<Vec<u64>, std::cell::Cell<u64>>>>()
+/// ```
+#[allow(dead_code)]
+struct CheckMemoizedReadSync;
@@ -0,0 +1,92 @@
+use crate::Apply;
+
+/// Operations that are told the id the writer assigned to them.
+///
+/// Operations appended with [`WriteHandle::append_with_id`](crate::WriteHandle::append_with_id)
+/// are assigned an id, which is handed to both of their applications. Ids start at 0, increase
+/// by one with every append, and are never reused for the lifetime of the
+/// [`WriteHandle`](crate::WriteHandle), which makes them suitable for correlating operations with
+/// external event logs. Since every operation is applied once to each copy, an external consumer
+/// that observes the applications sees each id twice, and can use it to deduplicate.
+///
+/// # Examples
+///
+/// ```
+/// use reft_light::ApplyWithId;
+///
+/// // the auxiliary logs the id of every application
+/// struct Add(u64);
+/// impl ApplyWithId<u64, Vec<u64>> for Add {
+///     fn apply_first_with_id(&mut self, id: u64, first: &mut u64, _: &u64, log: &mut Vec<u64>) {
+///         *first += self.0;
+///         log.push(id);
+///     }
+/// }
+///
+/// let mut w = reft_light::new(0, vec![]);
+/// let a = w.append_with_id(Add(1));
+/// let b = w.append_with_id(Add(2));
+/// assert!(b > a);
+///
+/// w.publish();
+/// w.publish();
+/// assert_eq!(*w.auxiliary(), [a, b, a, b]);
+/// ```
+pub trait ApplyWithId<T, A>: Sized {
+    /// Apply the operation with the given id to the first of the two copies.
+    ///
+    /// See [`Apply::apply_first`].
+    fn apply_first_with_id(&mut self, id: u64, first: &mut T, second: &T, auxiliary: &mut A);
+
+    /// Apply the operation with the given id to the second of the two copies.
+    ///
+    /// `id` is the same one that `apply_first_with_id` was called with. See
+    /// [`Apply::apply_second`].
+    ///
+    /// Defaults to calling `apply_first_with_id`.
+    fn apply_second_with_id(mut self, id: u64, first: &T, second: &mut T, auxiliary: &mut A) {
+        Self::apply_first_with_id(&mut self, id, second, first, auxiliary);
+    }
+}
+
+/// An operation along with the id the writer assigned to it.
+///
+/// Produced by [`WriteHandle::append_with_id`](crate::WriteHandle::append_with_id), which is the
+/// only way to create one, so the ids in an oplog are always the ones the writer assigned. See
+/// [`ApplyWithId`].
+#[derive(Debug)]
+pub struct Identified<O> {
+    id: u64,
+    op: O,
+}
+
+impl<O> Identified<O> {
+    pub(crate) fn new(id: u64, op: O) -> Self {
+        Self { id, op }
+    }
+
+    /// Returns the id of the operation.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Returns the wrapped operation.
+    pub fn into_inner(self) -> O {
+        self.op
+    }
+}
+
+impl<O, T, A> Apply<T, A> for Identified<O>
+where
+    O: ApplyWithId<T, A>,
+{
+    fn apply_first(&mut self, first: &mut T, second: &T, auxiliary: &mut A) {
+        self.op
+            .apply_first_with_id(self.id, first, second, auxiliary);
+    }
+
+    fn apply_second(self, first: &T, second: &mut T, auxiliary: &mut A) {
+        self.op
+            .apply_second_with_id(self.id, first, second, auxiliary);
+    }
+}
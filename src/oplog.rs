@@ -0,0 +1,261 @@
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::sync::Arc;
+
+mod shared;
+pub use shared::SharedOplog;
+
+#[cfg(feature = "file-oplog")]
+mod file;
+#[cfg(feature = "file-oplog")]
+pub use file::{FileOplog, Spill};
+
+/// Storage for the operational log of a [`WriteHandle`](crate::WriteHandle).
+///
+/// The oplog holds every operation that has not yet been applied to both copies of the data. By
+/// default, it is kept in memory as a [`VecDeque`]. If a writer appends a very large number of
+/// operations between publishes, say during a bulk import, an implementation of this trait can
+/// keep them elsewhere instead, such as in the `FileOplog` provided with the `file-oplog`
+/// feature. Use [`new_with_oplog`](crate::new_with_oplog) to construct a left-right with a
+/// different store.
+///
+/// The log is only ever appended to at the back and drained from the front. Operations are
+/// indexed from the front of the log, starting at 0.
+pub trait OplogStore<O> {
+    /// Returns the number of operations in the log.
+    fn len(&self) -> usize;
+
+    /// Returns true if the log holds no operations.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Appends an operation to the back of the log.
+    fn push_back(&mut self, op: O);
+
+    /// Inserts an operation at position `index` of the log, shifting all operations after it
+    /// back.
+    ///
+    /// Defaults to draining the whole log and pushing it back with the operation in place, which
+    /// works for any store, but costs a full pass over the log. Stores that support insertion
+    /// natively should override this.
+    fn insert(&mut self, index: usize, op: O) {
+        let mut ops = Vec::with_capacity(self.len() + 1);
+        self.drain_prefix(self.len(), |op| ops.push(op));
+        ops.insert(index, op);
+        for op in ops {
+            self.push_back(op);
+        }
+    }
+
+    /// Appends the operations in `ops[range]` to the back of the log.
+    ///
+    /// Defaults to cloning each of them in with `push_back`. Stores that can refer to the shared
+    /// buffer instead, like [`SharedOplog`], override this.
+    fn extend_shared(&mut self, ops: &Arc<Vec<O>>, range: Range<usize>)
+    where
+        O: Clone,
+    {
+        for op in &ops[range] {
+            self.push_back(op.clone());
+        }
+    }
+
+    /// Removes the first `end` operations from the log, passing each of them to `f` in order.
+    fn drain_prefix<F>(&mut self, end: usize, f: F)
+    where
+        F: FnMut(O);
+
+    /// Calls `f` with each operation from index `start` onwards, in order.
+    ///
+    /// Any changes `f` makes to the operations must be kept.
+    fn for_each_from<F>(&mut self, start: usize, f: F)
+    where
+        F: FnMut(&mut O);
+
+    /// Calls `f` with consecutive runs of the operations from index `start` onwards, in order.
+    ///
+    /// Neighbouring operations `a` and `b` may only end up in the same run if `same_run(a, b)`
+    /// returns true, but stores are free to split runs further. Any changes `f` makes to the
+    /// operations must be kept.
+    ///
+    /// Defaults to calling `f` with one operation at a time through `for_each_from`.
+    fn for_each_run_from<R, F>(&mut self, start: usize, same_run: R, mut f: F)
+    where
+        R: FnMut(&O, &O) -> bool,
+        F: FnMut(&mut [O]),
+    {
+        let _ = same_run;
+        self.for_each_from(start, |op| f(std::slice::from_mut(op)));
+    }
+
+    /// Returns the operations from index `start` onwards as a single mutable slice, if the store
+    /// can provide one.
+    ///
+    /// The writer uses this to reorder independent operations, see
+    /// [`Apply::conflicts_with`](crate::Apply::conflicts_with). Any changes made through the
+    /// slice, including to the order of the operations, must be kept.
+    ///
+    /// Defaults to `None`, in which case operations are never reordered.
+    fn as_mut_slice_from(&mut self, start: usize) -> Option<&mut [O]> {
+        let _ = start;
+        None
+    }
+
+    /// Calls `f` with an iterator over the operations from index `start` onwards, in order.
+    fn with_iter_from<R, F>(&self, start: usize, f: F) -> R
+    where
+        F: FnOnce(&mut dyn Iterator<Item = &O>) -> R;
+
+    /// Release memory held on to after operations were drained, as `policy` asks.
+    ///
+    /// Called after every drain. Stores that manage their own memory can ignore this, which is
+    /// what the default implementation does.
+    fn shrink(&mut self, policy: ShrinkPolicy) {
+        let _ = policy;
+    }
+}
+
+/// What a [`WriteHandle`](crate::WriteHandle) does with the spare capacity of its oplog after
+/// draining operations during a publish.
+///
+/// The in-memory oplog grows to hold every operation appended between two publishes, and keeps
+/// that capacity after the operations are drained. A single burst of appends can thus pin a large
+/// allocation for the lifetime of the writer. The ring buffer of a [`VecDeque`] may also wrap
+/// around, which splits iteration over the log in two.
+///
+/// Set it with [`WriteHandle::set_shrink_policy`](crate::WriteHandle::set_shrink_policy).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShrinkPolicy {
+    /// Keep all spare capacity. This is the default.
+    Never,
+    /// Shrink the capacity down to the length once it exceeds `ratio` times the length.
+    Shrink {
+        /// How many times larger than the length the capacity may grow.
+        ratio: usize,
+    },
+    /// Like [`Shrink`](Self::Shrink), but also move the operations into one contiguous block
+    /// whenever the log is shrunk, so that replaying them walks memory in order.
+    Contiguous {
+        /// How many times larger than the length the capacity may grow.
+        ratio: usize,
+    },
+}
+
+// `#[default]` on enum variants needs a newer compiler than the crate supports
+#[allow(clippy::derivable_impls)]
+impl Default for ShrinkPolicy {
+    fn default() -> Self {
+        ShrinkPolicy::Never
+    }
+}
+
+impl<O> OplogStore<O> for VecDeque<O> {
+    fn len(&self) -> usize {
+        VecDeque::len(self)
+    }
+
+    fn push_back(&mut self, op: O) {
+        VecDeque::push_back(self, op)
+    }
+
+    fn insert(&mut self, index: usize, op: O) {
+        VecDeque::insert(self, index, op)
+    }
+
+    fn drain_prefix<F>(&mut self, end: usize, f: F)
+    where
+        F: FnMut(O),
+    {
+        self.drain(..end).for_each(f)
+    }
+
+    fn for_each_from<F>(&mut self, start: usize, f: F)
+    where
+        F: FnMut(&mut O),
+    {
+        let (front, back) = slices_from_mut(self, start);
+        front.iter_mut().chain(back).for_each(f)
+    }
+
+    fn for_each_run_from<R, F>(&mut self, start: usize, mut same_run: R, mut f: F)
+    where
+        R: FnMut(&O, &O) -> bool,
+        F: FnMut(&mut [O]),
+    {
+        let (front, back) = slices_from_mut(self, start);
+        let mut runs = |mut ops: &mut [O]| {
+            while !ops.is_empty() {
+                let len = 1 + ops
+                    .windows(2)
+                    .take_while(|pair| same_run(&pair[0], &pair[1]))
+                    .count();
+                let (run, rest) = ops.split_at_mut(len);
+                f(run);
+                ops = rest;
+            }
+        };
+        // a run that wraps around the end of the ring buffer is split in two
+        runs(front);
+        runs(back);
+    }
+
+    fn as_mut_slice_from(&mut self, start: usize) -> Option<&mut [O]> {
+        if !self.as_slices().1.is_empty() {
+            rebuild(self);
+        }
+        Some(&mut self.as_mut_slices().0[start..])
+    }
+
+    fn with_iter_from<R, F>(&self, start: usize, f: F) -> R
+    where
+        F: FnOnce(&mut dyn Iterator<Item = &O>) -> R,
+    {
+        let (front, back) = slices_from(self, start);
+        f(&mut front.iter().chain(back))
+    }
+
+    fn shrink(&mut self, policy: ShrinkPolicy) {
+        let (ratio, contiguous) = match policy {
+            ShrinkPolicy::Never => return,
+            ShrinkPolicy::Shrink { ratio } => (ratio, false),
+            ShrinkPolicy::Contiguous { ratio } => (ratio, true),
+        };
+        if self.capacity() <= self.len().saturating_mul(ratio) {
+            return;
+        }
+        if contiguous {
+            // this leaves no spare capacity behind either
+            rebuild(self);
+        } else {
+            self.shrink_to_fit();
+        }
+    }
+}
+
+/// Move the operations into a fresh deque, which holds them in one contiguous block, with no
+/// spare capacity.
+fn rebuild<O>(ops: &mut VecDeque<O>) {
+    let moved: Vec<O> = ops.drain(..).collect();
+    *ops = moved.into();
+}
+
+/// The two halves of the ring buffer of `ops`, starting at index `start`.
+fn slices_from<O>(ops: &VecDeque<O>, start: usize) -> (&[O], &[O]) {
+    let (front, back) = ops.as_slices();
+    if start < front.len() {
+        (&front[start..], back)
+    } else {
+        (&[], &back[start - front.len()..])
+    }
+}
+
+/// Like `slices_from`, but for mutable access.
+fn slices_from_mut<O>(ops: &mut VecDeque<O>, start: usize) -> (&mut [O], &mut [O]) {
+    let (front, back) = ops.as_mut_slices();
+    if start < front.len() {
+        (&mut front[start..], back)
+    } else {
+        (&mut [], &mut back[start - front.len()..])
+    }
+}
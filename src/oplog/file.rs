@@ -0,0 +1,263 @@
+use super::OplogStore;
+use std::cell::RefCell;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::marker::PhantomData;
+use std::path::Path;
+
+/// Operations that [`FileOplog`] can write out to a file and read back in.
+pub trait Spill: Sized {
+    /// Appends an encoding of `self` to `buf`.
+    fn encode(&self, buf: &mut Vec<u8>);
+
+    /// Decodes an operation from the bytes produced by [`encode`](Self::encode).
+    fn decode(bytes: &[u8]) -> Self;
+}
+
+/// Appended records are written out once this many bytes have accumulated.
+const FLUSH_AT: usize = 64 * 1024;
+
+/// An [`OplogStore`] that keeps operations in a file rather than in memory.
+///
+/// Each operation is encoded with [`Spill`] and appended to the file, so the oplog only takes up
+/// a few bytes of memory per operation no matter how large the operations are. Applying an
+/// operation to the first copy may change it, so operations are re-encoded and appended to the
+/// file again when they are published. Space in the file is reclaimed as operations are drained.
+///
+/// # Panics
+///
+/// The [`OplogStore`] methods panic if reading from or writing to the file fails.
+///
+/// # Examples
+///
+/// ```
+/// use reft_light::{Apply, FileOplog, Spill};
+/// use std::convert::TryInto;
+///
+/// struct Add(u64);
+/// impl Apply<u64, ()> for Add {
+///     fn apply_first(&mut self, first: &mut u64, _: &u64, _: &mut ()) {
+///         *first += self.0;
+///     }
+/// }
+///
+/// impl Spill for Add {
+///     fn encode(&self, buf: &mut Vec<u8>) {
+///         buf.extend_from_slice(&self.0.to_le_bytes());
+///     }
+///
+///     fn decode(bytes: &[u8]) -> Self {
+///         Add(u64::from_le_bytes(bytes.try_into().unwrap()))
+///     }
+/// }
+///
+/// let path = std::env::temp_dir().join(format!("reft-light-doctest-{}", std::process::id()));
+/// let oplog = FileOplog::create(&path).unwrap();
+/// let mut w = reft_light::new_with_oplog::<Add, _, _, _>(0, (), oplog);
+/// w.extend((1..=1000).map(Add));
+/// w.publish();
+/// assert_eq!(*w.enter().unwrap(), 500500);
+/// # drop(w);
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+pub struct FileOplog<O> {
+    file: File,
+    /// Where each operation's record starts in the file, in oplog order.
+    offsets: Vec<u64>,
+    /// The length of the file. Appended records past this point are still in `buf`.
+    flushed: u64,
+    buf: Vec<u8>,
+    _ops: PhantomData<fn(O) -> O>,
+}
+
+impl<O> FileOplog<O> {
+    /// Use `file` to store the oplog, discarding its current contents.
+    ///
+    /// The file must be open for both reading and writing.
+    pub fn new(file: File) -> io::Result<Self> {
+        file.set_len(0)?;
+        Ok(FileOplog {
+            file,
+            offsets: Vec::new(),
+            flushed: 0,
+            buf: Vec::new(),
+            _ops: PhantomData,
+        })
+    }
+
+    /// Store the oplog in the file at `path`, creating it if it does not exist.
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        Self::new(file)
+    }
+
+    fn end(&self) -> u64 {
+        self.flushed + self.buf.len() as u64
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.buf.is_empty() {
+            self.file.seek(SeekFrom::Start(self.flushed))?;
+            self.file.write_all(&self.buf)?;
+            self.flushed += self.buf.len() as u64;
+            self.buf.clear();
+        }
+        Ok(())
+    }
+
+    /// Read the raw record that starts at `offset` into `scratch`.
+    fn read_record<'s>(&self, offset: u64, scratch: &'s mut Vec<u8>) -> io::Result<&'s [u8]> {
+        scratch.clear();
+        if offset >= self.flushed {
+            let start = (offset - self.flushed) as usize;
+            let len = record_len(&self.buf[start..]);
+            scratch.extend_from_slice(&self.buf[start + 8..][..len]);
+        } else {
+            let mut file = &self.file;
+            file.seek(SeekFrom::Start(offset))?;
+            let mut len = [0; 8];
+            file.read_exact(&mut len)?;
+            scratch.resize(record_len(&len), 0);
+            file.read_exact(scratch)?;
+        }
+        Ok(scratch)
+    }
+
+    /// Move the remaining records to the front of the file if that reclaims at least as much
+    /// space as it copies.
+    fn compact(&mut self) -> io::Result<()> {
+        if self.offsets.is_empty() {
+            self.file.set_len(0)?;
+            self.flushed = 0;
+            self.buf.clear();
+            return Ok(());
+        }
+
+        // records are only moved towards the front, so each one must lie after the previous one
+        // for us not to overwrite a record before we have read it.
+        let live = self.end() - self.offsets[0];
+        if self.offsets[0] < live || self.offsets.windows(2).any(|w| w[0] > w[1]) {
+            return Ok(());
+        }
+
+        self.flush()?;
+        let mut scratch = Vec::new();
+        let mut at = 0;
+        for i in 0..self.offsets.len() {
+            let record = self.read_record(self.offsets[i], &mut scratch)?;
+            let len = (record.len() as u64).to_le_bytes();
+            self.file.seek(SeekFrom::Start(at))?;
+            self.file.write_all(&len)?;
+            self.file.write_all(&scratch)?;
+            self.offsets[i] = at;
+            at += 8 + scratch.len() as u64;
+        }
+        self.file.set_len(at)?;
+        self.flushed = at;
+        Ok(())
+    }
+}
+
+impl<O> FileOplog<O>
+where
+    O: Spill,
+{
+    fn append(&mut self, op: &O) -> io::Result<u64> {
+        let offset = self.end();
+        let start = self.buf.len();
+        self.buf.extend_from_slice(&[0; 8]);
+        op.encode(&mut self.buf);
+        let len = (self.buf.len() - start - 8) as u64;
+        self.buf[start..start + 8].copy_from_slice(&len.to_le_bytes());
+        if self.buf.len() >= FLUSH_AT {
+            self.flush()?;
+        }
+        Ok(offset)
+    }
+
+    fn read(&self, offset: u64, scratch: &mut Vec<u8>) -> io::Result<O> {
+        self.read_record(offset, scratch).map(O::decode)
+    }
+}
+
+fn record_len(header: &[u8]) -> usize {
+    let mut len = [0; 8];
+    len.copy_from_slice(&header[..8]);
+    u64::from_le_bytes(len) as usize
+}
+
+fn check<T>(result: io::Result<T>) -> T {
+    result.unwrap_or_else(|e| panic!("file-backed oplog I/O failed: {}", e))
+}
+
+impl<O> OplogStore<O> for FileOplog<O>
+where
+    O: Spill,
+{
+    fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    fn push_back(&mut self, op: O) {
+        let offset = check(self.append(&op));
+        self.offsets.push(offset);
+    }
+
+    fn drain_prefix<F>(&mut self, end: usize, mut f: F)
+    where
+        F: FnMut(O),
+    {
+        let mut scratch = Vec::new();
+        for &offset in &self.offsets[..end] {
+            f(check(self.read(offset, &mut scratch)));
+        }
+        self.offsets.drain(..end);
+        check(self.compact());
+    }
+
+    fn for_each_from<F>(&mut self, start: usize, mut f: F)
+    where
+        F: FnMut(&mut O),
+    {
+        let mut scratch = Vec::new();
+        for i in start..self.offsets.len() {
+            let mut op = check(self.read(self.offsets[i], &mut scratch));
+            f(&mut op);
+            self.offsets[i] = check(self.append(&op));
+        }
+    }
+
+    fn with_iter_from<R, F>(&self, start: usize, f: F) -> R
+    where
+        F: FnOnce(&mut dyn Iterator<Item = &O>) -> R,
+    {
+        // operations are only decoded if `f` asks for them, and each one is boxed so that it
+        // stays put as more are decoded.
+        let decoded: RefCell<Vec<Box<O>>> = RefCell::new(Vec::new());
+        let mut scratch = Vec::new();
+        let mut ops = self.offsets[start..].iter().map(|&offset| {
+            let op = Box::new(check(self.read(offset, &mut scratch)));
+            let op_ref: *const O = &*op;
+            decoded.borrow_mut().push(op);
+            // safety: the box is neither moved out of nor dropped until `decoded` is, which is
+            // after `f` returns, and `f` cannot keep the reference past that.
+            unsafe { &*op_ref }
+        });
+        f(&mut ops)
+    }
+}
+
+impl<O> fmt::Debug for FileOplog<O> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FileOplog")
+            .field("file", &self.file)
+            .field("len", &self.offsets.len())
+            .finish()
+    }
+}
@@ -0,0 +1,190 @@
+use super::{OplogStore, ShrinkPolicy};
+use std::collections::VecDeque;
+use std::fmt;
+use std::ops::Range;
+use std::sync::Arc;
+
+/// An [`OplogStore`] that can refer to operations in shared buffers instead of copying them in.
+///
+/// When many logical writers propose operations that a single writer applies, the operations
+/// often already live in a shared, immutable log segment. Appending them with
+/// [`WriteHandle::append_range`](crate::WriteHandle::append_range) to a `SharedOplog` only records
+/// a reference to the range, no matter how many operations it holds.
+///
+/// Operations cannot be applied straight out of the shared buffer, though:
+/// [`Apply::apply_first`](crate::Apply::apply_first) may modify an operation, and
+/// [`Apply::apply_second`](crate::Apply::apply_second) consumes it. So when a publish applies a
+/// referenced operation to the first copy, it clones it into the log, where it stays until it has
+/// been applied to the second copy too. The savings are thus limited to operations that are
+/// waiting to be published, which is where a backlog piles up when a writer falls behind.
+///
+/// # Examples
+///
+/// ```
+/// use reft_light::{Apply, SharedOplog};
+/// use std::sync::Arc;
+///
+/// #[derive(Clone)]
+/// struct Add(u64);
+/// impl Apply<u64, ()> for Add {
+///     fn apply_first(&mut self, first: &mut u64, _: &u64, _: &mut ()) {
+///         *first += self.0;
+///     }
+/// }
+///
+/// let segment: Arc<Vec<Add>> = Arc::new((1..=100).map(Add).collect());
+/// let mut w = reft_light::new_with_oplog::<Add, _, _, _>(0, (), SharedOplog::new());
+/// w.append_range(Arc::clone(&segment), 0..50);
+/// w.append_range(Arc::clone(&segment), 50..100);
+/// w.publish();
+/// assert_eq!(*w.enter().unwrap(), 5050);
+/// ```
+pub struct SharedOplog<O> {
+    entries: VecDeque<Entry<O>>,
+    len: usize,
+}
+
+enum Entry<O> {
+    Owned(O),
+    Shared(Arc<Vec<O>>, Range<usize>),
+}
+
+impl<O> Entry<O> {
+    fn as_slice(&self) -> &[O] {
+        match self {
+            Entry::Owned(op) => std::slice::from_ref(op),
+            Entry::Shared(ops, range) => &ops[range.clone()],
+        }
+    }
+}
+
+impl<O> SharedOplog<O> {
+    /// Create an empty log.
+    pub fn new() -> Self {
+        SharedOplog {
+            entries: VecDeque::new(),
+            len: 0,
+        }
+    }
+
+    /// Returns the number of entries the log keeps, where a whole referenced range counts as one.
+    pub fn entries(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Make sure an entry starts at operation index `at`, and return the index of that entry.
+    fn split(&mut self, at: usize) -> usize {
+        let mut pos = 0;
+        for i in 0..self.entries.len() {
+            if pos == at {
+                return i;
+            }
+            let len = self.entries[i].as_slice().len();
+            if at < pos + len {
+                if let Entry::Shared(ops, range) = &mut self.entries[i] {
+                    let mid = range.start + (at - pos);
+                    let tail = Entry::Shared(Arc::clone(ops), mid..range.end);
+                    range.end = mid;
+                    self.entries.insert(i + 1, tail);
+                    return i + 1;
+                }
+                unreachable!("owned entries hold exactly one operation");
+            }
+            pos += len;
+        }
+        self.entries.len()
+    }
+}
+
+impl<O> Default for SharedOplog<O> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<O> OplogStore<O> for SharedOplog<O>
+where
+    O: Clone,
+{
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn push_back(&mut self, op: O) {
+        self.entries.push_back(Entry::Owned(op));
+        self.len += 1;
+    }
+
+    fn insert(&mut self, index: usize, op: O) {
+        let split = self.split(index);
+        self.entries.insert(split, Entry::Owned(op));
+        self.len += 1;
+    }
+
+    fn extend_shared(&mut self, ops: &Arc<Vec<O>>, range: Range<usize>)
+    where
+        O: Clone,
+    {
+        if range.start < range.end {
+            self.len += ops[range.clone()].len();
+            self.entries
+                .push_back(Entry::Shared(Arc::clone(ops), range));
+        }
+    }
+
+    fn drain_prefix<F>(&mut self, end: usize, mut f: F)
+    where
+        F: FnMut(O),
+    {
+        let split = self.split(end);
+        for entry in self.entries.drain(..split) {
+            match entry {
+                Entry::Owned(op) => f(op),
+                Entry::Shared(ops, range) => ops[range].iter().cloned().for_each(&mut f),
+            }
+        }
+        self.len -= end;
+    }
+
+    fn for_each_from<F>(&mut self, start: usize, mut f: F)
+    where
+        F: FnMut(&mut O),
+    {
+        // changes to the operations must be kept, so referenced operations are cloned in
+        let split = self.split(start);
+        let tail: Vec<_> = self.entries.drain(split..).collect();
+        for entry in tail {
+            for mut op in match entry {
+                Entry::Owned(op) => vec![op],
+                Entry::Shared(ops, range) => ops[range].to_vec(),
+            } {
+                f(&mut op);
+                self.entries.push_back(Entry::Owned(op));
+            }
+        }
+    }
+
+    fn with_iter_from<R, F>(&self, start: usize, f: F) -> R
+    where
+        F: FnOnce(&mut dyn Iterator<Item = &O>) -> R,
+    {
+        f(&mut self
+            .entries
+            .iter()
+            .flat_map(|entry| entry.as_slice())
+            .skip(start))
+    }
+
+    fn shrink(&mut self, policy: ShrinkPolicy) {
+        self.entries.shrink(policy);
+    }
+}
+
+impl<O> fmt::Debug for SharedOplog<O> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedOplog")
+            .field("len", &self.len)
+            .field("entries", &self.entries.len())
+            .finish()
+    }
+}
@@ -0,0 +1,214 @@
+#[cfg(feature = "parallel-apply")]
+use crate::Apply;
+#[cfg(feature = "parallel-apply")]
+use std::thread;
+
+/// Data that is split into partitions which can be modified independently of one another.
+///
+/// See [`ApplyPartitioned`] and [`new_partitioned`](crate::new_partitioned).
+///
+/// Only available with the `parallel-apply` feature.
+#[cfg(feature = "parallel-apply")]
+pub trait Partitioned {
+    /// A single partition of the data.
+    type Partition: Send + Sync;
+
+    /// Returns every partition of the data, in a fixed order.
+    fn partitions(&self) -> Vec<&Self::Partition>;
+
+    /// Returns every partition of the data, in the same order as
+    /// [`partitions`](Self::partitions).
+    ///
+    /// Both copies of the data must always have the same number of partitions.
+    fn partitions_mut(&mut self) -> Vec<&mut Self::Partition>;
+}
+
+/// Operations that each only touch a single partition of a [`Partitioned`] data structure.
+///
+/// A write handle built with [`new_partitioned`](crate::new_partitioned) groups the operations of
+/// a publish by [`partition`](Self::partition), and applies the groups in parallel on scoped
+/// threads, each with exclusive access to its partition. Within a partition, operations are
+/// applied in the order they were appended. Partitioned handles only fall back to the regular
+/// [`Apply`] methods if their oplog store cannot hand out the operations as a slice. The finalize
+/// hooks still run once per batch on the whole data.
+///
+/// Since operations run concurrently, they get no access to the auxiliary data, and
+/// [`Apply::summarize`] still sees every operation in append order. Spreading a publish over
+/// threads has a fixed cost, so this only pays off for large batches of operations, or for
+/// operations that are expensive to apply.
+///
+/// Only available with the `parallel-apply` feature.
+///
+/// # Examples
+///
+/// ```
+/// use reft_light::{Apply, ApplyPartitioned, Partitioned};
+/// use std::collections::HashMap;
+///
+/// #[derive(Clone)]
+/// struct Sharded(Vec<HashMap<u64, u64>>);
+/// impl Partitioned for Sharded {
+///     type Partition = HashMap<u64, u64>;
+///     fn partitions(&self) -> Vec<&Self::Partition> {
+///         self.0.iter().collect()
+///     }
+///     fn partitions_mut(&mut self) -> Vec<&mut Self::Partition> {
+///         self.0.iter_mut().collect()
+///     }
+/// }
+///
+/// struct Insert(u64, u64);
+/// impl Apply<Sharded, ()> for Insert {
+///     fn apply_first(&mut self, first: &mut Sharded, _: &Sharded, _: &mut ()) {
+///         first.0[self.partition()].insert(self.0, self.1);
+///     }
+/// }
+/// impl ApplyPartitioned<Sharded, ()> for Insert {
+///     fn partition(&self) -> usize {
+///         (self.0 % 4) as usize
+///     }
+///     fn apply_first_partitioned(&mut self, first: &mut HashMap<u64, u64>, _: &HashMap<u64, u64>) {
+///         first.insert(self.0, self.1);
+///     }
+/// }
+///
+/// let mut w = reft_light::new_partitioned::<Insert, _, _>(Sharded(vec![HashMap::new(); 4]), ());
+/// w.extend((0..100).map(|k| Insert(k, k * k)));
+/// w.publish();
+/// assert_eq!(w.enter().unwrap().0[1][&9], 81);
+/// ```
+#[cfg(feature = "parallel-apply")]
+pub trait ApplyPartitioned<T, A>: Apply<T, A> + Send
+where
+    T: Partitioned,
+{
+    /// Returns the index of the partition this operation applies to.
+    ///
+    /// Must be less than the number of partitions, and must not change between the two
+    /// applications of the operation.
+    fn partition(&self) -> usize;
+
+    /// Apply `O` to its partition in the first of the two copies.
+    ///
+    /// See [`Apply::apply_first`].
+    fn apply_first_partitioned(&mut self, first: &mut T::Partition, second: &T::Partition);
+
+    /// Apply `O` to its partition in the second of the two copies.
+    ///
+    /// See [`Apply::apply_second`]. Defaults to calling `apply_first_partitioned`.
+    fn apply_second_partitioned(mut self, first: &T::Partition, second: &mut T::Partition) {
+        self.apply_first_partitioned(second, first);
+    }
+}
+
+/// How a partitioned write handle applies batches of operations.
+///
+/// Only ever constructed with the `parallel-apply` feature, but always part of the write handle
+/// so that publishing does not need two versions.
+#[cfg_attr(not(feature = "parallel-apply"), allow(dead_code))]
+pub(crate) struct ParallelApply<O, T> {
+    pub(crate) first: fn(&mut [O], &mut T, &T),
+    pub(crate) second: fn(std::vec::Drain<'_, O>, &T, &mut T),
+}
+
+impl<O, T> Clone for ParallelApply<O, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<O, T> Copy for ParallelApply<O, T> {}
+
+#[cfg(feature = "parallel-apply")]
+impl<O, T> ParallelApply<O, T> {
+    pub(crate) fn new<A>() -> Self
+    where
+        O: ApplyPartitioned<T, A>,
+        T: Partitioned,
+    {
+        ParallelApply {
+            first: |ops, first, second| {
+                let buckets = bucket::<O, T, A, _>(ops.iter_mut(), first, second);
+                in_parallel(buckets, |op, first, second| {
+                    op.apply_first_partitioned(first, second)
+                });
+            },
+            second: |ops, first, second| {
+                let buckets = bucket::<O, T, A, _>(ops, second, first);
+                in_parallel(buckets, |op, second, first| {
+                    op.apply_second_partitioned(first, second)
+                });
+            },
+        }
+    }
+}
+
+/// The operations that go to one partition, along with that partition in both copies.
+#[cfg(feature = "parallel-apply")]
+type Bucket<'a, P, I> = (&'a mut P, &'a P, Vec<I>);
+
+/// Group operations by the partition they apply to, preserving their order.
+#[cfg(feature = "parallel-apply")]
+fn bucket<'a, O, T, A, I>(
+    ops: impl IntoIterator<Item = I>,
+    target: &'a mut T,
+    other: &'a T,
+) -> Vec<Bucket<'a, T::Partition, I>>
+where
+    O: ApplyPartitioned<T, A>,
+    T: Partitioned,
+    I: std::borrow::Borrow<O>,
+{
+    let mut buckets: Vec<_> = target
+        .partitions_mut()
+        .into_iter()
+        .zip(other.partitions())
+        .map(|(target, other)| (target, other, Vec::new()))
+        .collect();
+    for op in ops {
+        let partition = op.borrow().partition();
+        let partitions = buckets.len();
+        buckets
+            .get_mut(partition)
+            .unwrap_or_else(|| {
+                panic!(
+                    "partition {} out of {} does not exist",
+                    partition, partitions
+                )
+            })
+            .2
+            .push(op);
+    }
+    buckets.retain(|(_, _, ops)| !ops.is_empty());
+    buckets
+}
+
+/// Apply each bucket on one of a few scoped threads, including the current one.
+#[cfg(feature = "parallel-apply")]
+fn in_parallel<P, I, F>(mut buckets: Vec<Bucket<'_, P, I>>, apply: F)
+where
+    P: Send + Sync,
+    I: Send,
+    F: Fn(I, &mut P, &P) + Sync,
+{
+    let workers = thread::available_parallelism().map_or(1, |n| n.get());
+    let per_worker = buckets.len().div_ceil(workers).max(1);
+    let apply = &apply;
+    let run = move |chunk: &mut [Bucket<'_, P, I>]| {
+        for (target, other, ops) in chunk {
+            for op in ops.drain(..) {
+                apply(op, target, other);
+            }
+        }
+    };
+    thread::scope(|scope| {
+        let mut chunks = buckets.chunks_mut(per_worker);
+        let local = chunks.next();
+        for chunk in chunks {
+            scope.spawn(move || run(chunk));
+        }
+        if let Some(chunk) = local {
+            run(chunk);
+        }
+    });
+}
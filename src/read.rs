@@ -1,19 +1,51 @@
 use crate::sync::{fence, Arc, AtomicPtr, AtomicUsize, Ordering};
+use crate::Slot;
+use std::any::Any;
 use std::cell::Cell;
 use std::fmt;
 use std::marker::PhantomData;
 use std::ptr::NonNull;
+use std::task::{Context, Poll};
 
 // To make [`WriteHandle`] and friends work.
 #[cfg(doc)]
-use crate::WriteHandle;
+use crate::{Apply, WriteHandle};
 
 mod guard;
-pub use guard::ReadGuard;
+pub use guard::{ReadGuard, SendReadGuard};
 
 mod factory;
 pub use factory::ReadHandleFactory;
 
+mod cow;
+pub use cow::CowGuard;
+
+mod owned;
+pub use owned::OwnedReadGuard;
+
+mod batch;
+pub use batch::BatchGuard;
+
+mod poll;
+pub use poll::WaitForGeneration;
+
+mod gone;
+pub use gone::WriterGone;
+
+mod fresh;
+pub use fresh::FreshGuard;
+pub(crate) use fresh::{lock_fresh, try_lock_fresh, Fresh, FreshCopy};
+
+#[cfg(feature = "arc-swap-compat")]
+mod arc_compat;
+#[cfg(feature = "arc-swap-compat")]
+pub use arc_compat::{ArcGuard, ArcLoader};
+
+#[cfg(feature = "rkyv")]
+mod archived;
+#[cfg(feature = "rkyv")]
+pub use archived::InvalidArchive;
+
 /// A read handle to a left-right guarded data structure.
 ///
 /// To use a handle, first call [`enter`](Self::enter) to acquire a [`ReadGuard`]. This is similar
@@ -36,9 +68,14 @@ pub use factory::ReadHandleFactory;
 /// You can create a new, independent `ReadHandle` either by cloning an existing handle or by using
 /// a [`ReadHandleFactory`]. Note, however, that creating a new handle through either of these
 /// mechanisms _does_ take a lock, and may therefore become a bottleneck if you do it frequently.
+/// That lock is only taken when the handle is created, where it registers its epoch with the
+/// writer, so even the first call to `enter` on a new handle does not take it.
 pub struct ReadHandle<T> {
-    pub(crate) inner: Arc<AtomicPtr<T>>,
+    pub(crate) inner: Arc<AtomicPtr<Slot<T>>>,
     pub(crate) epochs: crate::Epochs,
+    pub(crate) pins: crate::Pins,
+    pub(crate) published: crate::Published,
+    pub(crate) fresh: Fresh<T>,
     epoch: Arc<AtomicUsize>,
     epoch_i: usize,
     enters: Cell<usize>,
@@ -55,7 +92,7 @@ impl<T> Drop for ReadHandle<T> {
     fn drop(&mut self) {
         // epoch must already be even for us to have &mut self,
         // so okay to lock since we're not holding up the epoch anyway.
-        let e = self.epochs.lock().unwrap().remove(self.epoch_i);
+        let e = crate::lock_epochs(&self.epochs).remove(self.epoch_i);
         assert!(Arc::ptr_eq(&e, &self.epoch));
         assert_eq!(self.enters.get(), 0);
     }
@@ -72,7 +109,13 @@ impl<T> fmt::Debug for ReadHandle<T> {
 
 impl<T> Clone for ReadHandle<T> {
     fn clone(&self) -> Self {
-        ReadHandle::new_with_arc(Arc::clone(&self.inner), Arc::clone(&self.epochs))
+        ReadHandle::new_with_arc(
+            Arc::clone(&self.inner),
+            Arc::clone(&self.epochs),
+            Arc::clone(&self.pins),
+            Arc::clone(&self.published),
+            Arc::clone(&self.fresh),
+        )
     }
 }
 
@@ -86,19 +129,34 @@ impl<T> Eq for ReadHandle<T> {}
 
 impl<T> ReadHandle<T> {
     pub(crate) fn new(inner: T, epochs: crate::Epochs) -> Self {
-        let store = Box::into_raw(Box::new(inner));
+        let store = Box::into_raw(Box::new(Slot::new(inner)));
         let inner = Arc::new(AtomicPtr::new(store));
-        Self::new_with_arc(inner, epochs)
+        Self::new_with_arc(
+            inner,
+            epochs,
+            Default::default(),
+            Default::default(),
+            Default::default(),
+        )
     }
 
-    fn new_with_arc(inner: Arc<AtomicPtr<T>>, epochs: crate::Epochs) -> Self {
+    fn new_with_arc(
+        inner: Arc<AtomicPtr<Slot<T>>>,
+        epochs: crate::Epochs,
+        pins: crate::Pins,
+        published: crate::Published,
+        fresh: Fresh<T>,
+    ) -> Self {
         // tell writer about our epoch tracker
         let epoch = Arc::new(AtomicUsize::new(0));
         // okay to lock, since we're not holding up the epoch
-        let epoch_i = epochs.lock().unwrap().insert(Arc::clone(&epoch));
+        let epoch_i = crate::lock_epochs(&epochs).insert(Arc::clone(&epoch));
 
         Self {
             epochs,
+            pins,
+            published,
+            fresh,
             epoch,
             epoch_i,
             enters: Cell::new(0),
@@ -113,6 +171,9 @@ impl<T> ReadHandle<T> {
         ReadHandleFactory {
             inner: Arc::clone(&self.inner),
             epochs: Arc::clone(&self.epochs),
+            pins: Arc::clone(&self.pins),
+            published: Arc::clone(&self.published),
+            fresh: Arc::clone(&self.fresh),
         }
     }
 }
@@ -138,13 +199,226 @@ impl<T> ReadHandle<T> {
                 self.enters.set(enters + 1);
                 Some(ReadGuard {
                     handle: guard::ReadHandleState::from(self),
-                    t: r_handle,
+                    t: &r_handle.data,
+                    meta: &r_handle.meta,
                 })
             } else {
                 unreachable!("if pointer is null, no ReadGuard should have been issued");
             };
         }
 
+        let r_handle = self.pin()?;
+        // add a guard to ensure we restore read parity even if we panic
+        let enters = self.enters.get() + 1;
+        self.enters.set(enters);
+        Some(ReadGuard {
+            handle: guard::ReadHandleState::from(self),
+            t: &r_handle.data,
+            meta: &r_handle.meta,
+        })
+    }
+
+    /// Take out a guarded live reference to the read copy of the `T`, or find out how far the
+    /// writer has gotten with dropping.
+    ///
+    /// This behaves just like [`enter`](Self::enter), except that it tells apart the two reasons
+    /// for not getting a guard. While the [`WriteHandle`] is being dropped, it waits for readers
+    /// to depart before it frees the data, and this returns [`WriterGone::Draining`]. Once it is
+    /// done, this returns [`WriterGone::Dropped`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use reft_light::{Apply, WriterGone};
+    ///
+    /// struct Add(u64);
+    /// impl Apply<u64, ()> for Add {
+    ///     fn apply_first(&mut self, first: &mut u64, _: &u64, _: &mut ()) {
+    ///         *first += self.0;
+    ///     }
+    /// }
+    ///
+    /// let mut w = reft_light::new::<Add, _, _>(0, ());
+    /// let r = w.clone();
+    /// w.append(Add(1)).publish();
+    /// assert_eq!(*r.try_enter().unwrap(), 1);
+    ///
+    /// drop(w);
+    /// assert_eq!(r.try_enter().err(), Some(WriterGone::Dropped));
+    /// ```
+    pub fn try_enter(&self) -> Result<ReadGuard<'_, T>, WriterGone> {
+        self.enter().ok_or_else(|| {
+            // the writer only marks itself dropped after it has cleared the pointer, so until
+            // then, a cleared pointer means that it is still draining
+            if self.published.lock().dropped {
+                WriterGone::Dropped
+            } else {
+                WriterGone::Draining
+            }
+        })
+    }
+
+    /// Take out a guarded reference to the copy of the `T` that has seen the most operations,
+    /// including ones that have not been published yet.
+    ///
+    /// This inverts the usual staleness guarantee for readers that would rather see the writer's
+    /// in-flight state than the last published one, like a dashboard that monitors live data.
+    /// Once the writer has brought its write copy up to date with
+    /// [`WriteHandle::current`], fresh reads see that copy, while [`enter`](Self::enter) keeps
+    /// seeing the read copy until the next [`publish`](WriteHandle::publish). Operations that have
+    /// only been appended are not visible to either, since nothing has applied them yet. Fresh
+    /// reads therefore see what `current` last returned, or what was last published, whichever is
+    /// newer.
+    ///
+    /// Fresh reads must be enabled when the left-right is created, with
+    /// [`new_with_fresh_reads`](crate::new_with_fresh_reads). Otherwise, this always returns
+    /// `None`, and the writer never pays for them.
+    ///
+    /// The writer modifies the write copy in place, so reading it while operations are being
+    /// applied would be a data race, not merely a torn read. Fresh reads are instead protected by
+    /// a lock: the guard holds a shared lock, and the writer takes it exclusively whenever it
+    /// applies operations or publishes. This means that, unlike `enter`, this may block while the
+    /// writer applies a batch. In turn, a `FreshGuard` stalls every publish for as long as it
+    /// lives, so keep fresh guards short-lived. Publishes that must not block, like
+    /// [`poll_publish`](WriteHandle::poll_publish) and
+    /// [`PendingPublish::try_complete`](crate::PendingPublish::try_complete), report that they
+    /// are not done yet instead. Do not create or drop handles to the same data while holding a
+    /// `FreshGuard`, since the writer may be waiting for the guard while holding the lock those
+    /// need.
+    ///
+    /// If fresh reads are not enabled, or the `WriteHandle` has been dropped, this function
+    /// returns `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use reft_light::Apply;
+    ///
+    /// struct Add(u64);
+    /// impl Apply<u64, ()> for Add {
+    ///     fn apply_first(&mut self, first: &mut u64, _: &u64, _: &mut ()) {
+    ///         *first += self.0;
+    ///     }
+    /// }
+    ///
+    /// let mut w = reft_light::new_with_fresh_reads::<Add, _, _>(0, ());
+    /// let r = w.clone();
+    /// w.append(Add(1)).publish();
+    /// w.append(Add(2));
+    /// w.current();
+    ///
+    /// assert_eq!(*r.enter().unwrap(), 1);
+    /// assert_eq!(*r.enter_fresh().unwrap(), 3);
+    /// ```
+    pub fn enter_fresh(&self) -> Option<FreshGuard<'_, T>> {
+        FreshGuard::new(&self.fresh, &self.published)
+    }
+
+    /// Take out a guarded live reference to the read copy of the `T` that can be sent across
+    /// threads.
+    ///
+    /// [`ReadGuard`]s are deliberately not `Send`: a guard blocks [`WriteHandle::publish`] for as
+    /// long as it lives, so holding one across an `.await` point is almost always a mistake, and
+    /// making the guard `!Send` turns that mistake into a compile error for futures that run on a
+    /// multi-threaded executor. If you genuinely need to move a guard to another thread, use this
+    /// method instead. The returned [`SendReadGuard`] is `Send` whenever `T` is `Sync`.
+    ///
+    /// This method takes `&mut self` since the guard may release the epoch from a different
+    /// thread than the one that owns the handle, so no other guards may be taken out through this
+    /// handle in the meantime.
+    ///
+    /// If the `WriteHandle` has been dropped, this function returns `None`.
+    pub fn enter_send(&mut self) -> Option<SendReadGuard<'_, T>> {
+        // no ReadGuards can be alive since we have &mut self, so our epoch is even.
+        debug_assert_eq!(self.enters.get(), 0);
+        let r_handle = self.pin()?;
+        Some(SendReadGuard {
+            epoch: &self.epoch,
+            published: &self.published,
+            t: &r_handle.data,
+            meta: &r_handle.meta,
+        })
+    }
+
+    /// Take out a guard that reads through to the read copy of the `T`, but clones it on demand.
+    ///
+    /// The returned [`CowGuard`] behaves like a [`ReadGuard`] until [`CowGuard::to_mut`] is
+    /// called, at which point it clones the data into a local copy that you can modify, and stops
+    /// holding up the [`WriteHandle`]. This avoids cloning for readers that only rarely need to
+    /// modify what they read.
+    ///
+    /// If the `WriteHandle` has been dropped, this function returns `None`.
+    pub fn enter_cow(&self) -> Option<CowGuard<'_, T>>
+    where
+        T: Clone,
+    {
+        self.enter().map(CowGuard::new)
+    }
+
+    /// Take out a guard under which many short reads share a single epoch pin.
+    ///
+    /// See [`BatchGuard`] for when this is worth it.
+    ///
+    /// If the `WriteHandle` has been dropped, this function returns `None`.
+    pub fn enter_batch(&self) -> Option<BatchGuard<'_, T>> {
+        self.enter().map(BatchGuard::new)
+    }
+
+    /// Run `f` on a single version of the `T`, and return its result.
+    ///
+    /// Everything `f` reads comes from the same version of the data: the handle is entered once
+    /// for the whole call, so no publish can expose newer data halfway through. This matters
+    /// for reads that combine several parts of the data, like two fields that the writer always
+    /// updates together. Entering the handle separately for each part gives no such guarantee,
+    /// since a publish may happen between the two reads, and the second would then see a newer
+    /// version than the first.
+    ///
+    /// Just like a [`ReadGuard`], the call holds up [`WriteHandle::publish`] until `f` returns,
+    /// so keep `f` short.
+    ///
+    /// If the `WriteHandle` has been dropped, this function returns `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use reft_light::Apply;
+    ///
+    /// // a range whose bounds must always be read together
+    /// #[derive(Clone)]
+    /// struct Range {
+    ///     low: u64,
+    ///     high: u64,
+    /// }
+    ///
+    /// struct Shift(u64);
+    /// impl Apply<Range, ()> for Shift {
+    ///     fn apply_first(&mut self, first: &mut Range, _: &Range, _: &mut ()) {
+    ///         first.low += self.0;
+    ///         first.high += self.0;
+    ///     }
+    /// }
+    ///
+    /// let mut w = reft_light::new::<Shift, _, _>(Range { low: 0, high: 10 }, ());
+    /// let r = w.clone();
+    /// w.append(Shift(5)).publish();
+    ///
+    /// let width = r.read_consistent(|range| range.high - range.low);
+    /// assert_eq!(width, Some(10));
+    /// ```
+    pub fn read_consistent<R, F>(&self, f: F) -> Option<R>
+    where
+        F: FnOnce(&T) -> R,
+    {
+        let guard = self.enter()?;
+        Some(f(&guard))
+    }
+
+    /// Bumps our epoch to announce a read, and returns the copy that read should use.
+    ///
+    /// Must only be called while our epoch is even, i.e., while no guards are alive. If this
+    /// returns `Some`, the epoch has been left odd, and must be bumped again once the read is
+    /// over. If the `WriteHandle` has been dropped, parity is restored and `None` is returned.
+    fn pin(&self) -> Option<&Slot<T>> {
         // once we update our epoch, the writer can no longer do a swap until we set the MSB to
         // indicate that we've finished our read. however, we still need to deal with the case of a
         // race between when the writer reads our epoch and when they decide to make the swap.
@@ -184,22 +458,161 @@ impl<T> ReadHandle<T> {
         // since we bumped our epoch, this pointer will remain valid until we bump it again
         let r_handle = unsafe { r_handle.as_ref() };
 
-        if let Some(r_handle) = r_handle {
-            // add a guard to ensure we restore read parity even if we panic
-            let enters = self.enters.get() + 1;
-            self.enters.set(enters);
-            Some(ReadGuard {
-                handle: guard::ReadHandleState::from(self),
-                t: r_handle,
-            })
-        } else {
+        if r_handle.is_none() {
             // the writehandle has been dropped, and so has both copies,
             // so restore parity and return None
             self.epoch.fetch_add(1, Ordering::AcqRel);
-            None
+        }
+        r_handle
+    }
+
+    /// Take out a guarded live reference to the read copy of the `T`, along with a token that
+    /// identifies the publish that exposed it.
+    ///
+    /// The token outlives the guard. Pass it to [`is_stale`](Self::is_stale) later to cheaply
+    /// check whether a value computed from this read is still current.
+    ///
+    /// If the `WriteHandle` has been dropped, this function returns `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use reft_light::Apply;
+    ///
+    /// struct Add(u64);
+    /// impl Apply<u64, ()> for Add {
+    ///     fn apply_first(&mut self, first: &mut u64, _: &u64, _: &mut ()) {
+    ///         *first += self.0;
+    ///     }
+    /// }
+    ///
+    /// let mut w = reft_light::new::<Add, _, _>(1, ());
+    /// let r = w.clone();
+    ///
+    /// let (guard, token) = r.enter_with_token().unwrap();
+    /// let cached = *guard * 10;
+    /// drop(guard);
+    /// assert!(!r.is_stale(&token));
+    /// assert_eq!(cached, 10);
+    ///
+    /// w.append(Add(1)).publish();
+    /// assert!(r.is_stale(&token));
+    ///
+    /// // once the writer is gone, every token is stale
+    /// let (_, token) = r.enter_with_token().unwrap();
+    /// drop(w);
+    /// assert!(r.is_stale(&token));
+    /// ```
+    pub fn enter_with_token(&self) -> Option<(ReadGuard<'_, T>, StaleToken)> {
+        let guard = self.enter()?;
+        let token = StaleToken {
+            generation: guard.meta.generation,
+        };
+        Some((guard, token))
+    }
+
+    /// Returns true if a publish has happened since `token` was handed out by
+    /// [`enter_with_token`](Self::enter_with_token).
+    ///
+    /// This briefly enters the handle. If the `WriteHandle` has been dropped, every token is
+    /// stale. Tokens are only meaningful for handles to the same left-right they came from.
+    pub fn is_stale(&self, token: &StaleToken) -> bool {
+        match self.enter() {
+            Some(guard) => guard.meta.generation != token.generation,
+            None => true,
         }
     }
 
+    /// Block until a publish has exposed the data of the given generation.
+    ///
+    /// Generations count publishes: the data starts out at generation 0, and every call to
+    /// [`WriteHandle::publish`] exposes the next generation. A writer can tell a reader on
+    /// another thread which generation will contain its latest operations (see
+    /// [`WriteHandle::generation`]), and the reader can then wait here for them to become
+    /// visible. This parks the thread rather than spinning.
+    ///
+    /// Returns true once the data readers see is of generation `generation` or later, or false if
+    /// the `WriteHandle` was dropped before that happened.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use reft_light::Apply;
+    ///
+    /// struct Add(u64);
+    /// impl Apply<u64, ()> for Add {
+    ///     fn apply_first(&mut self, first: &mut u64, _: &u64, _: &mut ()) {
+    ///         *first += self.0;
+    ///     }
+    /// }
+    ///
+    /// let mut w = reft_light::new::<Add, _, _>(0, ());
+    /// let r = w.clone();
+    ///
+    /// w.append(Add(1));
+    /// let visible_at = w.generation() + 1;
+    /// let reader = std::thread::spawn(move || {
+    ///     assert!(r.wait_for_generation(visible_at));
+    ///     *r.enter().unwrap()
+    /// });
+    /// w.publish();
+    /// assert_eq!(reader.join().unwrap(), 1);
+    /// ```
+    pub fn wait_for_generation(&self, generation: u64) -> bool {
+        let mut state = self.published.lock();
+        while state.generation < generation && !state.retired {
+            state = self
+                .published
+                .changed
+                .wait(state)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+        state.generation >= generation
+    }
+
+    /// Check whether a publish has exposed the data of the given generation, without blocking.
+    ///
+    /// This is the non-blocking counterpart to [`wait_for_generation`](Self::wait_for_generation)
+    /// for use with any executor: if the generation has not been reached yet, the waker of `cx`
+    /// is woken by the next publish, or when the `WriteHandle` is dropped.
+    pub fn poll_generation(&self, cx: &mut Context<'_>, generation: u64) -> Poll<bool> {
+        let mut state = self.published.lock();
+        if state.generation >= generation || state.retired {
+            return Poll::Ready(state.generation >= generation);
+        }
+        if !state.readers.iter().any(|w| w.will_wake(cx.waker())) {
+            state.readers.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+
+    /// Returns a future that resolves once a publish has exposed the data of the given
+    /// generation.
+    ///
+    /// See [`wait_for_generation`](Self::wait_for_generation) and [`WaitForGeneration`].
+    pub fn wait_for_generation_async(&self, generation: u64) -> WaitForGeneration<'_, T> {
+        WaitForGeneration {
+            handle: self,
+            generation,
+        }
+    }
+
+    /// Returns the [`Delta`](crate::Delta) published along with the data readers currently see.
+    ///
+    /// This is the summary that [`Apply::summarize`] produced for the most recent call to
+    /// [`WriteHandle::publish`]. It returns `None` if the `WriteHandle` has been dropped, if no
+    /// delta was published, or if the delta is not of type `D`.
+    ///
+    /// Note that a subsequent call to [`enter`](Self::enter) may already observe newer data. If
+    /// you need the delta that matches the data you are reading, use [`ReadGuard::delta`] on the
+    /// guard you read through instead.
+    pub fn last_delta<D>(&self) -> Option<std::sync::Arc<D>>
+    where
+        D: Any + Send + Sync,
+    {
+        self.enter().and_then(|guard| ReadGuard::delta(&guard))
+    }
+
     /// Returns true if the [`WriteHandle`] has been dropped.
     pub fn was_dropped(&self) -> bool {
         self.inner.load(Ordering::Acquire).is_null()
@@ -213,10 +626,20 @@ impl<T> ReadHandle<T> {
     ///
     /// Casting this pointer to `&mut` is never safe.
     pub fn raw_handle(&self) -> Option<NonNull<T>> {
-        NonNull::new(self.inner.load(Ordering::Acquire))
+        // the data is the first field of the `#[repr(C)]` Slot, so this only changes the type of
+        // the pointer, without creating a reference to a Slot that may already be gone.
+        NonNull::new(self.inner.load(Ordering::Acquire)).map(NonNull::cast)
     }
 }
 
+/// Identifies the publish that exposed the data a reader saw.
+///
+/// Handed out by [`ReadHandle::enter_with_token`], and checked with [`ReadHandle::is_stale`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StaleToken {
+    generation: u64,
+}
+
 /// `ReadHandle` cannot be shared across threads:
 ///
 /// ```compile_fail
@@ -0,0 +1,126 @@
+use super::ReadHandle;
+use std::cell::RefCell;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// Adapts a [`ReadHandle`] to the `load` API of `arc_swap::ArcSwap`.
+///
+/// Code built on `arc-swap` reads shared state through `load`, which hands out an `Arc` to the
+/// current value. An `ArcLoader` offers the same API on top of a left-right, so read sites can be
+/// migrated one at a time: [`load`](Self::load) enters the handle, clones the data into an `Arc`
+/// while the epoch is held, and releases the epoch again before returning.
+///
+/// That clone is what sets this apart from [`ReadHandle::enter`], which never copies the data.
+/// The loader keeps the `Arc` it last handed out, and only clones again once a publish has
+/// exposed new data, so the data is cloned at most once per publish per loader rather than once
+/// per load. In exchange, the returned snapshots hold up neither the writer nor the epoch, and
+/// can be kept around and sent to other threads like any `Arc`. Read sites that are migrated to
+/// `enter` avoid the clone altogether.
+///
+/// Once the [`WriteHandle`](crate::WriteHandle) is gone, `load` keeps returning the last data it
+/// saw.
+///
+/// Only available with the `arc-swap-compat` feature.
+///
+/// # Examples
+///
+/// ```
+/// use reft_light::{Apply, ArcLoader};
+/// use std::sync::Arc;
+///
+/// struct Push(u32);
+/// impl Apply<Vec<u32>, ()> for Push {
+///     fn apply_first(&mut self, first: &mut Vec<u32>, _: &Vec<u32>, _: &mut ()) {
+///         first.push(self.0);
+///     }
+/// }
+///
+/// let mut w = reft_light::new::<Push, _, _>(vec![], ());
+/// w.append(Push(1)).publish();
+/// let config = ArcLoader::new(w.clone()).unwrap();
+///
+/// // read sites written against arc-swap keep working
+/// let snapshot = config.load();
+/// assert_eq!(snapshot.len(), 1);
+///
+/// // a snapshot is an Arc, and does not change under a publish
+/// w.append(Push(2)).publish();
+/// assert_eq!(**snapshot, [1]);
+/// assert_eq!(**config.load(), [1, 2]);
+///
+/// // loads without a publish in between share one clone
+/// assert!(Arc::ptr_eq(&config.load_full(), &config.load_full()));
+/// ```
+pub struct ArcLoader<T> {
+    handle: ReadHandle<T>,
+    // the generation of the data, and a clone of it
+    cached: RefCell<(u64, Arc<T>)>,
+}
+
+impl<T> ArcLoader<T>
+where
+    T: Clone,
+{
+    /// Wrap `handle`, taking a first snapshot of the data.
+    ///
+    /// Returns `None` if the [`WriteHandle`](crate::WriteHandle) has been dropped.
+    pub fn new(handle: ReadHandle<T>) -> Option<Self> {
+        let guard = handle.enter()?;
+        let cached = (guard.meta.generation, Arc::new(T::clone(&guard)));
+        drop(guard);
+        Some(Self {
+            handle,
+            cached: RefCell::new(cached),
+        })
+    }
+
+    /// Returns a snapshot of the current data.
+    pub fn load(&self) -> ArcGuard<T> {
+        ArcGuard(self.load_full())
+    }
+
+    /// Returns a snapshot of the current data as a plain `Arc`.
+    pub fn load_full(&self) -> Arc<T> {
+        let mut cached = self.cached.borrow_mut();
+        if let Some(guard) = self.handle.enter() {
+            if guard.meta.generation != cached.0 {
+                *cached = (guard.meta.generation, Arc::new(T::clone(&guard)));
+            }
+        }
+        Arc::clone(&cached.1)
+    }
+
+    /// Returns the wrapped handle.
+    pub fn into_inner(self) -> ReadHandle<T> {
+        self.handle
+    }
+}
+
+impl<T> fmt::Debug for ArcLoader<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArcLoader")
+            .field("handle", &self.handle)
+            .finish()
+    }
+}
+
+/// A snapshot handed out by [`ArcLoader::load`].
+///
+/// Like the guards of `arc-swap`, it dereferences to an `Arc` of the data.
+#[derive(Debug)]
+pub struct ArcGuard<T>(Arc<T>);
+
+impl<T> ArcGuard<T> {
+    /// Returns the `Arc` of the snapshot.
+    pub fn into_inner(guard: Self) -> Arc<T> {
+        guard.0
+    }
+}
+
+impl<T> Deref for ArcGuard<T> {
+    type Target = Arc<T>;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
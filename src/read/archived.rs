@@ -0,0 +1,101 @@
+use super::ReadGuard;
+use rkyv::validation::validators::DefaultValidator;
+use rkyv::{Archive, Archived, CheckBytes};
+use std::error::Error;
+use std::fmt;
+
+impl<'rh, T: ?Sized + AsRef<[u8]>> ReadGuard<'rh, T> {
+    /// Makes a new `ReadGuard` for the archived root of the `rkyv` bytes in the borrowed data,
+    /// after checking that the bytes hold a valid archive of a `U`.
+    ///
+    /// Nothing is deserialized: the returned guard reads the `U` in place, and keeps the bytes
+    /// pinned, and so the view valid, for as long as it lives. The bytes must be aligned for the
+    /// archived type, which an [`AlignedVec`](rkyv::AlignedVec) takes care of. See
+    /// [`archived_unchecked`](Self::archived_unchecked) to skip the check.
+    ///
+    /// This is an associated function that needs to be used as `ReadGuard::archived(...)`, since
+    /// a method would interfere with methods of the same name on the contents of a `Readguard`
+    /// used through `Deref`.
+    ///
+    /// Only available with the `rkyv` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use reft_light::{Apply, ReadGuard};
+    /// use rkyv::{AlignedVec, Archive, Serialize};
+    ///
+    /// #[derive(Archive, Serialize)]
+    /// #[archive(check_bytes)]
+    /// struct Config {
+    ///     name: String,
+    ///     limits: Vec<u32>,
+    /// }
+    ///
+    /// // the data is an archive, replaced wholesale by every write
+    /// struct Replace(AlignedVec);
+    /// impl Apply<AlignedVec, ()> for Replace {
+    ///     fn apply_first(&mut self, first: &mut AlignedVec, _: &AlignedVec, _: &mut ()) {
+    ///         first.clone_from(&self.0);
+    ///     }
+    /// }
+    ///
+    /// let config = Config {
+    ///     name: "zero-copy".to_string(),
+    ///     limits: vec![1, 2, 3],
+    /// };
+    /// let bytes = rkyv::to_bytes::<_, 256>(&config).unwrap();
+    ///
+    /// let mut w = reft_light::new::<Replace, _, _>(AlignedVec::new(), ());
+    /// let r = w.clone();
+    /// w.append(Replace(bytes)).publish();
+    ///
+    /// let config = ReadGuard::archived::<Config>(r.enter().unwrap()).unwrap();
+    /// assert_eq!(config.name, "zero-copy");
+    /// assert_eq!(config.limits.as_slice(), [1, 2, 3]);
+    /// ```
+    pub fn archived<U>(orig: Self) -> Result<ReadGuard<'rh, Archived<U>>, InvalidArchive>
+    where
+        U: Archive,
+        U::Archived: for<'a> CheckBytes<DefaultValidator<'a>>,
+    {
+        ReadGuard::map_validated(orig, |bytes| {
+            rkyv::check_archived_root::<U>(bytes.as_ref()).map_err(|e| InvalidArchive {
+                reason: e.to_string(),
+            })
+        })
+    }
+
+    /// Makes a new `ReadGuard` for the archived root of the `rkyv` bytes in the borrowed data,
+    /// without checking them.
+    ///
+    /// This is an associated function that needs to be used as
+    /// `ReadGuard::archived_unchecked(...)`, since a method would interfere with methods of the
+    /// same name on the contents of a `Readguard` used through `Deref`.
+    ///
+    /// Only available with the `rkyv` feature.
+    ///
+    /// # Safety
+    ///
+    /// The bytes must hold a valid archive of a `U`, with its root at the end, just like for
+    /// [`rkyv::archived_root`].
+    pub unsafe fn archived_unchecked<U: Archive>(orig: Self) -> ReadGuard<'rh, Archived<U>> {
+        ReadGuard::map(orig, |bytes| rkyv::archived_root::<U>(bytes.as_ref()))
+    }
+}
+
+/// The error returned by [`ReadGuard::archived`] when the bytes do not hold a valid archive.
+///
+/// Only available with the `rkyv` feature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidArchive {
+    reason: String,
+}
+
+impl fmt::Display for InvalidArchive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid archive: {}", self.reason)
+    }
+}
+
+impl Error for InvalidArchive {}
@@ -0,0 +1,68 @@
+use super::ReadGuard;
+use std::fmt;
+
+/// A single epoch pin shared by a batch of short reads.
+///
+/// Produced by [`ReadHandle::enter_batch`](crate::ReadHandle::enter_batch). Every call to
+/// [`ReadHandle::enter`](crate::ReadHandle::enter) that finds no other guard alive bumps the
+/// reader's epoch twice, once on entry and once when the guard is dropped. For a loop doing many
+/// tiny independent reads, those atomic writes can dominate the cost of the reads themselves. A
+/// `BatchGuard` bumps the epoch once when it is taken out and once when it is dropped, and every
+/// [`read`](Self::read) in between is a plain memory access.
+///
+/// The price is staleness granularity: all reads in the batch see the same copy, even if the
+/// writer publishes in the meantime, and the writer's next [`publish`](crate::WriteHandle::publish)
+/// waits for the whole batch rather than for a single read. Keep batches short.
+///
+/// # Examples
+///
+/// ```
+/// use reft_light::Apply;
+///
+/// struct Push(u32);
+/// impl Apply<Vec<u32>, ()> for Push {
+///     fn apply_first(&mut self, first: &mut Vec<u32>, _: &Vec<u32>, _: &mut ()) {
+///         first.push(self.0);
+///     }
+/// }
+///
+/// let mut w = reft_light::new::<Push, _, _>(vec![], ());
+/// let r = w.clone();
+/// w.extend((0..100).map(Push));
+/// w.publish();
+///
+/// let batch = r.enter_batch().unwrap();
+/// let mut hits = 0;
+/// for i in (0..1000).step_by(7) {
+///     hits += batch.read(|v| v.binary_search(&i).is_ok()) as usize;
+/// }
+/// assert_eq!(hits, 15);
+/// ```
+pub struct BatchGuard<'rh, T> {
+    guard: ReadGuard<'rh, T>,
+}
+
+impl<'rh, T> BatchGuard<'rh, T> {
+    pub(super) fn new(guard: ReadGuard<'rh, T>) -> Self {
+        Self { guard }
+    }
+
+    /// Perform one read in the batch.
+    pub fn read<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&T) -> R,
+    {
+        f(&self.guard)
+    }
+
+    /// Ends the batch, and returns a guard on the copy the batch was reading.
+    pub fn into_guard(self) -> ReadGuard<'rh, T> {
+        self.guard
+    }
+}
+
+impl<'rh, T: fmt::Debug> fmt::Debug for BatchGuard<'rh, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("BatchGuard").field(&*self.guard).finish()
+    }
+}
@@ -0,0 +1,148 @@
+use super::ReadGuard;
+use std::fmt;
+use std::ops::Deref;
+
+/// A guard that reads through to the left-right protected `T` until you ask to modify it.
+///
+/// Produced by [`ReadHandle::enter_cow`](crate::ReadHandle::enter_cow). Much like
+/// [`Cow`](std::borrow::Cow), a `CowGuard` starts out borrowing the read copy, and only clones it
+/// when [`to_mut`](Self::to_mut) is called. While it borrows, it behaves like a [`ReadGuard`], and
+/// keeps [`WriteHandle::publish`](crate::WriteHandle::publish) from making progress. Once it has
+/// cloned the data, it releases the epoch, and you are free to modify your local copy for as long
+/// as you like without holding up the writer. Modifications are never visible to anyone else.
+///
+/// # Examples
+///
+/// ```
+/// use reft_light::{Apply, CowGuard};
+///
+/// struct Push(i32);
+/// impl Apply<Vec<i32>, ()> for Push {
+///     fn apply_first(&mut self, first: &mut Vec<i32>, _: &Vec<i32>, _: &mut ()) {
+///         first.push(self.0);
+///     }
+/// }
+///
+/// let mut w = reft_light::new::<Push, _, _>(vec![], ());
+/// let r = w.clone();
+/// w.append(Push(1)).publish();
+///
+/// // reading does not clone
+/// let guard = r.enter_cow().unwrap();
+/// assert!(CowGuard::is_borrowed(&guard));
+/// assert_eq!(&*guard, &[1]);
+/// drop(guard);
+///
+/// // modifying clones, and lets go of the read copy
+/// let mut guard = r.enter_cow().unwrap();
+/// CowGuard::to_mut(&mut guard).push(2);
+/// assert!(!CowGuard::is_borrowed(&guard));
+/// assert_eq!(&*guard, &[1, 2]);
+///
+/// // so the writer can carry on even though the guard is still around
+/// w.append(Push(3)).publish();
+/// w.publish();
+/// assert_eq!(&*guard, &[1, 2]);
+/// assert_eq!(&*r.enter().unwrap(), &[1, 3]);
+/// ```
+pub struct CowGuard<'rh, T>
+where
+    T: Clone,
+{
+    state: State<'rh, T>,
+}
+
+enum State<'rh, T> {
+    Borrowed(ReadGuard<'rh, T>),
+    Owned(T),
+}
+
+impl<'rh, T> CowGuard<'rh, T>
+where
+    T: Clone,
+{
+    pub(super) fn new(guard: ReadGuard<'rh, T>) -> Self {
+        Self {
+            state: State::Borrowed(guard),
+        }
+    }
+
+    /// Returns a mutable reference to a local copy of the data.
+    ///
+    /// The first call clones the read copy and releases the guard's epoch. The clone is never
+    /// visible to other readers or to the writer.
+    ///
+    /// This is an associated function that needs to be used as `CowGuard::to_mut(...)`, since
+    /// a method would interfere with methods of the same name on the contents of a `CowGuard`
+    /// used through `Deref`.
+    pub fn to_mut(guard: &mut Self) -> &mut T {
+        if let State::Borrowed(ref borrowed) = guard.state {
+            // assigning drops the ReadGuard, which releases the epoch.
+            guard.state = State::Owned(T::clone(borrowed));
+        }
+
+        match guard.state {
+            State::Owned(ref mut t) => t,
+            State::Borrowed(_) => unreachable!("we just cloned the data"),
+        }
+    }
+
+    /// Extracts an owned copy of the data, cloning it if it is still borrowed.
+    ///
+    /// This is an associated function that needs to be used as `CowGuard::into_owned(...)`,
+    /// since a method would interfere with methods of the same name on the contents of a
+    /// `CowGuard` used through `Deref`.
+    pub fn into_owned(guard: Self) -> T {
+        match guard.state {
+            State::Borrowed(borrowed) => T::clone(&borrowed),
+            State::Owned(t) => t,
+        }
+    }
+
+    /// Returns true if the guard still reads through to the read copy of the data.
+    ///
+    /// This is an associated function that needs to be used as `CowGuard::is_borrowed(...)`,
+    /// since a method would interfere with methods of the same name on the contents of a
+    /// `CowGuard` used through `Deref`.
+    #[allow(clippy::match_like_matches_macro)]
+    pub fn is_borrowed(guard: &Self) -> bool {
+        match guard.state {
+            State::Borrowed(_) => true,
+            State::Owned(_) => false,
+        }
+    }
+}
+
+impl<'rh, T> Deref for CowGuard<'rh, T>
+where
+    T: Clone,
+{
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        match self.state {
+            State::Borrowed(ref borrowed) => borrowed,
+            State::Owned(ref t) => t,
+        }
+    }
+}
+
+impl<'rh, T> AsRef<T> for CowGuard<'rh, T>
+where
+    T: Clone,
+{
+    fn as_ref(&self) -> &T {
+        self
+    }
+}
+
+impl<'rh, T> fmt::Debug for CowGuard<'rh, T>
+where
+    T: Clone + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.state {
+            State::Borrowed(ref borrowed) => f.debug_tuple("Borrowed").field(borrowed).finish(),
+            State::Owned(ref t) => f.debug_tuple("Owned").field(t).finish(),
+        }
+    }
+}
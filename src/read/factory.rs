@@ -1,5 +1,6 @@
 use super::ReadHandle;
 use crate::sync::{Arc, AtomicPtr};
+use crate::Slot;
 use std::fmt;
 
 /// A type that is both `Sync` and `Send` and lets you produce new [`ReadHandle`] instances.
@@ -9,8 +10,11 @@ use std::fmt;
 /// that this _internally_ takes a lock whenever you call [`ReadHandleFactory::handle`], so
 /// you should not expect producing new handles rapidly to scale well.
 pub struct ReadHandleFactory<T> {
-    pub(super) inner: Arc<AtomicPtr<T>>,
+    pub(super) inner: Arc<AtomicPtr<Slot<T>>>,
     pub(super) epochs: crate::Epochs,
+    pub(super) pins: crate::Pins,
+    pub(super) published: crate::Published,
+    pub(super) fresh: super::Fresh<T>,
 }
 
 impl<T> fmt::Debug for ReadHandleFactory<T> {
@@ -26,6 +30,9 @@ impl<T> Clone for ReadHandleFactory<T> {
         Self {
             inner: Arc::clone(&self.inner),
             epochs: Arc::clone(&self.epochs),
+            pins: Arc::clone(&self.pins),
+            published: Arc::clone(&self.published),
+            fresh: Arc::clone(&self.fresh),
         }
     }
 }
@@ -34,6 +41,12 @@ impl<T> ReadHandleFactory<T> {
     /// Produce a new [`ReadHandle`] to the same left-right data structure as this factory was
     /// originally produced from.
     pub fn handle(&self) -> ReadHandle<T> {
-        ReadHandle::new_with_arc(Arc::clone(&self.inner), Arc::clone(&self.epochs))
+        ReadHandle::new_with_arc(
+            Arc::clone(&self.inner),
+            Arc::clone(&self.epochs),
+            Arc::clone(&self.pins),
+            Arc::clone(&self.published),
+            Arc::clone(&self.fresh),
+        )
     }
 }
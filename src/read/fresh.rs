@@ -0,0 +1,106 @@
+use crate::sync::{fence, Arc, Ordering, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use crate::Slot;
+use std::fmt;
+use std::ops::Deref;
+use std::ptr;
+use std::sync::TryLockError;
+
+/// The copy that has seen the most operations, shared by the writer and all of its readers.
+///
+/// The writer only modifies a copy while it holds the write lock, and fresh readers only read the
+/// copy while they hold the read lock. The pointer is null unless fresh reads were enabled, and
+/// once the writer has been dropped.
+pub(crate) type Fresh<T> = Arc<RwLock<FreshCopy<T>>>;
+
+pub(crate) struct FreshCopy<T>(pub(crate) *const Slot<T>);
+
+// safety: the pointer is only dereferenced by the handles that share it, which carry the Send and
+// Sync requirements for T themselves, just like the `AtomicPtr` to the read copy.
+unsafe impl<T> Send for FreshCopy<T> {}
+unsafe impl<T> Sync for FreshCopy<T> {}
+
+impl<T> Default for FreshCopy<T> {
+    fn default() -> Self {
+        FreshCopy(ptr::null())
+    }
+}
+
+/// Lock out fresh readers while the writer modifies a copy, or changes which copy they read.
+///
+/// Like the epochs, the lock stays usable if a thread panicked while holding it.
+pub(crate) fn lock_fresh<T>(fresh: &Fresh<T>) -> RwLockWriteGuard<'_, FreshCopy<T>> {
+    fresh.write().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Like `lock_fresh`, but returns `None` rather than block if a fresh reader holds the lock.
+pub(crate) fn try_lock_fresh<T>(fresh: &Fresh<T>) -> Option<RwLockWriteGuard<'_, FreshCopy<T>>> {
+    match fresh.try_write() {
+        Ok(locked) => Some(locked),
+        Err(TryLockError::Poisoned(e)) => Some(e.into_inner()),
+        Err(TryLockError::WouldBlock) => None,
+    }
+}
+
+impl<T> FreshCopy<T> {
+    pub(crate) fn clear(&mut self) {
+        self.0 = ptr::null();
+    }
+}
+
+/// A guarded reference to the copy of the `T` that has seen the most operations, including ones
+/// that have not been published yet.
+///
+/// Produced by [`ReadHandle::enter_fresh`](crate::ReadHandle::enter_fresh). While the guard
+/// lives, the writer cannot apply operations to either copy, so the data it refers to is never
+/// torn. That also means that every publish stalls until the guard is dropped, so keep fresh
+/// guards short-lived. See `enter_fresh` for details.
+pub struct FreshGuard<'rh, T> {
+    // the lock is what keeps the writer off the copy, so it must outlive any use of `t`
+    lock: Option<RwLockReadGuard<'rh, FreshCopy<T>>>,
+    t: &'rh T,
+    published: &'rh crate::Published,
+}
+
+impl<'rh, T> FreshGuard<'rh, T> {
+    pub(super) fn new(fresh: &'rh Fresh<T>, published: &'rh crate::Published) -> Option<Self> {
+        let lock = fresh.read().unwrap_or_else(|e| e.into_inner());
+        // safety: the writer only frees or modifies the copy after taking the write lock, which
+        // it cannot get while we hold the read lock.
+        let slot = unsafe { lock.0.as_ref() }?;
+        Some(FreshGuard {
+            t: &slot.data,
+            lock: Some(lock),
+            published,
+        })
+    }
+}
+
+impl<'rh, T> Drop for FreshGuard<'rh, T> {
+    fn drop(&mut self) {
+        drop(self.lock.take());
+        // a polling writer only tries to take the lock, which does not synchronize with our
+        // release. this pairs with the fence in `Generation::wait_for_reader`: either the writer
+        // sees the lock released, or we see that it is waiting.
+        fence(Ordering::SeqCst);
+        self.published.reader_departed();
+    }
+}
+
+impl<'rh, T> Deref for FreshGuard<'rh, T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        self.t
+    }
+}
+
+impl<'rh, T> AsRef<T> for FreshGuard<'rh, T> {
+    fn as_ref(&self) -> &T {
+        self.t
+    }
+}
+
+impl<'rh, T: fmt::Debug> fmt::Debug for FreshGuard<'rh, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("FreshGuard").field(self.t).finish()
+    }
+}
@@ -0,0 +1,27 @@
+use std::error::Error;
+use std::fmt;
+
+/// The error returned by [`ReadHandle::try_enter`](crate::ReadHandle::try_enter) once the
+/// [`WriteHandle`](crate::WriteHandle) has been dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriterGone {
+    /// The writer is being dropped, and is waiting for readers to depart before it frees the
+    /// data.
+    ///
+    /// Any guard still held on one of the writer's handles keeps it in this state.
+    Draining,
+    /// The writer has been dropped, and has freed the data, or handed it back through
+    /// [`WriteHandle::take`](crate::WriteHandle::take).
+    Dropped,
+}
+
+impl fmt::Display for WriterGone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WriterGone::Draining => f.write_str("the writer is waiting for readers to depart"),
+            WriterGone::Dropped => f.write_str("the writer has been dropped"),
+        }
+    }
+}
+
+impl Error for WriterGone {}
@@ -1,11 +1,25 @@
 use crate::sync::{AtomicUsize, Ordering};
+use crate::Meta;
+use std::any::Any;
+use std::borrow::Borrow;
 use std::cell::Cell;
+use std::marker::PhantomData;
 use std::mem;
+use std::sync::Arc;
+use std::time::Duration;
 
 #[derive(Debug, Copy, Clone)]
 pub(super) struct ReadHandleState<'rh> {
     pub(super) epoch: &'rh AtomicUsize,
     pub(super) enters: &'rh Cell<usize>,
+    pub(super) pins: &'rh crate::Pins,
+    pub(super) published: &'rh crate::Published,
+
+    // `ReadGuard` must never be `Send`. The shared `enters` counter already prevents it, but we
+    // don't want that to silently change if the bookkeeping ever does, since a guard that can be
+    // held across an `.await` on a multi-threaded executor stalls publishes for the whole await.
+    // See `ReadHandle::enter_send` for the `Send` flavor.
+    _unimpl_send: PhantomData<*const ()>,
 }
 
 impl<'rh, T> From<&'rh super::ReadHandle<T>> for ReadHandleState<'rh> {
@@ -13,6 +27,9 @@ impl<'rh, T> From<&'rh super::ReadHandle<T>> for ReadHandleState<'rh> {
         Self {
             epoch: &rh.epoch,
             enters: &rh.enters,
+            pins: &rh.pins,
+            published: &rh.published,
+            _unimpl_send: PhantomData,
         }
     }
 }
@@ -25,11 +42,17 @@ impl<'rh, T> From<&'rh super::ReadHandle<T>> for ReadHandleState<'rh> {
 ///
 /// To scope the guard to a subset of the data in `T`, use [`map`](Self::map) and
 /// [`try_map`](Self::try_map).
+///
+/// `ReadGuard` is not `Send`, so it cannot be held across an `.await` in a future that must be
+/// `Send`. If you really need to move a guard across threads, see
+/// [`ReadHandle::enter_send`](crate::ReadHandle::enter_send). To split a read across the
+/// threads of a scope instead, share `&*guard` with them, which is `Send` whenever `T` is `Sync`.
 #[derive(Debug)]
 pub struct ReadGuard<'rh, T: ?Sized> {
     // NOTE: _technically_ this is more like &'self.
     // the reference is valid until the guard is dropped.
     pub(super) t: &'rh T,
+    pub(super) meta: &'rh Meta,
     pub(super) handle: ReadHandleState<'rh>,
 }
 
@@ -59,6 +82,7 @@ impl<'rh, T: ?Sized> ReadGuard<'rh, T> {
     {
         let rg = ReadGuard {
             t: f(orig.t),
+            meta: orig.meta,
             handle: orig.handle,
         };
         mem::forget(orig);
@@ -94,11 +118,269 @@ impl<'rh, T: ?Sized> ReadGuard<'rh, T> {
     {
         let rg = ReadGuard {
             t: f(orig.t)?,
+            meta: orig.meta,
             handle: orig.handle,
         };
         mem::forget(orig);
         Some(rg)
     }
+
+    /// Makes a new `ReadGuard` for a view of the borrowed data that has to be validated first.
+    ///
+    /// This differs from [`try_map`](Self::try_map) in that the closure reports why the view could
+    /// not be produced. It is meant for zero-copy deserialization, where `T` holds serialized
+    /// bytes and `f` checks them and then reinterprets them in place. With
+    /// [`rkyv`](https://docs.rs/rkyv), for instance, `f` would be `rkyv::check_archived_root`,
+    /// which yields the archived root type without deserializing anything. The guard keeps the
+    /// bytes pinned, and so the view valid, for as long as it lives. If validation is not needed,
+    /// [`map`](Self::map) works with the unchecked variants just as well. With the `rkyv`
+    /// feature, `ReadGuard::archived` wraps exactly this.
+    ///
+    /// This is an associated function that needs to be used as `ReadGuard::map_validated(...)`,
+    /// since a method would interfere with methods of the same name on the contents of a
+    /// `Readguard` used through `Deref`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use reft_light::{Apply, ReadGuard};
+    ///
+    /// // the data is a serialized buffer, replaced wholesale by every write
+    /// struct Replace(Vec<u8>);
+    /// impl Apply<Vec<u8>, ()> for Replace {
+    ///     fn apply_first(&mut self, first: &mut Vec<u8>, _: &Vec<u8>, _: &mut ()) {
+    ///         first.clone_from(&self.0);
+    ///     }
+    /// }
+    ///
+    /// let mut w = reft_light::new::<Replace, _, _>(vec![], ());
+    /// let r = w.clone();
+    /// w.append(Replace(b"zero-copy".to_vec())).publish();
+    ///
+    /// let view = ReadGuard::map_validated(r.enter().unwrap(), |bytes| std::str::from_utf8(bytes));
+    /// assert_eq!(&*view.unwrap(), "zero-copy");
+    ///
+    /// w.append(Replace(vec![0xff])).publish();
+    /// let view = ReadGuard::map_validated(r.enter().unwrap(), |bytes| std::str::from_utf8(bytes));
+    /// assert!(view.is_err());
+    /// ```
+    pub fn map_validated<F, U: ?Sized, E>(orig: Self, f: F) -> Result<ReadGuard<'rh, U>, E>
+    where
+        F: for<'a> FnOnce(&'a T) -> Result<&'a U, E>,
+    {
+        let rg = ReadGuard {
+            t: f(orig.t)?,
+            meta: orig.meta,
+            handle: orig.handle,
+        };
+        mem::forget(orig);
+        Ok(rg)
+    }
+
+    /// Updates reader-local state from the guarded data, and then drops the guard.
+    ///
+    /// This packages the common pattern of keeping a cache derived from the data up to date on
+    /// the read side: `f` gets to look at the data while the guard pins it, and the guard is
+    /// released as soon as `f` returns, so the update cannot accidentally hold up the writer for
+    /// longer than it takes. Nothing is cloned out of the data.
+    ///
+    /// This is an associated function that needs to be used as `ReadGuard::fold_into(...)`, since
+    /// a method would interfere with methods of the same name on the contents of a `Readguard`
+    /// used through `Deref`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use reft_light::{Apply, ReadGuard};
+    ///
+    /// struct Push(u64);
+    /// impl Apply<Vec<u64>, ()> for Push {
+    ///     fn apply_first(&mut self, first: &mut Vec<u64>, _: &Vec<u64>, _: &mut ()) {
+    ///         first.push(self.0);
+    ///     }
+    /// }
+    ///
+    /// // a running total that only looks at values it has not seen before
+    /// #[derive(Default)]
+    /// struct Total {
+    ///     seen: usize,
+    ///     sum: u64,
+    /// }
+    ///
+    /// let mut w = reft_light::new::<Push, _, _>(vec![], ());
+    /// let r = w.clone();
+    /// let mut total = Total::default();
+    /// let update = |total: &mut Total, values: &Vec<u64>| {
+    ///     total.sum += values[total.seen..].iter().sum::<u64>();
+    ///     total.seen = values.len();
+    /// };
+    ///
+    /// w.extend((1..=3).map(Push));
+    /// w.publish();
+    /// ReadGuard::fold_into(r.enter().unwrap(), &mut total, update);
+    /// assert_eq!(total.sum, 6);
+    ///
+    /// w.extend((4..=5).map(Push));
+    /// w.publish();
+    /// ReadGuard::fold_into(r.enter().unwrap(), &mut total, update);
+    /// assert_eq!(total.sum, 15);
+    /// ```
+    pub fn fold_into<S, F>(guard: Self, state: &mut S, f: F)
+    where
+        F: FnOnce(&mut S, &T),
+    {
+        f(state, guard.t);
+    }
+
+    /// Returns the [`Delta`](crate::Delta) that was published together with the data behind this
+    /// guard.
+    ///
+    /// The delta is the summary [`Apply::summarize`](crate::Apply::summarize) produced for the
+    /// call to [`WriteHandle::publish`](crate::WriteHandle::publish) that exposed this data, so
+    /// the two are always consistent with one another. Returns `None` if no delta was published,
+    /// or if it is not of type `D`.
+    ///
+    /// This is an associated function that needs to be used as `ReadGuard::delta(...)`, since
+    /// a method would interfere with methods of the same name on the contents of a `Readguard`
+    /// used through `Deref`.
+    pub fn delta<D>(guard: &Self) -> Option<Arc<D>>
+    where
+        D: Any + Send + Sync,
+    {
+        Arc::clone(guard.meta.delta.as_ref()?).downcast().ok()
+    }
+
+    /// Returns how long ago the data behind this guard was published.
+    ///
+    /// The timestamp is recorded by [`WriteHandle::publish`](crate::WriteHandle::publish) and
+    /// travels with the data, just like the [`delta`](Self::delta), so it is always the time at
+    /// which this exact copy became visible. If nothing has been published yet, the age is
+    /// measured from when the left-right was created.
+    ///
+    /// Note that the age keeps growing for as long as the guard is held, and that a reader can
+    /// only tell how stale its view is, not whether the writer has unpublished operations.
+    ///
+    /// This is an associated function that needs to be used as `ReadGuard::data_age(...)`, since
+    /// a method would interfere with methods of the same name on the contents of a `Readguard`
+    /// used through `Deref`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use reft_light::{Apply, ReadGuard};
+    /// use std::time::Duration;
+    ///
+    /// struct Add(u64);
+    /// impl Apply<u64, ()> for Add {
+    ///     fn apply_first(&mut self, first: &mut u64, _: &u64, _: &mut ()) {
+    ///         *first += self.0;
+    ///     }
+    /// }
+    ///
+    /// let mut w = reft_light::new::<Add, _, _>(0, ());
+    /// let r = w.clone();
+    /// w.append(Add(1)).publish();
+    ///
+    /// std::thread::sleep(Duration::from_millis(10));
+    /// let guard = r.enter().unwrap();
+    /// assert!(ReadGuard::data_age(&guard) >= Duration::from_millis(10));
+    /// ```
+    pub fn data_age(guard: &Self) -> Duration {
+        guard.meta.published_at.elapsed()
+    }
+
+    /// Returns the two contiguous segments of the guarded ring buffer.
+    ///
+    /// The slices borrow the guard, so they remain valid for as long as it lives. See
+    /// [`AsSlices`](crate::AsSlices).
+    ///
+    /// This is an associated function that needs to be used as `ReadGuard::as_slices(...)`, since
+    /// a method would interfere with methods of the same name on the contents of a `Readguard`
+    /// used through `Deref`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use reft_light::{Apply, ReadGuard};
+    /// use std::collections::VecDeque;
+    ///
+    /// // keeps the last three values
+    /// struct Push(u32);
+    /// impl Apply<VecDeque<u32>, ()> for Push {
+    ///     fn apply_first(&mut self, first: &mut VecDeque<u32>, _: &VecDeque<u32>, _: &mut ()) {
+    ///         if first.len() == 3 {
+    ///             first.pop_front();
+    ///         }
+    ///         first.push_back(self.0);
+    ///     }
+    /// }
+    ///
+    /// let mut w = reft_light::new::<Push, _, _>(VecDeque::with_capacity(3), ());
+    /// let r = w.clone();
+    /// w.extend((1..=5).map(Push));
+    /// w.publish();
+    ///
+    /// let guard = r.enter().unwrap();
+    /// let (front, back) = ReadGuard::as_slices(&guard);
+    /// assert_eq!(front.len() + back.len(), 3);
+    /// assert_eq!([front, back].concat(), [3, 4, 5]);
+    /// ```
+    pub fn as_slices(guard: &Self) -> (&[T::Item], &[T::Item])
+    where
+        T: crate::AsSlices,
+    {
+        guard.t.as_slices()
+    }
+}
+
+impl<'rh, T> ReadGuard<'rh, T>
+where
+    T: ?Sized + Borrow<dyn Any + Send + Sync>,
+{
+    /// Makes a new `ReadGuard` for type-erased data, if it is of type `U`.
+    ///
+    /// This lets a left-right hold values of any type, say for a plugin system, and still offer
+    /// typed reads. It works for any `T` that borrows as a `dyn Any + Send + Sync`, such as
+    /// `Box<dyn Any + Send + Sync>` or, since the data has to be `Clone` to be put in a
+    /// left-right, `Arc<dyn Any + Send + Sync>`. Like [`try_map`](Self::try_map), this drops the
+    /// guard if the data is not a `U`, and otherwise keeps the epoch pinned for as long as the
+    /// returned guard lives.
+    ///
+    /// This is an associated function that needs to be used as `ReadGuard::downcast_ref(...)`,
+    /// since a method would interfere with methods of the same name on the contents of a
+    /// `Readguard` used through `Deref`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use reft_light::{Apply, ReadGuard};
+    /// use std::any::Any;
+    /// use std::sync::Arc;
+    ///
+    /// type Plugin = Arc<dyn Any + Send + Sync>;
+    ///
+    /// struct Replace(Plugin);
+    /// impl Apply<Plugin, ()> for Replace {
+    ///     fn apply_first(&mut self, first: &mut Plugin, _: &Plugin, _: &mut ()) {
+    ///         *first = Arc::clone(&self.0);
+    ///     }
+    /// }
+    ///
+    /// let mut w = reft_light::new::<Replace, Plugin, _>(Arc::new(42u64), ());
+    /// let r = w.clone();
+    /// assert_eq!(ReadGuard::downcast_ref::<u64>(r.enter().unwrap()).as_deref(), Some(&42));
+    ///
+    /// w.append(Replace(Arc::new(String::from("hello")))).publish();
+    /// assert!(ReadGuard::downcast_ref::<u64>(r.enter().unwrap()).is_none());
+    /// let greeting = ReadGuard::downcast_ref::<String>(r.enter().unwrap()).unwrap();
+    /// assert_eq!(&*greeting, "hello");
+    /// ```
+    pub fn downcast_ref<U>(guard: Self) -> Option<ReadGuard<'rh, U>>
+    where
+        U: Any,
+    {
+        ReadGuard::try_map(guard, |t| t.borrow().downcast_ref::<U>())
+    }
 }
 
 impl<'rh, T: ?Sized> AsRef<T> for ReadGuard<'rh, T> {
@@ -121,6 +403,128 @@ impl<'rh, T: ?Sized> Drop for ReadGuard<'rh, T> {
         if enters == 0 {
             // We are the last guard to be dropped -- now release our epoch.
             self.handle.epoch.fetch_add(1, Ordering::AcqRel);
+            self.handle.published.reader_departed();
         }
     }
 }
+
+/// A guard wrapping a live reference into a left-right protected `T` that can be sent across
+/// threads.
+///
+/// This is the `Send` flavor of [`ReadGuard`], produced by
+/// [`ReadHandle::enter_send`](crate::ReadHandle::enter_send). Just like a `ReadGuard`, it blocks
+/// [`WriteHandle::publish`](crate::WriteHandle::publish) for as long as it lives, no matter which
+/// thread it lives on.
+#[derive(Debug)]
+pub struct SendReadGuard<'rh, T: ?Sized> {
+    pub(super) t: &'rh T,
+    pub(super) meta: &'rh Meta,
+    pub(super) epoch: &'rh AtomicUsize,
+    pub(super) published: &'rh crate::Published,
+}
+
+impl<'rh, T: ?Sized> SendReadGuard<'rh, T> {
+    /// Makes a new `SendReadGuard` for a component of the borrowed data.
+    ///
+    /// See [`ReadGuard::map`].
+    pub fn map<F, U: ?Sized>(orig: Self, f: F) -> SendReadGuard<'rh, U>
+    where
+        F: for<'a> FnOnce(&'a T) -> &'a U,
+    {
+        let rg = SendReadGuard {
+            t: f(orig.t),
+            meta: orig.meta,
+            epoch: orig.epoch,
+            published: orig.published,
+        };
+        mem::forget(orig);
+        rg
+    }
+
+    /// Returns the [`Delta`](crate::Delta) that was published together with the data behind this
+    /// guard.
+    ///
+    /// See [`ReadGuard::delta`].
+    pub fn delta<D>(guard: &Self) -> Option<Arc<D>>
+    where
+        D: Any + Send + Sync,
+    {
+        Arc::clone(guard.meta.delta.as_ref()?).downcast().ok()
+    }
+
+    /// Returns how long ago the data behind this guard was published.
+    ///
+    /// See [`ReadGuard::data_age`].
+    pub fn data_age(guard: &Self) -> Duration {
+        guard.meta.published_at.elapsed()
+    }
+}
+
+impl<'rh, T: ?Sized> AsRef<T> for SendReadGuard<'rh, T> {
+    fn as_ref(&self) -> &T {
+        self.t
+    }
+}
+
+impl<'rh, T: ?Sized> std::ops::Deref for SendReadGuard<'rh, T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        self.t
+    }
+}
+
+impl<'rh, T: ?Sized> Drop for SendReadGuard<'rh, T> {
+    fn drop(&mut self) {
+        // the handle is mutably borrowed for as long as we live, so we are the only guard.
+        self.epoch.fetch_add(1, Ordering::AcqRel);
+        self.published.reader_departed();
+    }
+}
+
+/// `ReadGuard` cannot be held across an `.await` in a future that must be `Send`:
+///
+/// ```compile_fail
+/// use reft_light::ReadHandle;
+///
+/// fn is_send<F: Send>(_: F) {
+///   // dummy function just used for its parameterized type bound
+/// }
+///
+/// fn spawn_reader(r: ReadHandle<u64>) {
+///     is_send(async move {
+///         let guard = r.enter();
+///         std::future::ready(()).await;
+///         drop(guard);
+///     });
+/// }
+/// ```
+///
+/// But a `SendReadGuard` can:
+///
+/// ```
+/// use reft_light::ReadHandle;
+///
+/// fn is_send<F: Send>(_: F) {
+///   // dummy function just used for its parameterized type bound
+/// }
+///
+/// fn spawn_reader(mut r: ReadHandle<u64>) {
+///     is_send(async move {
+///         let guard = r.enter_send();
+///         std::future::ready(()).await;
+///         drop(guard);
+///     });
+/// }
+/// ```
+///
+/// As long as the wrapped type is `Sync`:
+///
+/// ```compile_fail
+/// use reft_light::SendReadGuard;
+///
+/// fn is_send<T: Send>() {}
+///
+/// is_send::<SendReadGuard<'static, std::cell::Cell<u64>>>()
+/// ```
+#[allow(dead_code)]
+struct CheckReadGuardSend;
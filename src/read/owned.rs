@@ -0,0 +1,178 @@
+use super::ReadGuard;
+use crate::Meta;
+use std::fmt;
+use std::mem;
+use std::ops::Deref;
+use std::ptr::NonNull;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A guarded live reference into a left-right protected `T` that does not borrow its
+/// [`ReadHandle`](crate::ReadHandle).
+///
+/// Produced by [`ReadGuard::into_owned`]. An `OwnedReadGuard` is `'static`, and is `Send` and
+/// `Sync` whenever `T` is `Sync`, so it can be sent over a channel to be processed by another
+/// thread without cloning the data it refers to.
+///
+/// Just like a [`ReadGuard`], it keeps the copy it refers to from changing for as long as it
+/// lives, and so holds up [`WriteHandle::publish`](crate::WriteHandle::publish) until it is
+/// dropped. Keep owned guards short-lived, and make sure they do not end up forgotten at the far
+/// end of a channel.
+///
+/// # Examples
+///
+/// ```
+/// use reft_light::{Apply, OwnedReadGuard, ReadGuard};
+/// use std::sync::mpsc;
+///
+/// struct Push(i32);
+/// impl Apply<Vec<i32>, ()> for Push {
+///     fn apply_first(&mut self, first: &mut Vec<i32>, _: &Vec<i32>, _: &mut ()) {
+///         first.push(self.0);
+///     }
+/// }
+///
+/// let mut w = reft_light::new::<Push, _, _>(vec![], ());
+/// let r = w.clone();
+/// w.append(Push(1)).append(Push(2)).publish();
+///
+/// let (tx, rx) = mpsc::channel::<OwnedReadGuard<[i32]>>();
+/// let worker = std::thread::spawn(move || rx.recv().unwrap().iter().sum::<i32>());
+///
+/// let guard = ReadGuard::map(r.enter().unwrap(), |v| &v[..]);
+/// tx.send(ReadGuard::into_owned(guard)).unwrap();
+///
+/// // the handle the guard came from remains usable in the meantime
+/// assert_eq!(r.enter().unwrap().len(), 2);
+/// assert_eq!(worker.join().unwrap(), 3);
+/// ```
+pub struct OwnedReadGuard<T: ?Sized> {
+    t: NonNull<T>,
+    meta: NonNull<Meta>,
+    pins: crate::Pins,
+    pin_i: usize,
+    published: crate::Published,
+}
+
+// safety: we only ever hand out shared references to the T, and the pin may be released from any
+// thread.
+unsafe impl<T: ?Sized + Sync> Send for OwnedReadGuard<T> {}
+unsafe impl<T: ?Sized + Sync> Sync for OwnedReadGuard<T> {}
+
+impl<'rh, T: ?Sized> ReadGuard<'rh, T> {
+    /// Converts this guard into one that does not borrow its [`ReadHandle`](crate::ReadHandle).
+    ///
+    /// The returned [`OwnedReadGuard`] refers to exactly the same data as this guard did. See its
+    /// documentation for details.
+    ///
+    /// This is an associated function that needs to be used as `ReadGuard::into_owned(...)`, since
+    /// a method would interfere with methods of the same name on the contents of a `Readguard`
+    /// used through `Deref`.
+    pub fn into_owned(guard: Self) -> OwnedReadGuard<T> {
+        // pin the copy before our epoch lets go of it. the writer checks for pins on a copy only
+        // after the epochs of its readers have moved on, and our epoch has not moved on yet.
+        let pin_i = crate::lock_pins(guard.handle.pins).insert(guard.meta as *const Meta as usize);
+        OwnedReadGuard {
+            t: NonNull::from(guard.t),
+            meta: NonNull::from(guard.meta),
+            pins: crate::sync::Arc::clone(guard.handle.pins),
+            pin_i,
+            published: crate::sync::Arc::clone(guard.handle.published),
+        }
+    }
+}
+
+impl<T: ?Sized> OwnedReadGuard<T> {
+    /// Makes a new `OwnedReadGuard` for a component of the borrowed data.
+    ///
+    /// See [`ReadGuard::map`].
+    pub fn map<F, U: ?Sized>(orig: Self, f: F) -> OwnedReadGuard<U>
+    where
+        F: for<'a> FnOnce(&'a T) -> &'a U,
+    {
+        let rg = OwnedReadGuard {
+            t: NonNull::from(f(&*orig)),
+            meta: orig.meta,
+            pins: crate::sync::Arc::clone(&orig.pins),
+            pin_i: orig.pin_i,
+            published: crate::sync::Arc::clone(&orig.published),
+        };
+        // the new guard has taken over our pin.
+        let orig = mem::ManuallyDrop::new(orig);
+        // safety: we never touch `orig` again.
+        drop(unsafe { std::ptr::read(&orig.pins) });
+        drop(unsafe { std::ptr::read(&orig.published) });
+        rg
+    }
+
+    /// Returns the [`Delta`](crate::Delta) that was published together with the data behind this
+    /// guard.
+    ///
+    /// See [`ReadGuard::delta`].
+    pub fn delta<D>(guard: &Self) -> Option<Arc<D>>
+    where
+        D: std::any::Any + Send + Sync,
+    {
+        // safety: the pin keeps the copy, and so its metadata, alive.
+        let meta = unsafe { guard.meta.as_ref() };
+        Arc::clone(meta.delta.as_ref()?).downcast().ok()
+    }
+
+    /// Returns how long ago the data behind this guard was published.
+    ///
+    /// See [`ReadGuard::data_age`].
+    pub fn data_age(guard: &Self) -> Duration {
+        // safety: the pin keeps the copy, and so its metadata, alive.
+        unsafe { guard.meta.as_ref() }.published_at.elapsed()
+    }
+}
+
+impl<T: ?Sized> Deref for OwnedReadGuard<T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        // safety: the pin keeps the writer from modifying or freeing the copy until we are
+        // dropped.
+        unsafe { self.t.as_ref() }
+    }
+}
+
+impl<T: ?Sized> AsRef<T> for OwnedReadGuard<T> {
+    fn as_ref(&self) -> &T {
+        self
+    }
+}
+
+impl<T: ?Sized> Drop for OwnedReadGuard<T> {
+    fn drop(&mut self) {
+        crate::lock_pins(&self.pins).remove(self.pin_i);
+        self.published.reader_departed();
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for OwnedReadGuard<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("OwnedReadGuard").field(&&**self).finish()
+    }
+}
+
+/// `OwnedReadGuard` can be sent across threads:
+///
+/// ```
+/// use reft_light::OwnedReadGuard;
+///
+/// fn is_send<T: Send + 'static>() {}
+///
+/// is_send::<OwnedReadGuard<Vec<u64>>>()
+/// ```
+///
+/// But only if the data it refers to can be shared:
+///
+/// ```compile_fail
+/// use reft_light::OwnedReadGuard;
+///
+/// fn is_send<T: Send>() {}
+///
+/// is_send::<OwnedReadGuard<std::cell::Cell<u64>>>()
+/// ```
+#[allow(dead_code)]
+struct CheckOwnedReadGuardSend;
@@ -0,0 +1,31 @@
+use super::ReadHandle;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// A future that resolves once a publish has exposed the data of a given generation.
+///
+/// Produced by [`ReadHandle::wait_for_generation_async`], and driven by
+/// [`ReadHandle::poll_generation`]. Resolves to the same value that
+/// [`ReadHandle::wait_for_generation`] returns.
+pub struct WaitForGeneration<'rh, T> {
+    pub(super) handle: &'rh ReadHandle<T>,
+    pub(super) generation: u64,
+}
+
+impl<'rh, T> Future for WaitForGeneration<'rh, T> {
+    type Output = bool;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.handle.poll_generation(cx, self.generation)
+    }
+}
+
+impl<'rh, T> fmt::Debug for WaitForGeneration<'rh, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WaitForGeneration")
+            .field("generation", &self.generation)
+            .finish()
+    }
+}
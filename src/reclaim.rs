@@ -0,0 +1,91 @@
+use std::fmt;
+use std::time::Instant;
+
+/// Collects values that operations remove from the data, so that they can be dropped later.
+///
+/// Applying an operation during [`WriteHandle::publish`](crate::WriteHandle::publish) may drop
+/// large allocations, like a value removed from a map. Dropping those inline adds to the latency
+/// of `publish`. An operation that implements
+/// [`Apply::apply_first_deferred`](crate::Apply::apply_first_deferred) and
+/// [`Apply::apply_second_deferred`](crate::Apply::apply_second_deferred) can instead hand what it
+/// removes to [`defer`](Self::defer). Once the publish has exposed the new data to readers, the
+/// deferred values are passed as a single [`Reclaimed`] batch to the reclaimer set with
+/// [`WriteHandle::set_reclaimer`](crate::WriteHandle::set_reclaimer), which can send them off to
+/// be dropped on another thread. Without a reclaimer, they are dropped at the end of the publish.
+///
+/// A `Reclaim` also carries the time at which the writer started applying the current batch, see
+/// [`now`](Self::now).
+#[derive(Default)]
+pub struct Reclaim {
+    garbage: Vec<Box<dyn Send>>,
+    now: Option<Instant>,
+}
+
+impl Reclaim {
+    /// Defer dropping `garbage` until the current publish is over.
+    pub fn defer<G>(&mut self, garbage: G)
+    where
+        G: Send + 'static,
+    {
+        self.garbage.push(Box::new(garbage));
+    }
+
+    /// Returns the time at which the writer started applying the current batch of operations.
+    ///
+    /// The time is captured once per batch, so every operation in the batch sees the same value,
+    /// no matter how long applying the batch takes. See [`Expiring`](crate::Expiring) for an
+    /// operation that uses it.
+    pub fn now(&self) -> Instant {
+        self.now.unwrap_or_else(Instant::now)
+    }
+
+    pub(crate) fn start_batch(&mut self) {
+        self.now = Some(Instant::now());
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.garbage.is_empty()
+    }
+
+    pub(crate) fn take(&mut self) -> Reclaimed {
+        Reclaimed {
+            garbage: std::mem::take(&mut self.garbage),
+        }
+    }
+}
+
+impl fmt::Debug for Reclaim {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Reclaim")
+            .field("deferred", &self.garbage.len())
+            .field("now", &self.now)
+            .finish()
+    }
+}
+
+/// A batch of values deferred during one publish, which are dropped when the batch is dropped.
+///
+/// See [`Reclaim`].
+pub struct Reclaimed {
+    garbage: Vec<Box<dyn Send>>,
+}
+
+impl Reclaimed {
+    /// Returns the number of values in the batch.
+    pub fn len(&self) -> usize {
+        self.garbage.len()
+    }
+
+    /// Returns true if the batch holds no values.
+    pub fn is_empty(&self) -> bool {
+        self.garbage.is_empty()
+    }
+}
+
+impl fmt::Debug for Reclaimed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Reclaimed")
+            .field("len", &self.garbage.len())
+            .finish()
+    }
+}
@@ -0,0 +1,30 @@
+use std::collections::VecDeque;
+
+/// Data structures that store their elements in at most two contiguous segments.
+///
+/// Used by [`ReadGuard::as_slices`](crate::ReadGuard::as_slices) to hand out the segments of a
+/// guarded ring buffer without cloning. Types that wrap a [`VecDeque`] can implement this by
+/// forwarding to it.
+pub trait AsSlices {
+    /// The type of the elements.
+    type Item;
+
+    /// Returns the elements as two slices, which together hold every element in order.
+    fn as_slices(&self) -> (&[Self::Item], &[Self::Item]);
+}
+
+impl<T> AsSlices for VecDeque<T> {
+    type Item = T;
+
+    fn as_slices(&self) -> (&[T], &[T]) {
+        VecDeque::as_slices(self)
+    }
+}
+
+impl<T> AsSlices for Vec<T> {
+    type Item = T;
+
+    fn as_slices(&self) -> (&[T], &[T]) {
+        (self, &[])
+    }
+}
@@ -0,0 +1,83 @@
+use std::thread;
+use std::time::Duration;
+
+/// A strategy for how the writer should wait for readers to depart.
+///
+/// Before [`WriteHandle::publish`](crate::WriteHandle::publish) can modify the copy that readers
+/// were using before the last publish, it has to wait for all of those readers to finish their
+/// reads. The writer repeatedly scans the readers' epochs, and calls [`pause`](Self::pause) every
+/// time it finds a reader that may still be using the old copy, before scanning again.
+///
+/// The default strategy is [`SpinThenYield`]. Use
+/// [`WriteHandle::set_wait_strategy`](crate::WriteHandle::set_wait_strategy) to pick another one.
+pub trait WaitStrategy {
+    /// Pause before the writer scans the readers' epochs again.
+    ///
+    /// `retries` is the number of times the writer has already paused during the current wait,
+    /// so it is `0` on the first call of every wait.
+    fn pause(&mut self, retries: usize);
+}
+
+impl<F> WaitStrategy for F
+where
+    F: FnMut(usize),
+{
+    fn pause(&mut self, retries: usize) {
+        self(retries)
+    }
+}
+
+/// Retry immediately for a few rounds, then yield the thread between retries.
+///
+/// This is the default [`WaitStrategy`]. It keeps publish latency low when readers are quick, but
+/// burns a full core for as long as a slow reader holds on to the old copy.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SpinThenYield;
+
+impl WaitStrategy for SpinThenYield {
+    fn pause(&mut self, retries: usize) {
+        // how eagerly should we retry?
+        if retries >= 20 {
+            thread::yield_now();
+        }
+    }
+}
+
+/// Spin for a few rounds, then sleep between retries for exponentially longer periods.
+///
+/// The writer first spins for `max_spin` retries. After that it sleeps, starting at one
+/// microsecond and doubling the sleep on every retry until it reaches `max_sleep`. This is a
+/// middle ground between [`SpinThenYield`] and fully parking the writer: it uses far less CPU
+/// while waiting for a genuinely slow reader, at the cost of noticing that the reader has departed
+/// up to `max_sleep` late.
+#[derive(Debug, Clone, Copy)]
+pub struct ExponentialBackoff {
+    /// The number of retries to spin for before starting to sleep.
+    pub max_spin: usize,
+    /// The longest the writer sleeps between two retries.
+    pub max_sleep: Duration,
+}
+
+impl Default for ExponentialBackoff {
+    fn default() -> Self {
+        Self {
+            max_spin: 64,
+            max_sleep: Duration::from_millis(1),
+        }
+    }
+}
+
+impl WaitStrategy for ExponentialBackoff {
+    fn pause(&mut self, retries: usize) {
+        if retries < self.max_spin {
+            // std::hint::spin_loop supersedes this, but is not available on our MSRV
+            #[allow(deprecated)]
+            std::sync::atomic::spin_loop_hint();
+            return;
+        }
+
+        // cap the exponent so the shift cannot overflow; 2^20µs is already more than a second.
+        let exp = (retries - self.max_spin).min(20) as u32;
+        thread::sleep(Duration::from_micros(1 << exp).min(self.max_sleep));
+    }
+}
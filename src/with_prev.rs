@@ -0,0 +1,124 @@
+use crate::Apply;
+use std::fmt;
+
+/// Operations that compute their update from the stale read copy, and replay that exact update
+/// on the other copy.
+///
+/// [`Apply::apply_first`] receives the stale read copy as `second`, which is one publish behind
+/// the copy being modified. By the time the same operation is applied to the other copy, what
+/// was the stale read copy has become the copy being modified, and the other copy has moved one
+/// publish ahead. An operation that reads from the other copy, like one that copies a value
+/// forward, therefore sees different data in its two applications, and the copies drift apart.
+///
+/// Implement this trait instead, and append the operations wrapped in [`WithPrev`]. The value
+/// returned by [`apply_first_with_prev`](Self::apply_first_with_prev) is kept in the wrapper,
+/// and handed back to [`apply_second_with_computed`](Self::apply_second_with_computed), so the
+/// second application reproduces exactly what the first one computed.
+///
+/// # Examples
+///
+/// ```
+/// use reft_light::{ApplyWithPrev, WithPrev};
+///
+/// // appends the length the data had when readers last saw it
+/// struct PushPrevLen;
+/// impl ApplyWithPrev<Vec<usize>, ()> for PushPrevLen {
+///     type Computed = usize;
+///     fn apply_first_with_prev(&mut self, first: &mut Vec<usize>, prev_read: &Vec<usize>, _: &mut ()) -> usize {
+///         first.push(prev_read.len());
+///         prev_read.len()
+///     }
+///     fn apply_second_with_computed(self, len: usize, _: &Vec<usize>, second: &mut Vec<usize>, _: &mut ()) {
+///         second.push(len);
+///     }
+/// }
+///
+/// let mut w = reft_light::new_checked::<WithPrev<PushPrevLen, usize>, _, _>(vec![], ());
+/// let r = w.clone();
+/// w.append(WithPrev::new(PushPrevLen)).append(WithPrev::new(PushPrevLen));
+/// w.publish();
+/// w.append(WithPrev::new(PushPrevLen)).publish();
+/// // the copies are checked against each other once both have seen the same operations
+/// w.publish();
+/// assert_eq!(*r.enter().unwrap(), [0, 0, 2]);
+/// ```
+pub trait ApplyWithPrev<T, A>: Sized {
+    /// What the first application computed, to be reused by the second application.
+    type Computed;
+
+    /// Apply the operation to the first of the two copies.
+    ///
+    /// `prev_read` is the stale read copy, which has seen all operations up until the previous
+    /// publish, just like the `second` argument of [`Apply::apply_first`].
+    fn apply_first_with_prev(
+        &mut self,
+        first: &mut T,
+        prev_read: &T,
+        auxiliary: &mut A,
+    ) -> Self::Computed;
+
+    /// Apply the operation to the second of the two copies, using what the first application
+    /// computed.
+    ///
+    /// This must modify `second` in exactly the same way as the first application modified the
+    /// first copy. `first` is the other copy, which is one publish ahead.
+    fn apply_second_with_computed(
+        self,
+        computed: Self::Computed,
+        first: &T,
+        second: &mut T,
+        auxiliary: &mut A,
+    );
+}
+
+/// Wraps an [`ApplyWithPrev`] operation so that it can be appended to a
+/// [`WriteHandle`](crate::WriteHandle).
+///
+/// `C` is the operation's [`Computed`](ApplyWithPrev::Computed) type, which the wrapper holds on
+/// to between the two applications. See [`ApplyWithPrev`].
+pub struct WithPrev<O, C> {
+    op: O,
+    computed: Option<C>,
+}
+
+impl<O, C> WithPrev<O, C> {
+    /// Wrap `op`.
+    pub fn new(op: O) -> Self {
+        Self { op, computed: None }
+    }
+
+    /// Returns the wrapped operation.
+    pub fn into_inner(self) -> O {
+        self.op
+    }
+}
+
+impl<O, T, A> Apply<T, A> for WithPrev<O, O::Computed>
+where
+    O: ApplyWithPrev<T, A>,
+{
+    fn apply_first(&mut self, first: &mut T, second: &T, auxiliary: &mut A) {
+        self.computed = Some(self.op.apply_first_with_prev(first, second, auxiliary));
+    }
+
+    fn apply_second(self, first: &T, second: &mut T, auxiliary: &mut A) {
+        let computed = self
+            .computed
+            .expect("every operation is applied to the first copy before the second");
+        self.op
+            .apply_second_with_computed(computed, first, second, auxiliary);
+    }
+}
+
+impl<O, C> fmt::Debug for WithPrev<O, C>
+where
+    O: fmt::Debug,
+    C: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WithPrev")
+            .field("op", &self.op)
+            .field("computed", &self.computed)
+            .finish()
+    }
+}
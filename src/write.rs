@@ -1,13 +1,57 @@
-use crate::read::ReadHandle;
-use crate::Apply;
+use crate::partition::ParallelApply;
+use crate::read::{Fresh, FreshCopy, ReadHandle};
+use crate::{
+    Apply, ApplyWithId, HasLen, Identified, OplogStore, Reclaim, Reclaimed, ShrinkPolicy, Slot,
+    SpinThenYield, WaitStrategy,
+};
 
 use crate::sync::{fence, Arc, AtomicUsize, MutexGuard, Ordering};
 use std::collections::VecDeque;
+use std::marker::PhantomData;
 use std::ptr::NonNull;
 #[cfg(test)]
 use std::sync::atomic::AtomicBool;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
 use std::{fmt, thread};
 
+mod group;
+pub use group::PublishGroup;
+
+mod batch;
+pub use batch::BatchTrend;
+
+mod local;
+pub use local::LocalWriteHandle;
+
+mod order;
+pub use order::ApplyOrder;
+
+mod unsync;
+pub use unsync::UnsyncWriteHandle;
+
+#[cfg(feature = "metrics")]
+mod high_water;
+#[cfg(feature = "metrics")]
+pub use high_water::HighWaterMarks;
+
+#[cfg(feature = "metrics")]
+mod timings;
+#[cfg(feature = "metrics")]
+pub use timings::PhaseTimings;
+
+mod pending;
+pub use pending::PendingPublish;
+
+mod poll;
+pub use poll::PublishFuture;
+
+mod rejected;
+pub use rejected::Rejected;
+
+mod transaction;
+pub use transaction::Transaction;
+
 /// A writer handle to a left-right guarded data structure.
 ///
 /// All operations on the underlying data should be enqueued as operations of type `O` using
@@ -19,18 +63,58 @@ use std::{fmt, thread};
 /// `WriteHandle` allows access to a [`ReadHandle`] through `Deref<Target = ReadHandle>`. Note that
 /// since the reads go through a [`ReadHandle`], those reads are subject to the same visibility
 /// restrictions as reads that do not go through the `WriteHandle`: they only see the effects of
-/// operations prior to the last call to [`publish`](Self::publish).
-pub struct WriteHandle<O, T, A>
+/// operations prior to the last call to [`publish`](Self::publish). To read the effects of
+/// operations that have not been published yet, use [`current`](Self::current).
+pub struct WriteHandle<O, T, A, S = VecDeque<O>>
 where
     O: Apply<T, A>,
+    S: OplogStore<O>,
 {
     epochs: crate::Epochs,
-    w_handle: NonNull<T>,
-    oplog: VecDeque<O>,
+    w_handle: NonNull<Slot<T>>,
+    oplog: S,
+    // the operations are owned by the oplog
+    _ops: PhantomData<O>,
     swap_index: usize,
+    // the number of operations following swap_index that have been applied to w_handle
+    applied: usize,
+    // reused buffer for the runs of homogeneous operations drained from the oplog
+    run: Vec<O>,
+    order: ApplyOrder,
+    // reused buffer for the indices of the operations applied in LIFO order
+    kept: Vec<usize>,
+    // the id for the next operation appended with append_with_id
+    next_id: u64,
     r_handle: ReadHandle<T>,
-    last_epochs: Vec<usize>,
+    // the readers that were in the middle of a read when we last swapped, along with the epoch
+    // they were at. only these may still be using w_handle.
+    active: Vec<(usize, usize)>,
+    wait_strategy: Box<dyn WaitStrategy + Send>,
+    check_copies: Option<fn(&T, &T) -> bool>,
+    // the lock fresh readers share with us, if they are enabled
+    fresh: Option<Fresh<T>>,
+    parallel: Option<ParallelApply<O, T>>,
+    strict: bool,
+    // set while operations are being applied, so that it stays set if one of them panics. the
+    // copies may then have seen different operations, and must never be published again.
+    poisoned: bool,
+    shrink_policy: ShrinkPolicy,
+    reclaim: Reclaim,
+    reclaimer: Option<Box<dyn FnMut(Reclaimed) + Send>>,
     auxiliary: A,
+    batches: BatchTrend,
+    last_publish: Instant,
+    #[cfg(feature = "metrics")]
+    timings: PhaseTimings,
+    // the phases of the publish in progress, which become `timings` once it swaps the copies
+    #[cfg(feature = "metrics")]
+    publishing: PhaseTimings,
+    #[cfg(feature = "metrics")]
+    high_water: HighWaterMarks,
+    #[cfg(feature = "metrics")]
+    wait_estimate: Option<Duration>,
+    #[cfg(debug_assertions)]
+    owner: Option<thread::ThreadId>,
     #[cfg(test)]
     refreshes: usize,
     #[cfg(test)]
@@ -40,21 +124,24 @@ where
 // safety: if a `WriteHandle` is sent across a thread boundary, we need to be able to take
 // ownership of both Ts and Os across that thread boundary. since `WriteHandle` holds a
 // `ReadHandle`, we also need to respect its Send requirements.
-unsafe impl<O, T, A> Send for WriteHandle<O, T, A>
+unsafe impl<O, T, A, S> Send for WriteHandle<O, T, A, S>
 where
     O: Apply<T, A>,
+    S: OplogStore<O>,
     T: Send,
     O: Send,
     A: Send,
+    S: Send,
     ReadHandle<T>: Send,
 {
 }
 
-impl<O, T, A> fmt::Debug for WriteHandle<O, T, A>
+impl<O, T, A, S> fmt::Debug for WriteHandle<O, T, A, S>
 where
     O: Apply<T, A> + fmt::Debug,
     O: fmt::Debug,
     A: fmt::Debug,
+    S: OplogStore<O> + fmt::Debug,
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("WriteHandle")
@@ -68,63 +155,104 @@ where
     }
 }
 
-impl<O, T, A> Drop for WriteHandle<O, T, A>
+fn conflicting<O, T, A>(a: &O, b: &O) -> bool
 where
     O: Apply<T, A>,
 {
-    fn drop(&mut self) {
-        use std::ptr;
-        // first, ensure the read handle is up-to-date with all operations
-        if self.swap_index != self.oplog.len() {
-            self.publish();
-        }
-
-        // next, grab the read handle and set it to NULL
-        let r_handle = self.r_handle.inner.swap(ptr::null_mut(), Ordering::Release);
-
-        // now, wait for all readers to depart
-        let epochs = Arc::clone(&self.epochs);
-        let mut epochs = epochs.lock().unwrap();
-        self.wait(&mut epochs);
-
-        // ensure that the subsequent epoch reads aren't re-ordered to before the swap
-        fence(Ordering::SeqCst);
+    a.conflicts_with(b) || b.conflicts_with(a)
+}
 
-        // all readers have now observed the NULL, so we own both handles.
-        // all operations have been applied to the r_handle.
-        //
-        // safety: w_handle was initially crated from a `Box`, and is no longer aliased.
-        drop(unsafe { Box::from_raw(self.w_handle.as_ptr()) });
+/// Stably move every operation back to the nearest earlier operation it is homogeneous with, as
+/// long as it does not conflict with any of the operations it moves past.
+fn group_homogeneous<O, T, A>(ops: &mut [O])
+where
+    O: Apply<T, A>,
+{
+    for i in 1..ops.len() {
+        for j in (0..i).rev() {
+            if ops[j].is_homogeneous_with(&ops[i]) {
+                // join the run that ends at j
+                ops[j + 1..=i].rotate_right(1);
+                break;
+            }
+            if conflicting(&ops[j], &ops[i]) {
+                break;
+            }
+        }
+    }
+}
 
-        // next we take the r_handle and return it as a boxed value.
-        //
-        // this is safe, since we know that no readers are using this pointer
-        // anymore (due to the .wait() following swapping the pointer with NULL).
-        //
-        // safety: r_handle was initially crated from a `Box`, and is no longer aliased.
-        drop(unsafe { Box::from_raw(r_handle) });
+impl<O, T, A, S> Drop for WriteHandle<O, T, A, S>
+where
+    O: Apply<T, A>,
+    S: OplogStore<O>,
+{
+    fn drop(&mut self) {
+        // dropping the handle is not a write, so it may happen on any thread.
+        self.release_owner();
+        let forgotten = self.strict && self.has_pending_operations();
+        // a poisoned handle cannot publish, and only has its copies left to free
+        drop(self.retire(!forgotten && !self.poisoned));
+        // don't pile a second panic onto one that is already unwinding
+        if forgotten && !thread::panicking() {
+            panic!(
+                "strict WriteHandle dropped with unpublished operations; \
+                 call publish or flush before dropping it"
+            );
+        }
     }
 }
 
-impl<O, T, A> WriteHandle<O, T, A>
+impl<O, T, A, S> WriteHandle<O, T, A, S>
 where
     O: Apply<T, A>,
+    S: OplogStore<O>,
 {
     pub(crate) fn new(
         w_handle: T,
         epochs: crate::Epochs,
         r_handle: ReadHandle<T>,
         auxiliary: A,
+        oplog: S,
     ) -> Self {
         Self {
             epochs,
             // safety: Box<T> is not null and covariant.
-            w_handle: unsafe { NonNull::new_unchecked(Box::into_raw(Box::new(w_handle))) },
-            oplog: VecDeque::new(),
+            w_handle: unsafe {
+                NonNull::new_unchecked(Box::into_raw(Box::new(Slot::new(w_handle))))
+            },
+            oplog,
+            _ops: PhantomData,
             swap_index: 0,
+            applied: 0,
+            run: Vec::new(),
+            order: ApplyOrder::Fifo,
+            kept: Vec::new(),
+            next_id: 0,
             r_handle,
-            last_epochs: Vec::new(),
+            active: Vec::new(),
+            wait_strategy: Box::new(SpinThenYield),
+            check_copies: None,
+            fresh: None,
+            parallel: None,
+            strict: false,
+            poisoned: false,
+            shrink_policy: ShrinkPolicy::Never,
+            reclaim: Reclaim::default(),
+            reclaimer: None,
             auxiliary,
+            batches: BatchTrend::default(),
+            last_publish: Instant::now(),
+            #[cfg(feature = "metrics")]
+            timings: PhaseTimings::default(),
+            #[cfg(feature = "metrics")]
+            publishing: PhaseTimings::default(),
+            #[cfg(feature = "metrics")]
+            high_water: HighWaterMarks::default(),
+            #[cfg(feature = "metrics")]
+            wait_estimate: None,
+            #[cfg(debug_assertions)]
+            owner: None,
             #[cfg(test)]
             is_waiting: Arc::new(AtomicBool::new(false)),
             #[cfg(test)]
@@ -132,61 +260,71 @@ where
         }
     }
 
+    /// Let readers see the copy that has seen the most operations through
+    /// [`ReadHandle::enter_fresh`].
+    pub(crate) fn fresh_reads(mut self) -> Self {
+        let fresh = Arc::clone(&self.r_handle.fresh);
+        crate::read::lock_fresh(&fresh).0 = self.r_handle.inner.load(Ordering::Acquire);
+        self.fresh = Some(fresh);
+        self
+    }
+
+    /// Make dropping the handle with unpublished operations a bug rather than a final publish.
+    pub(crate) fn strict_drop(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+
+    /// Make every publish apply operations to disjoint partitions of the data in parallel.
+    #[cfg(feature = "parallel-apply")]
+    pub(crate) fn apply_partitioned(mut self) -> Self
+    where
+        O: crate::ApplyPartitioned<T, A>,
+        T: crate::Partitioned,
+    {
+        self.parallel = Some(ParallelApply::new());
+        self
+    }
+
+    /// Make every publish check that the two copies match once both have seen the same operations.
+    pub(crate) fn check_copies_with(mut self, copies_match: fn(&T, &T) -> bool) -> Self {
+        self.check_copies = Some(copies_match);
+        self
+    }
+
     fn wait(&mut self, epochs: &mut MutexGuard<'_, slab::Slab<Arc<AtomicUsize>>>) {
         let mut iter = 0;
-        let mut starti = 0;
+        #[cfg(feature = "metrics")]
+        let start = Instant::now();
 
         #[cfg(test)]
         {
             self.is_waiting.store(true, Ordering::Relaxed);
         }
-        // we're over-estimating here, but slab doesn't expose its max index
-        self.last_epochs.resize(epochs.capacity(), 0);
-        'retry: loop {
-            // read all and see if all have changed (which is likely)
-            for (ii, (ri, epoch)) in epochs.iter().enumerate().skip(starti) {
-                // if the reader's epoch was even last we read it (which was _after_ the swap),
-                // then they either do not have the pointer, or must have read the pointer strictly
-                // after the swap. in either case, they cannot be using the old pointer value (what
-                // is now w_handle).
-                //
-                // note that this holds even with wrap-around since std::u{N}::MAX == 2 ^ N - 1,
-                // which is odd, and std::u{N}::MAX + 1 == 0 is even.
-                //
-                // note also that `ri` _may_ have been re-used since we last read into last_epochs.
-                // this is okay though, as a change still implies that the new reader must have
-                // arrived _after_ we did the atomic swap, and thus must also have seen the new
-                // pointer.
-                if self.last_epochs[ri] % 2 == 0 {
-                    continue;
-                }
+        while !self.readers_departed(epochs, false) {
+            if !cfg!(loom) {
+                self.wait_strategy.pause(iter);
+                iter += 1;
+            }
 
-                let now = epoch.load(Ordering::Acquire);
-                if now != self.last_epochs[ri] {
-                    // reader must have seen the last swap, since they have done at least one
-                    // operation since we last looked at their epoch, which _must_ mean that they
-                    // are no longer using the old pointer value.
-                } else {
-                    // reader may not have seen swap
-                    // continue from this reader's epoch
-                    starti = ii;
-
-                    if !cfg!(loom) {
-                        // how eagerly should we retry?
-                        if iter != 20 {
-                            iter += 1;
-                        } else {
-                            thread::yield_now();
-                        }
-                    }
+            #[cfg(loom)]
+            loom::thread::yield_now();
+        }
 
-                    #[cfg(loom)]
-                    loom::thread::yield_now();
+        // now that no reader can convert a guard on w_handle into an owned guard anymore, wait
+        // for the owned guards that already did.
+        let w_copy = self.w_copy();
+        self.wait_for_pins(|copy| copy == w_copy);
 
-                    continue 'retry;
-                }
-            }
-            break;
+        #[cfg(feature = "metrics")]
+        {
+            let wait = start.elapsed();
+            self.publishing.wait = wait;
+            // an exponentially weighted moving average, with each new wait weighted by 1/4
+            self.wait_estimate = Some(match self.wait_estimate {
+                Some(estimate) => estimate - estimate / 4 + wait / 4,
+                None => wait,
+            });
         }
         #[cfg(test)]
         {
@@ -194,13 +332,141 @@ where
         }
     }
 
+    /// Check, without blocking, whether all readers that may be using w_handle have departed.
+    ///
+    /// Only the readers recorded as active at the last swap are checked, and those that have
+    /// departed are forgotten, so each check only costs as much as there are readers left.
+    ///
+    /// With `latest`, the epochs are read with a read-modify-write, which always sees the latest
+    /// epoch. A writer that is about to sleep until a reader wakes it needs that guarantee, see
+    /// `Generation::reader_departed`. Spinning writers make do with plain loads, which do not
+    /// take the readers' cache lines away from them.
+    #[allow(clippy::unnecessary_map_or)]
+    fn readers_departed(
+        &mut self,
+        epochs: &mut MutexGuard<'_, slab::Slab<Arc<AtomicUsize>>>,
+        latest: bool,
+    ) -> bool {
+        #[cfg(feature = "metrics")]
+        {
+            self.high_water.readers = self.high_water.readers.max(epochs.len());
+        }
+        self.active.retain(|&(ri, last)| {
+            // a reader that has since been dropped cannot be using w_handle anymore.
+            //
+            // note that `ri` _may_ have been re-used since we recorded it. this is okay though,
+            // as a change still implies that the new reader must have arrived _after_ we did the
+            // atomic swap, and thus must also have seen the new pointer.
+            epochs.get(ri).map_or(false, |epoch| {
+                // if the epoch has changed, the reader must have seen the last swap, since they
+                // have done at least one operation since we last looked at their epoch, which
+                // _must_ mean that they are no longer using the old pointer value.
+                let now = if latest {
+                    epoch.fetch_add(0, Ordering::AcqRel)
+                } else {
+                    epoch.load(Ordering::Acquire)
+                };
+                now == last
+            })
+        });
+        self.active.is_empty()
+    }
+
+    /// Record which readers may be using the copy that was just swapped out.
+    ///
+    /// Must be called right after the swap, with a fence in between.
+    #[allow(clippy::manual_is_multiple_of)]
+    fn record_active(&mut self, epochs: &mut MutexGuard<'_, slab::Slab<Arc<AtomicUsize>>>) {
+        self.active.clear();
+        for (ri, epoch) in epochs.iter() {
+            // if the reader's epoch is even now (which is _after_ the swap), then they either do
+            // not have the pointer, or must have read the pointer strictly after the swap. in
+            // either case, they cannot be using the old pointer value.
+            //
+            // note that this holds even with wrap-around since std::u{N}::MAX == 2 ^ N - 1,
+            // which is odd, and std::u{N}::MAX + 1 == 0 is even.
+            let now = epoch.load(Ordering::Acquire);
+            if now % 2 != 0 {
+                self.active.push((ri, now));
+            }
+        }
+    }
+
+    /// Check, without blocking, whether every reader and every owned guard has departed from
+    /// w_handle.
+    ///
+    /// See `readers_departed` for `latest`.
+    fn has_departed(
+        &mut self,
+        epochs: &mut MutexGuard<'_, slab::Slab<Arc<AtomicUsize>>>,
+        latest: bool,
+    ) -> bool {
+        if !self.readers_departed(epochs, latest) {
+            return false;
+        }
+        let w_copy = self.w_copy();
+        !self.is_pinned(|copy| copy == w_copy)
+    }
+
+    /// The address that identifies w_handle in the pins of owned guards.
+    fn w_copy(&self) -> usize {
+        // safety: w_handle is always valid, and readers that may still be using it only ever take
+        // shared references to it, just like this one.
+        let w_handle = unsafe { self.w_handle.as_ref() };
+        &w_handle.meta as *const crate::Meta as usize
+    }
+
+    /// Returns true if an [`OwnedReadGuard`](crate::OwnedReadGuard) pins a copy for which
+    /// `pinned` returns true.
+    fn is_pinned<F>(&self, pinned: F) -> bool
+    where
+        F: Fn(usize) -> bool,
+    {
+        crate::lock_pins(&self.r_handle.pins)
+            .iter()
+            .any(|(_, &copy)| pinned(copy))
+    }
+
+    /// Wait until no [`OwnedReadGuard`](crate::OwnedReadGuard) pins a copy for which `pinned`
+    /// returns true.
+    fn wait_for_pins<F>(&mut self, pinned: F)
+    where
+        F: Fn(usize) -> bool,
+    {
+        let mut iter = 0;
+        while self.is_pinned(&pinned) {
+            if !cfg!(loom) {
+                self.wait_strategy.pause(iter);
+                iter += 1;
+            }
+
+            #[cfg(loom)]
+            loom::thread::yield_now();
+        }
+    }
+
     /// Publish all operations append to the log to reads.
     ///
     /// This method needs to wait for all readers to move to the "other" copy of the data so that
     /// it can replay the operational log onto the stale copy the readers used to use. This can
     /// take some time, especially if readers are executing slow operations, or if there are many
     /// of them.
+    ///
+    /// Every publish swaps the copies, even if no reader has entered since the previous one.
+    /// Readers do not coordinate with the writer when they enter, so one may enter right after
+    /// `publish` returns, and it must find the new data in place; skipping the swap would leave
+    /// it reading what the previous publish exposed. To merge a burst of publishes, append the
+    /// operations of the burst and publish once, or use [`flush`](Self::flush), which does not
+    /// publish when nothing is pending.
+    ///
+    /// # Panics
+    ///
+    /// If an operation panics while it is applied, the two copies may have seen different
+    /// operations. That panic propagates, and every later attempt to publish panics as well,
+    /// while readers keep seeing the data from the last successful publish.
     pub fn publish(&mut self) -> &mut Self {
+        self.assert_owner();
+
         // we need to wait until all epochs have changed since the swaps *or* until a "finished"
         // flag has been observed to be on for two subsequent iterations (there still may be some
         // readers present since we did the previous refresh)
@@ -209,9 +475,232 @@ where
         // only block on pre-existing readers, and they are never waiting to push onto epochs
         // unless they have finished reading.
         let epochs = Arc::clone(&self.epochs);
-        let mut epochs = epochs.lock().unwrap();
+        let mut epochs = crate::lock_epochs(&epochs);
 
         self.wait(&mut epochs);
+        let fresh = self.fresh.clone();
+        let mut fresh = fresh.as_ref().map(crate::read::lock_fresh);
+        self.apply_oplog();
+        self.flip(Some(&mut epochs), fresh.as_deref_mut());
+        self
+    }
+
+    /// Publish all pending operations, but only if every one of them passes
+    /// [`Apply::validate`].
+    ///
+    /// Validation happens before anything else, and in particular before any operation is applied
+    /// to either copy. If an operation is rejected, this returns the error without publishing,
+    /// and leaves the oplog as it was, so the same operations can be published once whatever made
+    /// the operation fail has cleared. Regular calls to [`publish`](Self::publish) skip
+    /// validation.
+    ///
+    /// Every operation is validated on its own, against the data readers currently see. That is,
+    /// validation does _not_ see the effects of the pending operations before it, so it cannot
+    /// enforce a limit on what the pending operations add up to. To enforce such a limit, call
+    /// `try_publish` after appending each operation, so that every operation is validated against
+    /// the effects of all the ones before it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use reft_light::Apply;
+    /// use std::error::Error;
+    ///
+    /// // the auxiliary holds a cap on single transfers that is managed outside of the left-right
+    /// struct Transfer(u64);
+    /// impl Apply<u64, u64> for Transfer {
+    ///     fn apply_first(&mut self, first: &mut u64, _: &u64, _: &mut u64) {
+    ///         *first += self.0;
+    ///     }
+    ///
+    ///     fn validate(&self, _: &u64, cap: &u64) -> Result<(), Box<dyn Error + Send + Sync>> {
+    ///         if self.0 > *cap {
+    ///             return Err("transfer over the cap".into());
+    ///         }
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// let mut w = reft_light::new::<Transfer, _, _>(0, 10);
+    /// w.append(Transfer(4)).append(Transfer(12));
+    /// let rejected = w.try_publish().err().unwrap();
+    /// assert_eq!(rejected.index(), 1);
+    /// assert_eq!(*w.enter().unwrap(), 0);
+    ///
+    /// // once the cap is raised, the same operations go through
+    /// *w.auxiliary_mut() = 20;
+    /// assert!(w.try_publish().is_ok());
+    /// assert_eq!(*w.enter().unwrap(), 16);
+    /// ```
+    pub fn try_publish(&mut self) -> Result<&mut Self, Rejected> {
+        self.assert_owner();
+
+        // safety: we will not swap while we hold this reference
+        let r_handle = unsafe {
+            self.r_handle
+                .inner
+                .load(Ordering::Acquire)
+                .as_ref()
+                .unwrap()
+        };
+        let auxiliary = &self.auxiliary;
+        self.oplog.with_iter_from(self.swap_index, |ops| {
+            for (i, op) in ops.enumerate() {
+                op.validate(&r_handle.data, auxiliary)
+                    .map_err(|error| Rejected::new(i, error))?;
+            }
+            Ok(())
+        })?;
+        Ok(self.publish())
+    }
+
+    /// Publish if all readers have departed from the stale copy, without blocking otherwise.
+    ///
+    /// This is the non-blocking counterpart to [`publish`](Self::publish) for use with any
+    /// executor. If some reader may still be using the stale copy, this returns
+    /// `Poll::Pending`, and arranges for the waker of `cx` to be woken once a reader departs.
+    /// Poll again then to check whether that was the last one.
+    ///
+    /// Every operation appended before the call that returns `Poll::Ready` is published.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use reft_light::Apply;
+    /// use std::future::Future;
+    /// use std::pin::Pin;
+    /// use std::sync::Arc;
+    /// use std::task::{Context, Poll, Wake};
+    ///
+    /// struct Add(u64);
+    /// impl Apply<u64, ()> for Add {
+    ///     fn apply_first(&mut self, first: &mut u64, _: &u64, _: &mut ()) {
+    ///         *first += self.0;
+    ///     }
+    /// }
+    ///
+    /// struct Noop;
+    /// impl Wake for Noop {
+    ///     fn wake(self: Arc<Self>) {}
+    /// }
+    /// let waker = Arc::new(Noop).into();
+    /// let mut cx = Context::from_waker(&waker);
+    ///
+    /// let mut w = reft_light::new::<Add, _, _>(0, ());
+    /// let r = w.clone();
+    /// w.append(Add(1));
+    /// assert_eq!(Pin::new(&mut w.publish_async()).poll(&mut cx), Poll::Ready(()));
+    ///
+    /// // a reader that entered before the publish holds up the next one
+    /// let guard = r.enter().unwrap();
+    /// w.append(Add(2)).publish();
+    /// assert_eq!(w.poll_publish(&mut cx), Poll::Pending);
+    /// drop(guard);
+    /// assert_eq!(w.poll_publish(&mut cx), Poll::Ready(()));
+    /// assert_eq!(*r.enter().unwrap(), 3);
+    /// ```
+    pub fn poll_publish(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        self.assert_owner();
+        let epochs = Arc::clone(&self.epochs);
+        let mut epochs = crate::lock_epochs(&epochs);
+        if !self.has_departed(&mut epochs, false) {
+            self.r_handle.published.wait_for_reader(cx.waker());
+            // a reader may have departed before it could see our waker
+            if !self.has_departed(&mut epochs, true) {
+                return Poll::Pending;
+            }
+        }
+        // fresh readers hold a lock rather than an epoch, and must not make us block either
+        let fresh = self.fresh.clone();
+        let mut fresh = match &fresh {
+            Some(fresh) => match crate::read::try_lock_fresh(fresh) {
+                Some(fresh) => Some(fresh),
+                None => {
+                    self.r_handle.published.wait_for_reader(cx.waker());
+                    // a fresh reader may have let go before it could see our waker
+                    match crate::read::try_lock_fresh(fresh) {
+                        Some(fresh) => Some(fresh),
+                        None => return Poll::Pending,
+                    }
+                }
+            },
+            None => None,
+        };
+        self.r_handle.published.stop_waiting_for_readers();
+        self.apply_oplog();
+        self.flip(Some(&mut epochs), fresh.as_deref_mut());
+        Poll::Ready(())
+    }
+
+    /// Returns a future that publishes once all readers have departed from the stale copy.
+    ///
+    /// See [`poll_publish`](Self::poll_publish).
+    pub fn publish_async(&mut self) -> PublishFuture<'_, O, T, A, S> {
+        PublishFuture { writer: self }
+    }
+
+    /// Start a publish that accepts new operations while it waits for readers to depart.
+    ///
+    /// Operations appended before this call are exposed by the publish. Operations appended to
+    /// the returned [`PendingPublish`] are held back until the publish completes, and are then
+    /// left for the next publish. See [`PendingPublish`] for details.
+    pub fn begin_publish(&mut self) -> PendingPublish<'_, O, T, A, S> {
+        PendingPublish::new(self)
+    }
+
+    /// Bring the write copy up to date with every operation in the oplog.
+    ///
+    /// Must only be called once all readers have departed from the write copy, that is, after a
+    /// call to `wait`, and while fresh readers are locked out, since they may be reading the write
+    /// copy if `current` ran ahead.
+    fn apply_oplog(&mut self) {
+        self.batches.record(self.oplog.len() - self.swap_index);
+        self.apply_pending();
+        self.poisoned = true;
+
+        // safety: as in `apply_pending`.
+        let w_handle = unsafe { self.w_handle.as_mut() };
+        let r_handle = unsafe {
+            self.r_handle
+                .inner
+                .load(Ordering::Acquire)
+                .as_ref()
+                .unwrap()
+        };
+
+        #[cfg(feature = "metrics")]
+        let start = Instant::now();
+        if !self.oplog.is_empty() {
+            O::finalize_first(&mut w_handle.data, &r_handle.data, &mut self.auxiliary);
+        }
+        #[cfg(feature = "metrics")]
+        {
+            self.publishing.apply_first += start.elapsed();
+        }
+        // the summary travels with w_handle, so readers see it exactly when they see its data
+        let auxiliary = &self.auxiliary;
+        w_handle.meta.delta = self
+            .oplog
+            .with_iter_from(0, |ops| O::summarize(ops, auxiliary));
+        w_handle.meta.generation = r_handle.meta.generation.wrapping_add(1);
+        // the w_handle copy is about to become the r_handle, and can ignore the oplog
+        self.swap_index = self.oplog.len();
+        self.applied = 0;
+        self.poisoned = false;
+        // w_handle (the old r_handle) is now fully up to date!
+    }
+
+    /// Apply every operation in the oplog that the write copy has not seen yet, without
+    /// finalizing the batch.
+    ///
+    /// Must only be called once all readers have departed from the write copy, that is, after a
+    /// call to `wait`.
+    fn apply_pending(&mut self) {
+        assert!(
+            !self.poisoned,
+            "an operation panicked while it was being applied, so the copies may have diverged"
+        );
+        self.poisoned = true;
 
         // all the readers have left!
         // safety: we haven't freed the Box, and no readers are accessing the w_handle
@@ -226,25 +715,165 @@ where
                 .unwrap()
         };
 
-        // the w_handle copy has not seen any of the writes in the oplog
+        // every operation of this batch sees the same time, however long applying it takes
+        self.reclaim.start_batch();
+
+        #[cfg(feature = "metrics")]
+        let mut start = Instant::now();
+        #[cfg(feature = "metrics")]
+        {
+            // only draining shrinks the oplog, so this is as long as it has been since the last
+            // drain
+            self.high_water.oplog_len = self.high_water.oplog_len.max(self.oplog.len());
+        }
+
+        // the w_handle copy has not seen any of the writes in the oplog, except for the first
+        // `applied` ones following swap_index, which `current` may have applied already.
         // the r_handle copy has not seen any of the writes following swap_index
         if self.swap_index != 0 {
             // we can drain out the operations that only the w_handle copy needs
             //
             // NOTE: the if above is because drain(0..0) would remove 0
-            for op in self.oplog.drain(0..self.swap_index) {
-                O::apply_second(op, r_handle, w_handle, &mut self.auxiliary);
+            let auxiliary = &mut self.auxiliary;
+            let reclaim = &mut self.reclaim;
+            let run = &mut self.run;
+            if let Some(parallel) = self.parallel {
+                self.oplog.drain_prefix(self.swap_index, |op| run.push(op));
+                (parallel.second)(run.drain(..), &r_handle.data, &mut w_handle.data);
+            } else if self.order == ApplyOrder::Lifo {
+                self.oplog.drain_prefix(self.swap_index, |op| run.push(op));
+                order::drop_superseded(run);
+                for op in run.drain(..) {
+                    op.apply_second_deferred(
+                        &r_handle.data,
+                        &mut w_handle.data,
+                        auxiliary,
+                        reclaim,
+                    );
+                }
+            } else {
+                self.oplog.drain_prefix(self.swap_index, |op| {
+                    if let Some(last) = run.last() {
+                        if !last.is_homogeneous_with(&op) {
+                            O::apply_second_homogeneous(
+                                run.drain(..),
+                                &r_handle.data,
+                                &mut w_handle.data,
+                                auxiliary,
+                                reclaim,
+                            );
+                        }
+                    }
+                    run.push(op);
+                });
+                O::apply_second_homogeneous(
+                    run.drain(..),
+                    &r_handle.data,
+                    &mut w_handle.data,
+                    auxiliary,
+                    reclaim,
+                );
+            }
+            O::finalize_second(&r_handle.data, &mut w_handle.data, &mut self.auxiliary);
+            self.oplog.shrink(self.shrink_policy);
+            self.swap_index = 0;
+            #[cfg(feature = "metrics")]
+            {
+                self.publishing.apply_second = start.elapsed();
+                start = Instant::now();
+            }
+        }
+
+        // unless `current` already ran ahead, both copies have now seen exactly the same
+        // operations
+        if let (Some(copies_match), 0) = (self.check_copies, self.applied) {
+            // don't pile a second panic onto one that is already unwinding through Drop
+            if !thread::panicking() {
+                // the r_handle copy is the one the drained operations were applied to first
+                assert!(
+                    copies_match(&w_handle.data, &r_handle.data),
+                    "the two copies diverged; the Apply implementation is not deterministic: {}",
+                    crate::diff::describe(&O::diff(&r_handle.data, &w_handle.data))
+                );
+            }
+        }
+
+        // move independent operations next to the ones they can be batched with. the reordered
+        // operations are later applied to the r_handle copy in the same order. in LIFO order,
+        // operations are not batched.
+        let applied = self.applied;
+        let reorderable = self.order == ApplyOrder::Fifo
+            && self.oplog.with_iter_from(applied, |ops| {
+                let mut ops = ops.peekable();
+                while let (Some(op), Some(next)) = (ops.next(), ops.peek()) {
+                    if !conflicting(op, next) {
+                        return true;
+                    }
+                }
+                false
+            });
+        if reorderable {
+            if let Some(ops) = self.oplog.as_mut_slice_from(applied) {
+                group_homogeneous(ops);
             }
         }
+
         // we cannot give owned operations to apply_first
         // since they'll also be needed by the r_handle copy
-        for op in self.oplog.iter_mut() {
-            O::apply_first(op, w_handle, r_handle, &mut self.auxiliary);
+        let auxiliary = &mut self.auxiliary;
+        let reclaim = &mut self.reclaim;
+        // stores that cannot hand out a slice get the operations applied one by one, in order
+        let mut applied_as_slice = false;
+        if let Some(parallel) = self.parallel {
+            if let Some(ops) = self.oplog.as_mut_slice_from(applied) {
+                (parallel.first)(ops, &mut w_handle.data, &r_handle.data);
+                applied_as_slice = true;
+            }
+        } else if self.order == ApplyOrder::Lifo {
+            if let Some(ops) = self.oplog.as_mut_slice_from(applied) {
+                order::apply_first_lifo(
+                    ops,
+                    &mut self.kept,
+                    &mut w_handle.data,
+                    &r_handle.data,
+                    auxiliary,
+                    reclaim,
+                );
+                applied_as_slice = true;
+            }
         }
-        // the w_handle copy is about to become the r_handle, and can ignore the oplog
-        self.swap_index = self.oplog.len();
-        // w_handle (the old r_handle) is now fully up to date!
+        if !applied_as_slice {
+            self.oplog.for_each_run_from(
+                applied,
+                |op, next| op.is_homogeneous_with(next),
+                |ops| {
+                    O::apply_first_homogeneous(
+                        ops,
+                        &mut w_handle.data,
+                        &r_handle.data,
+                        auxiliary,
+                        reclaim,
+                    );
+                },
+            );
+        }
+        self.applied = self.oplog.len();
+        self.poisoned = false;
+        #[cfg(feature = "metrics")]
+        {
+            self.publishing.apply_first = start.elapsed();
+        }
+    }
 
+    /// Expose the write copy to readers, and start tracking the readers of the old read copy.
+    ///
+    /// Must only be called after `apply_oplog`. Without `epochs`, no readers are tracked, which
+    /// is only sound if there are none. `fresh` must be given if fresh reads are enabled.
+    fn flip(
+        &mut self,
+        epochs: Option<&mut MutexGuard<'_, slab::Slab<Arc<AtomicUsize>>>>,
+        fresh: Option<&mut FreshCopy<T>>,
+    ) {
         // at this point, we have exclusive access to w_handle, and it is up-to-date with all
         // writes. the stale r_handle is accessed by readers through an Arc clone of atomic pointer
         // inside the ReadHandle. oplog contains all the changes that are in w_handle, but not in
@@ -253,11 +882,24 @@ where
         // it's now time for us to swap the copies so that readers see up-to-date results from
         // w_handle.
 
+        // the timestamp travels with w_handle, just like the delta
+        let now = Instant::now();
+        // safety: readers cannot reach w_handle until the swap below, and fresh readers are
+        // locked out.
+        unsafe { self.w_handle.as_mut() }.meta.published_at = now;
+
         // swap in our w_handle, and get r_handle in return
         let r_handle = self
             .r_handle
             .inner
             .swap(self.w_handle.as_ptr(), Ordering::Release);
+        // the new read copy has seen every operation, so fresh readers move over as well
+        if let Some(fresh) = fresh {
+            fresh.0 = self.w_handle.as_ptr();
+        }
+
+        // safety: we just swapped w_handle in, and nothing frees it while we hold &mut self.
+        let generation = unsafe { self.w_handle.as_ref() }.meta.generation;
 
         // NOTE: at this point, there are likely still readers using r_handle.
         // safety: r_handle was also created from a Box, so it is not null and is covariant.
@@ -266,8 +908,18 @@ where
         // ensure that the subsequent epoch reads aren't re-ordered to before the swap
         fence(Ordering::SeqCst);
 
-        for (ri, epoch) in epochs.iter() {
-            self.last_epochs[ri] = epoch.load(Ordering::Acquire);
+        // new readers may have registered since we waited if the lock was released in between
+        if let Some(epochs) = epochs {
+            self.record_active(epochs);
+        }
+
+        self.r_handle
+            .published
+            .update(|state| state.generation = generation);
+        self.last_publish = now;
+        #[cfg(feature = "metrics")]
+        {
+            self.timings = std::mem::take(&mut self.publishing);
         }
 
         #[cfg(test)]
@@ -275,7 +927,81 @@ where
             self.refreshes += 1;
         }
 
-        self
+        // readers can see the new copy, so whatever the operations removed is no longer needed
+        self.hand_off_reclaimed();
+    }
+
+    /// Hand whatever the published operations removed to the reclaimer.
+    fn hand_off_reclaimed(&mut self) {
+        if !self.reclaim.is_empty() {
+            let reclaimed = self.reclaim.take();
+            if let Some(reclaimer) = &mut self.reclaimer {
+                reclaimer(reclaimed);
+            }
+        }
+    }
+
+    /// Returns the data with every appended operation applied, including unpublished ones.
+    ///
+    /// Reads through the `WriteHandle`'s [`Deref`](std::ops::Deref) go to the read copy, and so
+    /// only see operations up until the last [`publish`](Self::publish). This instead brings the
+    /// write copy up to date with all pending operations, and returns a reference to it. Readers
+    /// are not affected: they keep seeing the read copy until the next publish.
+    ///
+    /// Before it can touch the write copy, this has to wait for readers to depart from it, just
+    /// like `publish` does. The operations it applies are not applied again by the next publish.
+    /// Note that [`Apply::finalize_first`] only runs once per publish, so the data returned here
+    /// has not been finalized yet. Anything that `finalize_first` recomputes, like a cached total,
+    /// may therefore still be out of date.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use reft_light::Apply;
+    ///
+    /// struct Add(u64);
+    /// impl Apply<u64, ()> for Add {
+    ///     fn apply_first(&mut self, first: &mut u64, _: &u64, _: &mut ()) {
+    ///         *first += self.0;
+    ///     }
+    /// }
+    ///
+    /// let mut w = reft_light::new::<Add, _, _>(0, ());
+    /// let r = w.clone();
+    /// w.append(Add(1)).append(Add(2));
+    /// assert_eq!(*w.current(), 3);
+    /// assert_eq!(*r.enter().unwrap(), 0);
+    ///
+    /// w.publish();
+    /// assert_eq!(*r.enter().unwrap(), 3);
+    /// ```
+    pub fn current(&mut self) -> &T {
+        self.assert_owner();
+        // this is not a publish, so it must not show up in the timings of one
+        #[cfg(feature = "metrics")]
+        let publishing = self.publishing;
+        {
+            let epochs = Arc::clone(&self.epochs);
+            let mut epochs = crate::lock_epochs(&epochs);
+            self.wait(&mut epochs);
+        }
+        let fresh = self.fresh.clone();
+        let mut fresh = fresh.as_ref().map(crate::read::lock_fresh);
+        // finalizing is left to the publish, so that it happens exactly once per batch on both
+        // copies
+        self.apply_pending();
+        #[cfg(feature = "metrics")]
+        {
+            self.publishing = publishing;
+        }
+        // w_handle is now ahead of the read copy, so fresh readers should see it
+        if let Some(fresh) = &mut fresh {
+            fresh.0 = self.w_handle.as_ptr();
+        }
+        drop(fresh);
+        // safety: readers have departed from w_handle, and the returned reference keeps us from
+        // flipping.
+        &unsafe { self.w_handle.as_ref() }.data
     }
 
     /// Publish as necessary to ensure that all operations are visible to readers.
@@ -288,23 +1014,375 @@ where
         }
     }
 
-    /// Returns true if there are operations in the operational log that have not yet been exposed
-    /// to readers.
-    pub fn has_pending_operations(&self) -> bool {
-        // NOTE: we don't use self.oplog.is_empty() here because it's not really that important if
-        // there are operations that have not yet been applied to the _write_ handle.
-        self.swap_index < self.oplog.len()
+    /// Put `ops` in the oplog exactly as given, and apply the first `applied` of them to the
+    /// write copy, as [`current`](Self::current) would have.
+    pub(crate) fn restore_oplog(&mut self, ops: Vec<O>, applied: usize) {
+        assert!(
+            applied <= ops.len(),
+            "cannot apply {} of {} operations",
+            applied,
+            ops.len()
+        );
+        let mut ops = ops.into_iter();
+        for op in ops.by_ref().take(applied) {
+            self.oplog.push_back(op);
+        }
+        self.current();
+        for op in ops {
+            self.oplog.push_back(op);
+        }
     }
 
-    /// Append the given operation to the operational log.
+    /// Publish without waiting for readers, and without tracking them.
     ///
-    /// Its effects will not be exposed to readers until you call [`publish`](Self::publish).
-    pub fn append(&mut self, op: O) -> &mut Self {
-        self.extend(std::iter::once(op));
-        self
+    /// Only sound for handles whose copies are never read other than through
+    /// [`read_copy`](Self::read_copy).
+    pub(crate) fn publish_unsync(&mut self) {
+        self.apply_oplog();
+        // nobody but us reads either copy, so there is no one to tell about the swap. unlike
+        // `flip`, skip the timestamp, the generation and its wakeups, and the reader epochs.
+        let r_handle = self
+            .r_handle
+            .inner
+            .swap(self.w_handle.as_ptr(), Ordering::Relaxed);
+        // safety: r_handle was also created from a Box, so it is not null and is covariant.
+        self.w_handle = unsafe { NonNull::new_unchecked(r_handle) };
+        #[cfg(feature = "metrics")]
+        {
+            self.timings = std::mem::take(&mut self.publishing);
+        }
+
+        #[cfg(test)]
+        {
+            self.refreshes += 1;
+        }
+
+        self.hand_off_reclaimed();
     }
 
-    /// Returns a reference to the auxiliary data.
+    /// Returns the read copy without entering it.
+    ///
+    /// Publishing needs `&mut self`, so the read copy cannot change while the reference lives.
+    pub(crate) fn read_copy(&self) -> &T {
+        // safety: only we swap the pointer, and we do not free what it points to while we live.
+        &unsafe { self.r_handle.inner.load(Ordering::Acquire).as_ref() }
+            .expect("the read copy lives as long as the WriteHandle")
+            .data
+    }
+
+    /// Publish if the last publish happened more than `max_age` ago.
+    ///
+    /// This publishes even if there are no pending operations. Calling it regularly bounds how
+    /// stale the data readers see can be: together with [`generation`](Self::generation), a
+    /// reader knows that a generation is at most `max_age` (plus the calling interval) older
+    /// than the latest one. Note that the publish itself may take a while if readers are slow to
+    /// depart from the stale copy.
+    ///
+    /// Returns true if it published.
+    pub fn publish_if_stale(&mut self, max_age: Duration) -> bool {
+        if self.last_publish.elapsed() > max_age {
+            self.publish();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns true if there are operations in the operational log that have not yet been exposed
+    /// to readers.
+    pub fn has_pending_operations(&self) -> bool {
+        // NOTE: we don't use self.oplog.is_empty() here because it's not really that important if
+        // there are operations that have not yet been applied to the _write_ handle.
+        self.swap_index < self.oplog.len()
+    }
+
+    /// Returns the lengths of the write copy and of the read copy, in that order.
+    ///
+    /// This is a debugging aid for spotting copies that drift apart or leak. The write copy has
+    /// not yet seen the operations of the last publish, so the two lengths may legitimately
+    /// differ by the effect of those operations. Any larger difference means the copies have
+    /// diverged.
+    pub fn debug_copy_lens(&self) -> (usize, usize)
+    where
+        T: HasLen,
+    {
+        // safety: readers may still be looking at the write copy, but only ever through shared
+        // references, and we only modify it through &mut self.
+        let w_len = unsafe { self.w_handle.as_ref() }.data.len();
+        let r_len = self
+            .r_handle
+            .enter()
+            .map(|guard| guard.len())
+            .expect("the read copy lives as long as the WriteHandle");
+        (w_len, r_len)
+    }
+
+    /// Append the given operation to the operational log.
+    ///
+    /// Its effects will not be exposed to readers until you call [`publish`](Self::publish).
+    pub fn append(&mut self, op: O) -> &mut Self {
+        self.extend(std::iter::once(op));
+        self
+    }
+
+    /// Add an operation to the operational log ahead of every other pending operation.
+    ///
+    /// The next publish applies `op` before all operations that were appended before it and have
+    /// not been published yet. This is meant for urgent operations that must not wait for a
+    /// large backlog, like one that resets the data. Operations that were already published are
+    /// not affected, and neither are operations that [`current`](Self::current) has already
+    /// applied to the write copy: `op` goes right after them.
+    ///
+    /// Moving `op` ahead of operations that were appended earlier changes the order in which they
+    /// take effect. It is up to the caller to make sure that this order makes sense for the
+    /// operations involved. A "clear everything" operation that jumps the queue, for example,
+    /// does not clear what the operations it jumped over go on to add.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use reft_light::Apply;
+    ///
+    /// enum Op {
+    ///     Push(u32),
+    ///     Clear,
+    /// }
+    /// impl Apply<Vec<u32>, ()> for Op {
+    ///     fn apply_first(&mut self, first: &mut Vec<u32>, _: &Vec<u32>, _: &mut ()) {
+    ///         match self {
+    ///             Op::Push(v) => first.push(*v),
+    ///             Op::Clear => first.clear(),
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let mut w = reft_light::new::<Op, _, _>(vec![], ());
+    /// w.append(Op::Push(1)).publish();
+    /// w.append(Op::Push(2)).append(Op::Push(3));
+    /// w.append_priority(Op::Clear);
+    /// w.publish();
+    /// assert_eq!(*w.enter().unwrap(), [2, 3]);
+    /// ```
+    pub fn append_priority(&mut self, op: O) -> &mut Self {
+        if !self.has_pending_operations() {
+            return self.append(op);
+        }
+        self.assert_owner();
+        self.oplog.insert(self.swap_index + self.applied, op);
+        self
+    }
+
+    /// Append the operations in `ops[range]` to the operational log.
+    ///
+    /// With an oplog store that supports it, such as [`SharedOplog`](crate::SharedOplog), this
+    /// only records a reference to the shared buffer rather than copying every operation in. With
+    /// other stores, the operations are cloned in one by one. Unlike [`append`](Self::append),
+    /// this never checks whether the operations are [redundant](Apply::is_redundant).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds for `ops`.
+    pub fn append_range(
+        &mut self,
+        ops: std::sync::Arc<Vec<O>>,
+        range: std::ops::Range<usize>,
+    ) -> &mut Self
+    where
+        O: Clone,
+    {
+        self.assert_owner();
+        self.oplog.extend_shared(&ops, range);
+        self
+    }
+
+    /// Start a group of operations that is either appended as a whole, or not at all.
+    ///
+    /// See [`Transaction`] for details.
+    pub fn transaction(&mut self) -> Transaction<'_, O, T, A, S> {
+        Transaction::new(self)
+    }
+
+    /// Hand this `WriteHandle` off to be used from another thread.
+    ///
+    /// In debug builds, the `WriteHandle` records the thread that first writes to it, and panics
+    /// if [`append`](Self::append), [`extend`](Extend::extend), or [`publish`](Self::publish) are
+    /// later called from a different thread. This catches accidental use of the writer from
+    /// several threads, which usually means the single-writer contract is being violated
+    /// somewhere. If the writer is moved to another thread on purpose, hand it off first, and
+    /// the next write claims it for whichever thread performs it. When sharing the writer behind
+    /// a [`Mutex`](std::sync::Mutex), hand it off before releasing the lock.
+    ///
+    /// In release builds, no thread is recorded and this is a no-op.
+    pub fn handoff(&mut self) -> &mut Self {
+        self.release_owner();
+        self
+    }
+
+    #[inline]
+    fn assert_owner(&mut self) {
+        #[cfg(debug_assertions)]
+        {
+            let me = thread::current().id();
+            let owner = *self.owner.get_or_insert(me);
+            assert_eq!(
+                owner, me,
+                "WriteHandle used from a different thread than the one that first wrote to it; \
+                 use WriteHandle::handoff to move it between threads"
+            );
+        }
+    }
+
+    #[inline]
+    fn release_owner(&mut self) {
+        #[cfg(debug_assertions)]
+        {
+            self.owner = None;
+        }
+    }
+
+    /// Set the strategy the writer uses while it waits for readers to depart.
+    ///
+    /// Defaults to [`SpinThenYield`]. See [`WaitStrategy`] for details.
+    pub fn set_wait_strategy<W>(&mut self, strategy: W) -> &mut Self
+    where
+        W: WaitStrategy + Send + 'static,
+    {
+        self.wait_strategy = Box::new(strategy);
+        self
+    }
+
+    /// Set what publishes do with the oplog's spare capacity after draining operations from it.
+    ///
+    /// Defaults to [`ShrinkPolicy::Never`]. See [`ShrinkPolicy`] for details.
+    pub fn set_shrink_policy(&mut self, policy: ShrinkPolicy) -> &mut Self {
+        self.shrink_policy = policy;
+        self
+    }
+
+    /// Set the order in which publishes apply each batch of operations.
+    ///
+    /// Defaults to [`ApplyOrder::Fifo`]. See [`ApplyOrder`] for details.
+    pub fn set_apply_order(&mut self, order: ApplyOrder) -> &mut Self {
+        self.order = order;
+        self
+    }
+
+    /// Hand values that operations defer with [`Reclaim`] to `reclaimer` instead of dropping them
+    /// at the end of each publish.
+    ///
+    /// `reclaimer` is called at most once per publish, after the new data has been exposed to
+    /// readers, with everything deferred during that publish. It runs on the writer's thread and
+    /// holds up the publish until it returns, so it should do little more than send the batch off
+    /// to be dropped elsewhere.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use reft_light::{Apply, Reclaim, Reclaimed};
+    /// use std::collections::HashMap;
+    /// use std::sync::mpsc;
+    ///
+    /// type Map = HashMap<u32, Vec<u8>>;
+    ///
+    /// struct Remove(u32);
+    /// impl Apply<Map, ()> for Remove {
+    ///     fn apply_first(&mut self, first: &mut Map, _: &Map, _: &mut ()) {
+    ///         first.remove(&self.0);
+    ///     }
+    ///
+    ///     fn apply_first_deferred(&mut self, first: &mut Map, _: &Map, _: &mut (), reclaim: &mut Reclaim) {
+    ///         if let Some(value) = first.remove(&self.0) {
+    ///             reclaim.defer(value);
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let big: Map = (0..4).map(|k| (k, vec![0; 1 << 20])).collect();
+    /// let mut w = reft_light::new::<Remove, _, _>(big, ());
+    ///
+    /// // drop removed values on a background thread
+    /// let (tx, rx) = mpsc::channel::<Reclaimed>();
+    /// let dropper = std::thread::spawn(move || rx.iter().map(|batch| batch.len()).sum::<usize>());
+    /// w.set_reclaimer(move |batch| tx.send(batch).unwrap());
+    ///
+    /// w.append(Remove(0)).append(Remove(1)).publish();
+    /// w.publish();
+    /// assert_eq!(w.enter().unwrap().len(), 2);
+    /// drop(w);
+    /// // `apply_second` was not overridden, so only the first copy deferred its drops
+    /// assert_eq!(dropper.join().unwrap(), 2);
+    /// ```
+    pub fn set_reclaimer<R>(&mut self, reclaimer: R) -> &mut Self
+    where
+        R: FnMut(Reclaimed) + Send + 'static,
+    {
+        self.reclaimer = Some(Box::new(reclaimer));
+        self
+    }
+
+    /// Returns the generation of the data readers currently see.
+    ///
+    /// Every publish exposes the next generation, so operations appended now become visible at
+    /// `generation() + 1`. See [`ReadHandle::wait_for_generation`].
+    pub fn generation(&self) -> u64 {
+        // safety: only we swap the pointer, and we do not free what it points to while we live.
+        unsafe { self.r_handle.inner.load(Ordering::Acquire).as_ref() }
+            .expect("the read copy lives as long as the WriteHandle")
+            .meta
+            .generation
+    }
+
+    /// Returns how the number of operations per publish changed over recent publishes.
+    ///
+    /// See [`BatchTrend`] for details.
+    pub fn batch_trend(&self) -> BatchTrend {
+        self.batches
+    }
+
+    /// Returns how long each phase of the most recent publish took.
+    ///
+    /// See [`PhaseTimings`] for details. Publishes that complete through
+    /// [`poll_publish`](Self::poll_publish) or [`PendingPublish::try_complete`] record no time
+    /// spent waiting. Operations that [`current`](Self::current) already applied are not counted
+    /// towards the publish that exposes them.
+    #[cfg(feature = "metrics")]
+    pub fn phase_timings(&self) -> PhaseTimings {
+        self.timings
+    }
+
+    /// Returns an estimate of how long the next publish will wait for readers to depart.
+    ///
+    /// The estimate is a moving average of the [wait phase](PhaseTimings::wait) of recent
+    /// publishes, with each publish weighted by 1/4 and older ones decaying geometrically, so it
+    /// follows a change in reader behavior within a handful of publishes. A scheduler can use it
+    /// to decide between publishing now and batching more operations first. Returns zero until
+    /// the first publish has waited.
+    #[cfg(feature = "metrics")]
+    pub fn estimated_wait(&self) -> Duration {
+        self.wait_estimate.unwrap_or_default()
+    }
+
+    /// Returns the peak oplog length and reader count observed so far.
+    ///
+    /// See [`HighWaterMarks`] for details.
+    #[cfg(feature = "metrics")]
+    pub fn high_water_marks(&self) -> HighWaterMarks {
+        HighWaterMarks {
+            oplog_len: self.high_water.oplog_len.max(self.oplog.len()),
+            ..self.high_water
+        }
+    }
+
+    /// Start tracking high-water marks afresh from the current oplog length and reader count.
+    #[cfg(feature = "metrics")]
+    pub fn reset_high_water_marks(&mut self) {
+        self.high_water = HighWaterMarks {
+            oplog_len: 0,
+            readers: crate::lock_epochs(&self.epochs).len(),
+        };
+    }
+
+    /// Returns a reference to the auxiliary data.
     pub fn auxiliary(&self) -> &A {
         &self.auxiliary
     }
@@ -322,61 +1400,136 @@ where
     pub fn take(self) -> Box<T> {
         use std::mem;
         use std::ptr;
-        // first, ensure the read handle is up-to-date with all operations
         let mut this = mem::ManuallyDrop::new(self);
-        if this.swap_index != this.oplog.len() {
-            this.publish();
+        this.release_owner();
+        let r_handle = this.retire(true);
+
+        // drop the other fields
+        //
+        // safety: `this` is never used again, and `retire` has already freed w_handle.
+        let WriteHandle {
+            epochs,
+            oplog,
+            r_handle: read,
+            active,
+            wait_strategy,
+            reclaim,
+            reclaimer,
+            run,
+            kept,
+            fresh,
+            auxiliary,
+            #[cfg(test)]
+            is_waiting,
+            ..
+        } = &mut *this;
+        unsafe {
+            ptr::drop_in_place(epochs);
+            ptr::drop_in_place(oplog);
+            ptr::drop_in_place(read);
+            ptr::drop_in_place(active);
+            ptr::drop_in_place(wait_strategy);
+            ptr::drop_in_place(reclaim);
+            ptr::drop_in_place(reclaimer);
+            ptr::drop_in_place(run);
+            ptr::drop_in_place(kept);
+            ptr::drop_in_place(fresh);
+            ptr::drop_in_place(auxiliary);
+            #[cfg(test)]
+            ptr::drop_in_place(is_waiting);
+        }
+
+        // return the boxed r_handle
+        Box::new(r_handle.data)
+    }
+
+    /// Detach both copies from the readers, free the write copy, and return the read copy.
+    ///
+    /// Applies all pending operations first, so the returned copy is fully up to date. Afterwards,
+    /// readers see the handle as destroyed, and the `WriteHandle` must not be used again except to
+    /// drop its remaining fields.
+    fn retire(&mut self, publish_pending: bool) -> Box<Slot<T>> {
+        use std::ptr;
+        // first, ensure the read handle is up-to-date with all operations
+        if publish_pending && self.swap_index != self.oplog.len() {
+            self.publish();
         }
 
         // next, grab the read handle and set it to NULL
-        let r_handle = this.r_handle.inner.swap(ptr::null_mut(), Ordering::Release);
+        let r_handle = self.r_handle.inner.swap(ptr::null_mut(), Ordering::Release);
+        // fresh readers hold a lock rather than an epoch, so taking it is enough to see them off
+        if let Some(fresh) = &self.fresh {
+            crate::read::lock_fresh(fresh).clear();
+        }
+        // readers waiting for a generation will now never see it
+        self.r_handle.published.update(|state| state.retired = true);
 
-        // now, wait for all readers to depart
-        // we need to make sure that the lock is relesed before we drop the w_handle
-        // to prevent a deadlock if a reader tries to acquire the lock on drop
+        // now, wait for all readers to depart.
+        //
+        // unlike in `publish`, the epochs we recorded at the last swap are not good enough here:
+        // readers that entered since then got r_handle, which we are about to free, and their
+        // epochs may well have been even back then. so we take a fresh snapshot. just as in
+        // `flip`, the fence keeps the epoch reads from being reordered to before the swap, and
+        // pairs with the fence in `ReadHandle::enter`: any reader whose epoch we then see as even
+        // will see the NULL when it enters.
+        //
+        // we need to make sure that the lock is released before we free either copy, to prevent a
+        // deadlock if dropping a T drops a ReadHandle (which takes the lock).
         {
-            let epochs = Arc::clone(&this.epochs);
-            let mut epochs = epochs.lock().unwrap();
-            this.wait(&mut epochs);
+            let epochs = Arc::clone(&self.epochs);
+            let mut epochs = crate::lock_epochs(&epochs);
+            fence(Ordering::SeqCst);
+            self.record_active(&mut epochs);
+            self.wait(&mut epochs);
         }
+        self.wait_for_pins(|_| true);
 
-        // ensure that the subsequent epoch reads aren't re-ordered to before the swap
-        fence(Ordering::SeqCst);
+        // no fence is needed between the wait and freeing the copies. `wait` only returns once it
+        // has observed, with an Acquire load, every reader that may hold a pointer to either copy
+        // bump its epoch on the way out (a Release operation). that alone orders the readers'
+        // accesses before our frees. a fence here would only order later epoch reads, and there
+        // are none.
 
         // all readers have now observed the NULL, so we own both handles.
         // all operations have been applied to the r_handle.
         //
         // safety: w_handle was initially crated from a `Box`, and is no longer aliased.
-        drop(unsafe { Box::from_raw(this.w_handle.as_ptr()) });
+        drop(unsafe { Box::from_raw(self.w_handle.as_ptr()) });
+        // readers can no longer get at the r_handle either, so as far as they are concerned, it
+        // is gone as well.
+        self.r_handle.published.update(|state| state.dropped = true);
 
-        // next we take the r_handle and return it as a boxed value.
-        //
         // this is safe, since we know that no readers are using this pointer
         // anymore (due to the .wait() following swapping the pointer with NULL).
         //
         // safety: r_handle was initially crated from a `Box`, and is no longer aliased.
-        let boxed_r_handle = unsafe { Box::from_raw(r_handle) };
-
-        // drop the other fields
-        unsafe { ptr::drop_in_place(&mut this.epochs) };
-        unsafe { ptr::drop_in_place(&mut this.oplog) };
-        unsafe { ptr::drop_in_place(&mut this.r_handle) };
-        unsafe { ptr::drop_in_place(&mut this.last_epochs) };
-        #[cfg(test)]
-        unsafe {
-            ptr::drop_in_place(&mut this.is_waiting)
-        };
+        unsafe { Box::from_raw(r_handle) }
+    }
+}
 
-        // return the boxed r_handle
-        boxed_r_handle
+impl<O, T, A, S> WriteHandle<Identified<O>, T, A, S>
+where
+    O: ApplyWithId<T, A>,
+    S: OplogStore<Identified<O>>,
+{
+    /// Append the given operation to the operational log, and return the id assigned to it.
+    ///
+    /// The id is handed to both applications of the operation. Ids are assigned in the order
+    /// operations are appended, and are never reused. See [`ApplyWithId`] for details.
+    pub fn append_with_id(&mut self, op: O) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.append(Identified::new(id, op));
+        id
     }
 }
 
 // allow using write handle for reads
 use std::ops::Deref;
-impl<O, T, A> Deref for WriteHandle<O, T, A>
+impl<O, T, A, S> Deref for WriteHandle<O, T, A, S>
 where
     O: Apply<T, A>,
+    S: OplogStore<O>,
 {
     type Target = ReadHandle<T>;
     fn deref(&self) -> &Self::Target {
@@ -384,18 +1537,31 @@ where
     }
 }
 
-impl<O, T, A> Extend<O> for WriteHandle<O, T, A>
+impl<O, T, A, S> Extend<O> for WriteHandle<O, T, A, S>
 where
     O: Apply<T, A>,
+    S: OplogStore<O>,
 {
     /// Add multiple operations to the operational log.
     ///
     /// Their effects will not be exposed to readers until you call [`publish`](Self::publish)
+    #[allow(clippy::unnecessary_map_or)]
     fn extend<I>(&mut self, ops: I)
     where
         I: IntoIterator<Item = O>,
     {
-        self.oplog.extend(ops);
+        self.assert_owner();
+        for op in ops {
+            // while nothing is pending, the read copy reflects every operation we have seen
+            if !self.has_pending_operations() {
+                // safety: we will not swap while we hold this reference
+                let current = unsafe { self.r_handle.inner.load(Ordering::Acquire).as_ref() };
+                if current.map_or(false, |current| op.is_redundant(&current.data)) {
+                    continue;
+                }
+            }
+            self.oplog.push_back(op);
+        }
     }
 }
 
@@ -476,7 +1642,7 @@ struct CheckWriteHandleSend;
 #[cfg(test)]
 mod tests {
     use crate::sync::{AtomicUsize, Mutex, Ordering};
-    use crate::Apply;
+    use crate::{Apply, ShrinkPolicy};
     use slab::Slab;
     include!("./utilities.rs");
 
@@ -491,6 +1657,122 @@ mod tests {
         assert_eq!(w.oplog.len(), 3);
     }
 
+    #[test]
+    fn drop_waits_for_readers_that_entered_after_publish() {
+        use std::sync::atomic::AtomicBool;
+        use std::sync::Arc;
+
+        let mut w = crate::new::<CounterAddOp, _, _>(0, ());
+        let r = w.clone();
+        w.append(CounterAddOp(1));
+        w.publish();
+
+        // this reader's epoch was even when the publish above recorded it
+        let guard = r.enter().unwrap();
+        let is_waiting = Arc::clone(&w.is_waiting);
+        let dropped = AtomicBool::new(false);
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                drop(w);
+                dropped.store(true, Ordering::SeqCst);
+            });
+            while !is_waiting.load(Ordering::Relaxed) {
+                std::thread::yield_now();
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            assert!(!dropped.load(Ordering::SeqCst));
+            assert_eq!(*guard, 1);
+            drop(guard);
+        });
+        assert!(dropped.load(Ordering::SeqCst));
+        assert!(r.enter().is_none());
+    }
+
+    #[test]
+    fn debug_copy_lens_match_after_publish() {
+        struct Push(u8);
+        impl Apply<Vec<u8>, ()> for Push {
+            fn apply_first(&mut self, first: &mut Vec<u8>, _: &Vec<u8>, _: &mut ()) {
+                first.push(self.0);
+            }
+        }
+
+        let mut w = crate::new::<Push, _, _>(vec![], ());
+        assert_eq!(w.debug_copy_lens(), (0, 0));
+
+        w.append(Push(1)).append(Push(2));
+        // nothing has been published, and so nothing has been applied yet
+        assert_eq!(w.debug_copy_lens(), (0, 0));
+
+        w.publish();
+        // the write copy has not yet seen the ops readers can now see
+        assert_eq!(w.debug_copy_lens(), (0, 2));
+
+        w.publish();
+        assert_eq!(w.debug_copy_lens(), (2, 2));
+    }
+
+    #[test]
+    #[cfg(feature = "file-oplog")]
+    fn file_oplog_matches_in_memory() {
+        use crate::{FileOplog, Spill};
+        use std::convert::TryInto;
+
+        // apply_first rewrites the op, which the second copy must then see
+        #[derive(Debug)]
+        struct Push {
+            value: u32,
+            at: Option<u32>,
+        }
+        impl Apply<Vec<(u32, u32)>, ()> for Push {
+            fn apply_first(
+                &mut self,
+                first: &mut Vec<(u32, u32)>,
+                _: &Vec<(u32, u32)>,
+                _: &mut (),
+            ) {
+                let at = *self.at.get_or_insert(first.len() as u32);
+                first.push((at, self.value));
+            }
+        }
+        impl Spill for Push {
+            fn encode(&self, buf: &mut Vec<u8>) {
+                buf.extend_from_slice(&self.value.to_le_bytes());
+                if let Some(at) = self.at {
+                    buf.extend_from_slice(&at.to_le_bytes());
+                }
+            }
+
+            fn decode(bytes: &[u8]) -> Self {
+                let word = |i: usize| u32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+                Push {
+                    value: word(0),
+                    at: if bytes.len() > 4 { Some(word(4)) } else { None },
+                }
+            }
+        }
+
+        let path = std::env::temp_dir().join(format!("reft-light-oplog-{}", std::process::id()));
+        let oplog = FileOplog::create(&path).unwrap();
+        let mut file = crate::new_with_oplog::<Push, _, _, _>(vec![], (), oplog);
+        let mut memory = crate::new::<Push, _, _>(vec![], ());
+        for batch in 0..5u32 {
+            // enough ops to spill past the write buffer
+            for value in 0..(batch * 5000) {
+                file.append(Push { value, at: None });
+                memory.append(Push { value, at: None });
+            }
+            file.publish();
+            memory.publish();
+            assert_eq!(*file.enter().unwrap(), *memory.enter().unwrap());
+            assert_eq!(file.debug_copy_lens(), memory.debug_copy_lens());
+        }
+        file.publish();
+        assert_eq!(file.debug_copy_lens(), (50000, 50000));
+        assert_eq!(*file.take(), *memory.take());
+        std::fs::remove_file(&path).unwrap();
+    }
+
     #[test]
     fn take_test() {
         // publish twice then take with no pending operations
@@ -533,6 +1815,42 @@ mod tests {
         assert_eq!(*w.take(), 2);
     }
 
+    #[test]
+    fn wait_only_scans_readers_active_at_the_swap() {
+        let mut w = crate::new::<CounterAddOp, _, _>(0, ());
+        let readers: Vec<_> = (0..1000).map(|_| w.clone()).collect();
+        w.publish();
+        assert!(w.active.is_empty());
+
+        // guards taken after a swap do not hold up the next publish, only the one after that
+        let mut guards: Vec<_> = readers
+            .iter()
+            .step_by(200)
+            .map(|r| r.enter().unwrap())
+            .collect();
+        w.append(CounterAddOp(1)).publish();
+        assert_eq!(w.active.len(), 5);
+
+        let epochs = std::sync::Arc::clone(&w.epochs);
+        guards.truncate(2);
+        assert!(!w.has_departed(&mut crate::lock_epochs(&epochs), false));
+        assert_eq!(w.active.len(), 2);
+        // a reader that leaves and comes back has seen the swap
+        let (rest, first) = (guards.split_off(1), guards);
+        drop(first);
+        let again = readers[0].enter().unwrap();
+        assert!(!w.has_departed(&mut crate::lock_epochs(&epochs), false));
+        assert_eq!(w.active.len(), 1);
+        drop(rest);
+        assert!(w.has_departed(&mut crate::lock_epochs(&epochs), false));
+        assert!(w.active.is_empty());
+
+        w.publish();
+        assert_eq!(*again, 1);
+        drop(again);
+        w.publish();
+    }
+
     #[test]
     fn wait_test() {
         use std::sync::{Arc, Barrier};
@@ -549,7 +1867,7 @@ mod tests {
         // and wait has been called.
         let held_epoch = Arc::new(AtomicUsize::new(1));
 
-        w.last_epochs = vec![2, 2, 1];
+        w.active = vec![(2, 1)];
         let mut epochs_slab = Slab::new();
         epochs_slab.insert(Arc::new(AtomicUsize::new(2)));
         epochs_slab.insert(Arc::new(AtomicUsize::new(2)));
@@ -561,7 +1879,7 @@ mod tests {
 
         // check writers waiting state before calling wait.
         let is_waiting_v = is_waiting.load(Ordering::Relaxed);
-        assert_eq!(false, is_waiting_v);
+        assert!(!is_waiting_v);
 
         let barrier2 = Arc::clone(&barrier);
         let test_epochs = Arc::new(Mutex::new(epochs_slab));
@@ -586,46 +1904,2357 @@ mod tests {
     }
 
     #[test]
-    fn flush_noblock() {
-        let mut w = crate::new::<CounterAddOp, _, _>(0, ());
+    fn delta_matches_data() {
+        use crate::{Delta, ReadGuard};
+        use std::sync::Arc;
+
+        #[derive(Clone)]
+        struct Add(i32);
+        impl Apply<i32, ()> for Add {
+            fn apply_first(&mut self, first: &mut i32, _: &i32, _: &mut ()) {
+                *first += self.0;
+            }
+
+            fn summarize<'a, I>(ops: I, _: &()) -> Option<Delta>
+            where
+                I: Iterator<Item = &'a Self>,
+            {
+                Some(Arc::new(ops.map(|op| op.0).collect::<Vec<_>>()))
+            }
+        }
+
+        let mut w = crate::new::<Add, _, _>(0, ());
         let r = w.clone();
-        w.append(CounterAddOp(42));
+        assert!(r.last_delta::<Vec<i32>>().is_none());
+
+        w.append(Add(1)).append(Add(2));
         w.publish();
-        assert_eq!(*r.enter().unwrap(), 42);
+        let guard = r.enter().unwrap();
+        assert_eq!(*guard, 3);
+        assert_eq!(*ReadGuard::delta::<Vec<i32>>(&guard).unwrap(), vec![1, 2]);
+        // a delta of the wrong type is not handed out
+        assert!(ReadGuard::delta::<Vec<u8>>(&guard).is_none());
+        drop(guard);
 
-        // pin the epoch
-        let _count = r.enter();
-        // refresh would hang here
-        assert_eq!(w.oplog.iter().skip(w.swap_index).count(), 0);
-        assert!(!w.has_pending_operations());
+        w.append(Add(4));
+        w.publish();
+        let guard = r.enter().unwrap();
+        assert_eq!(*guard, 7);
+        assert_eq!(*ReadGuard::delta::<Vec<i32>>(&guard).unwrap(), vec![4]);
+        drop(guard);
+
+        // the delta follows the data through mapped guards too
+        let guard = ReadGuard::map(r.enter().unwrap(), |v| v);
+        assert_eq!(*ReadGuard::delta::<Vec<i32>>(&guard).unwrap(), vec![4]);
+        drop(guard);
+
+        w.publish();
+        assert_eq!(*r.last_delta::<Vec<i32>>().unwrap(), Vec::<i32>::new());
     }
 
     #[test]
-    fn flush_no_refresh() {
-        let mut w = crate::new::<CounterAddOp, _, _>(0, ());
+    fn redundant_appends_are_dropped() {
+        use std::collections::BTreeSet;
 
-        // Until we refresh, writes are written directly instead of going to the
-        // oplog (because there can't be any readers on the w_handle table).
-        assert!(!w.has_pending_operations());
+        struct Ensure(u8);
+        impl Apply<BTreeSet<u8>, ()> for Ensure {
+            fn apply_first(&mut self, first: &mut BTreeSet<u8>, _: &BTreeSet<u8>, _: &mut ()) {
+                first.insert(self.0);
+            }
+
+            fn is_redundant(&self, current: &BTreeSet<u8>) -> bool {
+                current.contains(&self.0)
+            }
+        }
+
+        let mut w = crate::new::<Ensure, _, _>(BTreeSet::new(), ());
+        w.append(Ensure(1)).append(Ensure(2));
+        w.publish();
         w.publish();
+        assert_eq!(w.oplog.len(), 0);
+
+        // both keys already exist
+        w.append(Ensure(1)).append(Ensure(2));
         assert!(!w.has_pending_operations());
-        assert_eq!(w.refreshes, 1);
+        assert_eq!(w.oplog.len(), 0);
 
-        w.append(CounterAddOp(42));
-        assert!(w.has_pending_operations());
+        // once an op is pending, the writer no longer knows the state later ops apply to
+        w.append(Ensure(3)).append(Ensure(1)).append(Ensure(3));
+        assert_eq!(w.oplog.len(), 3);
         w.publish();
-        assert!(!w.has_pending_operations());
-        assert_eq!(w.refreshes, 2);
+        w.publish();
+        assert_eq!(*w.enter().unwrap(), (1..=3).collect::<BTreeSet<_>>());
+        assert_eq!(w.debug_copy_lens(), (3, 3));
 
-        w.append(CounterAddOp(42));
-        assert!(w.has_pending_operations());
+        // only the first of a run of repeats is checked, the rest stay pending
+        w.extend(vec![Ensure(4), Ensure(4), Ensure(4)]);
+        assert_eq!(w.oplog.len(), 3);
         w.publish();
-        assert!(!w.has_pending_operations());
-        assert_eq!(w.refreshes, 3);
+        w.publish();
+        assert_eq!(*w.enter().unwrap(), (1..=4).collect::<BTreeSet<_>>());
+        assert_eq!(w.oplog.len(), 0);
 
-        // Sanity check that a refresh would have been visible
+        // and once published, none of them are
+        w.extend(vec![Ensure(4), Ensure(4)]);
         assert!(!w.has_pending_operations());
-        w.publish();
-        assert_eq!(w.refreshes, 4);
+    }
+
+    #[test]
+    fn pending_publish_accepts_appends_while_readers_linger() {
+        struct Add(i32);
+        impl Apply<i32, ()> for Add {
+            fn apply_first(&mut self, first: &mut i32, _: &i32, _: &mut ()) {
+                *first += self.0;
+            }
+        }
+
+        let mut w = crate::new::<Add, _, _>(0, ());
+        let r = w.clone();
+        w.append(Add(1)).publish();
+
+        // the next publish does not wait for this guard, so it ends up on the write copy
+        let guard = r.enter().unwrap();
+        w.append(Add(2)).publish();
+        assert_eq!(*guard, 1);
+
+        w.append(Add(3));
+        let mut publish = w.begin_publish();
+        assert!(!publish.try_complete());
+        publish.append(Add(4));
+        assert!(!publish.try_complete());
+        assert!(!publish.is_complete());
+
+        drop(guard);
+        assert!(publish.try_complete());
+        assert!(publish.try_complete());
+        drop(publish);
+
+        // the op staged during the publish is left for the next one
+        assert_eq!(*r.enter().unwrap(), 6);
+        assert!(w.has_pending_operations());
+        w.publish();
+        assert_eq!(*r.enter().unwrap(), 10);
+    }
+
+    #[test]
+    fn shrink_policy_releases_spare_oplog_capacity() {
+        let mut w = crate::new::<CounterAddOp, _, _>(0, ());
+        w.extend((0..1000).map(CounterAddOp));
+        w.publish();
+        w.append(CounterAddOp(1)).publish();
+        // the default keeps the capacity of the burst around
+        assert!(w.oplog.capacity() >= 1000);
+
+        let mut w = crate::new::<CounterAddOp, _, _>(0, ());
+        w.set_shrink_policy(ShrinkPolicy::Shrink { ratio: 4 });
+        w.extend((0..1000).map(CounterAddOp));
+        w.publish();
+        w.append(CounterAddOp(1)).publish();
+        assert_eq!(w.oplog.len(), 1);
+        assert!(w.oplog.capacity() < 4);
+
+        // wrap the ring buffer around before the drain
+        let mut w = crate::new::<CounterAddOp, _, _>(0, ());
+        w.set_shrink_policy(ShrinkPolicy::Contiguous { ratio: 2 });
+        w.extend((0..6).map(CounterAddOp));
+        w.publish();
+        w.extend((0..6).map(CounterAddOp));
+        w.publish();
+        w.extend((0..100).map(CounterAddOp));
+        w.publish();
+        assert!(w.oplog.as_slices().1.is_empty());
+        assert_eq!(*w.enter().unwrap(), 15 + 15 + 4950);
+    }
+
+    #[test]
+    fn local_handle_snapshots_rc_data() {
+        use std::rc::Rc;
+
+        struct Push(Rc<str>);
+        impl Apply<Vec<Rc<str>>, ()> for Push {
+            fn apply_first(&mut self, first: &mut Vec<Rc<str>>, _: &Vec<Rc<str>>, _: &mut ()) {
+                first.push(Rc::clone(&self.0));
+            }
+
+            fn apply_second(self, _: &Vec<Rc<str>>, second: &mut Vec<Rc<str>>, _: &mut ()) {
+                second.push(self.0);
+            }
+        }
+
+        let shared: Rc<str> = Rc::from("shared");
+        let mut w = crate::new_local::<Push, _, _>(Vec::new(), ());
+        w.append(Push(Rc::clone(&shared))).publish();
+
+        w.append(Push(Rc::clone(&shared)));
+        assert_eq!(w.enter().len(), 1);
+        assert!(w.has_pending_operations());
+        w.publish();
+        w.publish();
+        assert_eq!(w.enter().len(), 2);
+
+        // both copies hold both pushes, and nothing else holds on to the Rc
+        assert_eq!(Rc::strong_count(&shared), 5);
+        let data = w.take();
+        assert_eq!(Rc::strong_count(&shared), 3);
+        drop(data);
+        assert_eq!(Rc::strong_count(&shared), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "metrics")]
+    fn high_water_marks_track_peak_oplog_len() {
+        let mut w = crate::new::<CounterAddOp, _, _>(0, ());
+        assert_eq!(w.high_water_marks().oplog_len, 0);
+
+        // pending operations count even before they are published
+        w.extend((0..1000).map(CounterAddOp));
+        assert_eq!(w.high_water_marks().oplog_len, 1000);
+        w.publish();
+        assert_eq!(w.high_water_marks().oplog_len, 1000);
+
+        // the previous batch is still in the oplog until the next publish drains it
+        w.extend((0..10).map(CounterAddOp));
+        w.publish();
+        assert_eq!(w.high_water_marks().oplog_len, 1010);
+        w.publish();
+        assert_eq!(w.oplog.len(), 0);
+        assert_eq!(w.high_water_marks().oplog_len, 1010);
+
+        w.extend((0..1500).map(CounterAddOp));
+        w.publish();
+        w.extend((0..20).map(CounterAddOp));
+        w.publish();
+        assert_eq!(w.high_water_marks().oplog_len, 1520);
+
+        let readers: Vec<_> = (0..4).map(|_| w.clone()).collect();
+        w.publish();
+        assert_eq!(w.high_water_marks().readers, 5);
+        drop(readers);
+
+        w.reset_high_water_marks();
+        assert_eq!(
+            w.high_water_marks(),
+            crate::HighWaterMarks {
+                oplog_len: 0,
+                readers: 1,
+            }
+        );
+        w.extend((0..5).map(CounterAddOp));
+        w.publish();
+        assert_eq!(w.high_water_marks().oplog_len, 5);
+    }
+
+    #[test]
+    #[cfg(feature = "metrics")]
+    fn phase_timings_cover_both_apply_phases() {
+        use std::time::Duration;
+
+        struct Slow(u64);
+        impl Apply<u64, ()> for Slow {
+            fn apply_first(&mut self, first: &mut u64, _: &u64, _: &mut ()) {
+                std::thread::sleep(Duration::from_millis(1));
+                *first += self.0;
+            }
+        }
+
+        let mut w = crate::new::<Slow, _, _>(0, ());
+        w.extend((0..5).map(Slow));
+        w.publish();
+        w.extend((0..3).map(Slow));
+        w.publish();
+
+        let timings = w.phase_timings();
+        assert!(timings.apply_second >= Duration::from_millis(5));
+        assert!(timings.apply_first >= Duration::from_millis(3));
+        assert_eq!(*w.enter().unwrap(), 13);
+    }
+
+    #[test]
+    #[cfg(feature = "metrics")]
+    fn phase_timings_only_cover_the_latest_publish() {
+        use std::sync::mpsc;
+        use std::thread;
+        use std::time::Duration;
+
+        struct Slow(u64);
+        impl Apply<u64, ()> for Slow {
+            fn apply_first(&mut self, first: &mut u64, _: &u64, _: &mut ()) {
+                std::thread::sleep(Duration::from_millis(1));
+                *first += self.0;
+            }
+        }
+
+        let mut w = crate::new::<Slow, _, _>(0, ());
+        w.extend((0..5).map(Slow));
+        w.publish();
+        let published = w.phase_timings();
+        assert!(published.apply_first >= Duration::from_millis(5));
+
+        // running ahead is not a publish
+        w.extend((0..3).map(Slow));
+        assert_eq!(*w.current(), 13);
+        assert_eq!(w.phase_timings(), published);
+
+        // a publish that has to wait for a reader records the wait
+        let r = w.clone();
+        let (tx, rx) = mpsc::channel();
+        let reader = thread::spawn(move || {
+            let guard = r.enter().unwrap();
+            tx.send(()).unwrap();
+            thread::sleep(Duration::from_millis(20));
+            drop(guard);
+        });
+        rx.recv().unwrap();
+        // the first publish swaps the reader's copy out, the second has to reuse it
+        w.publish().publish();
+        reader.join().unwrap();
+        assert!(w.phase_timings().wait >= Duration::from_millis(10));
+
+        // and one that does not wait does not report the previous wait
+        let mut pending = w.begin_publish();
+        assert!(pending.try_complete());
+        drop(pending);
+        assert_eq!(w.phase_timings().wait, Duration::ZERO);
+        assert_eq!(*w.enter().unwrap(), 13);
+    }
+
+    #[test]
+    #[cfg(feature = "metrics")]
+    fn estimated_wait_tracks_recent_waits() {
+        use std::sync::mpsc;
+        use std::thread;
+        use std::time::Duration;
+
+        let mut w = crate::new::<CounterAddOp, _, _>(0, ());
+        assert_eq!(w.estimated_wait(), Duration::ZERO);
+
+        for _ in 0..4 {
+            let r = w.clone();
+            let (tx, rx) = mpsc::channel();
+            let reader = thread::spawn(move || {
+                let guard = r.enter().unwrap();
+                tx.send(()).unwrap();
+                thread::sleep(Duration::from_millis(20));
+                drop(guard);
+            });
+            rx.recv().unwrap();
+            // the reader is active at this swap, so the publish after it has to wait
+            w.append(CounterAddOp(1)).publish();
+            w.append(CounterAddOp(1)).publish();
+            reader.join().unwrap();
+        }
+        let slow = w.estimated_wait();
+        assert!(slow >= Duration::from_millis(5), "{:?}", slow);
+
+        // publishes without readers pull the estimate down
+        let mut last = slow;
+        for _ in 0..8 {
+            w.append(CounterAddOp(1)).publish();
+            assert!(w.estimated_wait() < last);
+            last = w.estimated_wait();
+        }
+        assert!(last < slow / 4);
+    }
+
+    #[test]
+    fn deferred_drops_go_to_the_reclaimer() {
+        use crate::{Reclaim, Reclaimed};
+        use std::sync::atomic::AtomicUsize as StdAtomicUsize;
+        use std::sync::Arc;
+
+        static DROPPED: StdAtomicUsize = StdAtomicUsize::new(0);
+        #[derive(Clone)]
+        struct Tracked;
+        impl Drop for Tracked {
+            fn drop(&mut self) {
+                DROPPED.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        struct Pop;
+        impl Apply<Vec<Tracked>, ()> for Pop {
+            fn apply_first(&mut self, first: &mut Vec<Tracked>, _: &Vec<Tracked>, _: &mut ()) {
+                first.pop();
+            }
+
+            fn apply_first_deferred(
+                &mut self,
+                first: &mut Vec<Tracked>,
+                _: &Vec<Tracked>,
+                _: &mut (),
+                reclaim: &mut Reclaim,
+            ) {
+                reclaim.defer(first.pop());
+            }
+
+            fn apply_second_deferred(
+                self,
+                _: &Vec<Tracked>,
+                second: &mut Vec<Tracked>,
+                _: &mut (),
+                reclaim: &mut Reclaim,
+            ) {
+                reclaim.defer(second.pop());
+            }
+        }
+
+        let mut w = crate::new::<Pop, _, _>(vec![Tracked, Tracked, Tracked], ());
+        let batches = Arc::new(Mutex::new(Vec::new()));
+        let b = Arc::clone(&batches);
+        w.set_reclaimer(move |batch: Reclaimed| b.lock().unwrap().push(batch));
+
+        w.append(Pop).append(Pop).publish();
+        w.publish();
+        assert_eq!(w.enter().unwrap().len(), 1);
+        // nothing removed from either copy has been dropped yet
+        assert_eq!(DROPPED.load(Ordering::SeqCst), 0);
+        let lens: Vec<_> = batches.lock().unwrap().iter().map(Reclaimed::len).collect();
+        assert_eq!(lens, [2, 2]);
+
+        batches.lock().unwrap().clear();
+        assert_eq!(DROPPED.load(Ordering::SeqCst), 4);
+
+        // without a reclaimer, deferred values are dropped by the publish that removed them
+        drop(w);
+        let mut w = crate::new::<Pop, _, _>(vec![Tracked], ());
+        DROPPED.store(0, Ordering::SeqCst);
+        w.append(Pop).publish();
+        assert_eq!(DROPPED.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn readers_wait_for_a_generation_published_on_another_thread() {
+        use std::sync::mpsc;
+
+        let mut w = crate::new::<CounterAddOp, _, _>(0, ());
+        let r = w.clone();
+        let (tx, rx) = mpsc::channel();
+        let (seen_tx, seen_rx) = mpsc::channel();
+
+        let reader = std::thread::spawn(move || {
+            let generation = rx.recv().unwrap();
+            assert!(r.wait_for_generation(generation));
+            seen_tx.send(*r.enter().unwrap()).unwrap();
+            // the writer is dropped before it ever publishes this one
+            assert!(!r.wait_for_generation(generation + 10));
+        });
+
+        w.append(CounterAddOp(7));
+        tx.send(w.generation() + 1).unwrap();
+        w.handoff();
+        let writer = std::thread::spawn(move || {
+            w.publish();
+            w.append(CounterAddOp(1)).publish();
+            assert_eq!(w.generation(), 2);
+            // keep the data alive until the reader has looked at it
+            seen_rx.recv().unwrap()
+        });
+        assert!(writer.join().unwrap() >= 7);
+        reader.join().unwrap();
+    }
+
+    #[test]
+    fn publish_if_stale_publishes_after_max_age() {
+        use std::time::Duration;
+
+        let mut w = crate::new::<CounterAddOp, _, _>(0, ());
+        w.append(CounterAddOp(1));
+        assert!(!w.publish_if_stale(Duration::from_secs(3600)));
+        assert_eq!(w.generation(), 0);
+
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(w.publish_if_stale(Duration::from_millis(10)));
+        assert_eq!(w.generation(), 1);
+        assert_eq!(*w.enter().unwrap(), 1);
+
+        // publishing restarts the clock, even without any operations
+        assert!(!w.publish_if_stale(Duration::from_secs(3600)));
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(w.publish_if_stale(Duration::from_millis(10)));
+        assert_eq!(w.generation(), 2);
+    }
+
+    #[test]
+    fn transactions_commit_or_roll_back_as_a_whole() {
+        let mut w = crate::new::<CounterAddOp, _, _>(0, ());
+        let r = w.clone();
+        w.append(CounterAddOp(1)).publish();
+        w.append(CounterAddOp(2));
+
+        let mut tx = w.transaction();
+        tx.append(CounterAddOp(10)).append(CounterAddOp(20));
+        assert_eq!(tx.len(), 2);
+        tx.rollback();
+
+        // dropping a transaction rolls it back too
+        let mut tx = w.transaction();
+        tx.extend(vec![CounterAddOp(100)]);
+        drop(tx);
+        assert_eq!(w.oplog.len(), 2);
+
+        w.publish();
+        assert_eq!(*r.enter().unwrap(), 3);
+
+        let mut tx = w.transaction();
+        tx.append(CounterAddOp(30)).append(CounterAddOp(40));
+        tx.commit();
+        assert!(w.has_pending_operations());
+        assert_eq!(*r.enter().unwrap(), 3);
+        w.publish();
+        assert_eq!(*r.enter().unwrap(), 73);
+    }
+
+    #[test]
+    fn current_reads_unpublished_ops_without_exposing_them() {
+        let mut w = crate::new_checked_hash::<CounterAddOp, _, _>(0, ());
+        let r = w.clone();
+        w.append(CounterAddOp(1)).publish();
+
+        w.append(CounterAddOp(2));
+        assert_eq!(*w.current(), 3);
+        w.append(CounterAddOp(3));
+        assert_eq!(*w.current(), 6);
+        assert_eq!(*w.current(), 6);
+        assert_eq!(*r.enter().unwrap(), 1);
+        assert!(w.has_pending_operations());
+
+        // the ops applied by `current` are not applied to the write copy again
+        w.publish();
+        assert_eq!(*r.enter().unwrap(), 6);
+        w.publish();
+        assert_eq!(*r.enter().unwrap(), 6);
+        assert_eq!(*w.current(), 6);
+        w.append(CounterAddOp(4)).publish();
+        assert_eq!(*r.enter().unwrap(), 10);
+        w.publish();
+    }
+
+    #[test]
+    fn current_leaves_finalizing_to_the_publish() {
+        #[derive(Clone, Debug, PartialEq)]
+        struct Counted {
+            sum: u64,
+            finalized: usize,
+        }
+
+        struct Add(u64);
+        impl Apply<Counted, ()> for Add {
+            fn apply_first(&mut self, first: &mut Counted, _: &Counted, _: &mut ()) {
+                first.sum += self.0;
+            }
+
+            fn finalize_first(first: &mut Counted, _: &Counted, _: &mut ()) {
+                first.finalized += 1;
+            }
+        }
+
+        let init = Counted {
+            sum: 0,
+            finalized: 0,
+        };
+        let mut w = crate::new_checked::<Add, _, _>(init, ());
+        let r = w.clone();
+        w.append(Add(1));
+        assert_eq!(w.current().finalized, 0);
+        w.append(Add(2));
+        assert_eq!(w.current().sum, 3);
+        assert_eq!(w.current().finalized, 0);
+
+        // one batch, so one finalize on each copy, and the checked publishes find them equal
+        w.publish();
+        assert_eq!(r.enter().unwrap().finalized, 1);
+        w.append(Add(3));
+        w.current();
+        w.publish();
+        w.publish();
+        assert_eq!(
+            *r.enter().unwrap(),
+            Counted {
+                sum: 6,
+                finalized: 2
+            }
+        );
+    }
+
+    #[test]
+    fn homogeneous_runs_are_applied_together() {
+        use crate::Reclaim;
+
+        #[derive(Debug)]
+        enum Op {
+            AddAll(i64),
+            Push(i64),
+        }
+        // the auxiliary records the length of every run, and which copy it went to
+        type Runs = Vec<(bool, usize)>;
+        impl Apply<Vec<i64>, Runs> for Op {
+            fn apply_first(&mut self, first: &mut Vec<i64>, _: &Vec<i64>, _: &mut Runs) {
+                match *self {
+                    Op::AddAll(n) => first.iter_mut().for_each(|x| *x += n),
+                    Op::Push(n) => first.push(n),
+                }
+            }
+
+            fn is_homogeneous_with(&self, next: &Self) -> bool {
+                matches!((self, next), (Op::AddAll(_), Op::AddAll(_)))
+            }
+
+            fn apply_first_homogeneous(
+                ops: &mut [Self],
+                first: &mut Vec<i64>,
+                second: &Vec<i64>,
+                runs: &mut Runs,
+                _: &mut Reclaim,
+            ) {
+                runs.push((true, ops.len()));
+                if let [Op::AddAll(_), ..] = ops {
+                    let n: i64 = ops
+                        .iter()
+                        .map(|op| match op {
+                            Op::AddAll(n) => n,
+                            Op::Push(_) => unreachable!(),
+                        })
+                        .sum();
+                    first.iter_mut().for_each(|x| *x += n);
+                } else {
+                    for op in ops {
+                        op.apply_first(first, second, runs);
+                    }
+                }
+            }
+
+            fn apply_second_homogeneous(
+                ops: std::vec::Drain<'_, Self>,
+                first: &Vec<i64>,
+                second: &mut Vec<i64>,
+                runs: &mut Runs,
+                _: &mut Reclaim,
+            ) {
+                runs.push((false, ops.len()));
+                for op in ops {
+                    op.apply_second(first, second, runs);
+                }
+            }
+        }
+
+        let mut w = crate::new::<Op, _, _>(vec![0; 4], Vec::new());
+        w.extend((0..5).map(|_| Op::AddAll(1)));
+        w.append(Op::Push(0)).append(Op::Push(0));
+        w.extend((0..3).map(|_| Op::AddAll(2)));
+        w.publish();
+        assert_eq!(*w.enter().unwrap(), [11, 11, 11, 11, 6, 6]);
+        assert_eq!(
+            std::mem::take(w.auxiliary_mut()),
+            [(true, 5), (true, 1), (true, 1), (true, 3)]
+        );
+
+        w.publish();
+        assert_eq!(*w.enter().unwrap(), [11, 11, 11, 11, 6, 6]);
+        assert_eq!(
+            std::mem::take(w.auxiliary_mut()),
+            [(false, 5), (false, 1), (false, 1), (false, 3)]
+        );
+        let data = w.take();
+        assert_eq!(*data, [11, 11, 11, 11, 6, 6]);
+    }
+
+    #[test]
+    fn reordering_independent_ops_preserves_the_result() {
+        use crate::Reclaim;
+
+        // adds and multiplies each commute among themselves, but not with one another
+        #[derive(Clone, Copy, Debug)]
+        enum Op {
+            Add(usize, i64),
+            Mul(usize, i64),
+        }
+        impl Op {
+            fn apply(self, data: &mut [i64]) {
+                match self {
+                    Op::Add(i, n) => data[i] += n,
+                    Op::Mul(i, n) => data[i] *= n,
+                }
+            }
+        }
+        // the auxiliary counts the runs applied to the first copy
+        impl Apply<Vec<i64>, usize> for Op {
+            fn apply_first(&mut self, first: &mut Vec<i64>, _: &Vec<i64>, _: &mut usize) {
+                self.apply(first);
+            }
+
+            fn is_homogeneous_with(&self, next: &Self) -> bool {
+                matches!(
+                    (self, next),
+                    (Op::Add(..), Op::Add(..)) | (Op::Mul(..), Op::Mul(..))
+                )
+            }
+
+            fn conflicts_with(&self, other: &Self) -> bool {
+                match (self, other) {
+                    (Op::Add(i, _), Op::Mul(j, _)) | (Op::Mul(i, _), Op::Add(j, _)) => i == j,
+                    _ => false,
+                }
+            }
+
+            fn apply_first_homogeneous(
+                ops: &mut [Self],
+                first: &mut Vec<i64>,
+                second: &Vec<i64>,
+                runs: &mut usize,
+                reclaim: &mut Reclaim,
+            ) {
+                *runs += 1;
+                for op in ops {
+                    op.apply_first_deferred(first, second, runs, reclaim);
+                }
+            }
+        }
+
+        let ops = [
+            Op::Add(0, 1),
+            Op::Mul(1, 2),
+            Op::Add(1, 3),
+            Op::Mul(0, 2),
+            Op::Add(2, 1),
+            Op::Mul(2, 3),
+            Op::Add(0, 4),
+            Op::Mul(1, 5),
+        ];
+        let mut expected = vec![1; 3];
+        for op in ops {
+            op.apply(&mut expected);
+        }
+
+        let mut w = crate::new_checked_hash::<Op, _, _>(vec![1; 3], 0);
+        w.extend(ops);
+        w.publish();
+        assert_eq!(*w.enter().unwrap(), expected);
+        // Mul(0, 2) joins Mul(1, 2), and Add(0, 4) moves past Mul(2, 3) to join Add(2, 1), but
+        // Add(1, 3) cannot move past Mul(1, 2)
+        assert_eq!(*w.auxiliary(), 4);
+
+        // the second copy sees the same order
+        w.extend(ops);
+        w.publish();
+        for op in ops {
+            op.apply(&mut expected);
+        }
+        assert_eq!(*w.enter().unwrap(), expected);
+        w.publish();
+        assert_eq!(*w.enter().unwrap(), expected);
+    }
+
+    #[test]
+    fn data_age_measures_time_since_last_publish() {
+        use std::time::Duration;
+
+        let mut w = crate::new::<CounterAddOp, _, _>(0, ());
+        let mut r = w.clone();
+        w.append(CounterAddOp(1)).publish();
+        std::thread::sleep(Duration::from_millis(50));
+
+        let guard = r.enter().unwrap();
+        let before = crate::ReadGuard::data_age(&guard);
+        assert!(before >= Duration::from_millis(50));
+        drop(guard);
+        // every guard on the same copy sees the same timestamp
+        let send = r.enter_send().unwrap();
+        assert!(crate::SendReadGuard::data_age(&send) >= before);
+        drop(send);
+
+        w.append(CounterAddOp(2)).publish();
+        let guard = r.enter().unwrap();
+        assert!(crate::ReadGuard::data_age(&guard) < before);
+        let owned = crate::ReadGuard::into_owned(guard);
+        assert!(crate::OwnedReadGuard::data_age(&owned) < before);
+        drop(owned);
+    }
+
+    #[test]
+    fn shared_ranges_are_referenced_until_published() {
+        use crate::{OplogStore, SharedOplog};
+        use std::sync::Arc;
+
+        static CLONES: AtomicUsize = AtomicUsize::new(0);
+        struct Add(u64);
+        impl Clone for Add {
+            fn clone(&self) -> Self {
+                CLONES.fetch_add(1, Ordering::Relaxed);
+                Add(self.0)
+            }
+        }
+        impl Apply<u64, ()> for Add {
+            fn apply_first(&mut self, first: &mut u64, _: &u64, _: &mut ()) {
+                *first += self.0;
+            }
+        }
+
+        let segment: Arc<Vec<Add>> = Arc::new((1..=1000).map(Add).collect());
+
+        // copying the range in clones every op up front
+        let mut w = crate::new::<Add, _, _>(0, ());
+        w.append_range(Arc::clone(&segment), 0..1000);
+        assert_eq!(CLONES.swap(0, Ordering::Relaxed), 1000);
+        assert_eq!(w.oplog.len(), 1000);
+        w.publish();
+        assert_eq!(*w.enter().unwrap(), 500500);
+
+        // a shared oplog holds a single reference until the range is applied
+        let mut w = crate::new_with_oplog::<Add, _, _, _>(0, (), SharedOplog::new());
+        w.append_range(Arc::clone(&segment), 0..600);
+        w.append(Add(1));
+        w.append_range(Arc::clone(&segment), 600..1000);
+        assert_eq!(CLONES.swap(0, Ordering::Relaxed), 0);
+        assert_eq!(w.oplog.len(), 1001);
+        assert_eq!(w.oplog.entries(), 3);
+
+        // the first copy needs owned ops, and the second copy applies those same ops later
+        w.publish();
+        assert_eq!(*w.enter().unwrap(), 500501);
+        assert_eq!(CLONES.swap(0, Ordering::Relaxed), 1000);
+        w.append_range(Arc::clone(&segment), 0..1);
+        w.publish();
+        assert_eq!(*w.enter().unwrap(), 500502);
+        assert_eq!(CLONES.swap(0, Ordering::Relaxed), 1);
+        assert_eq!(w.oplog.entries(), 1);
+        assert_eq!(Arc::strong_count(&segment), 1);
+    }
+
+    #[test]
+    fn wakers_drive_publishes_and_generation_waits() {
+        use std::future::Future;
+        use std::sync::{mpsc, Arc};
+        use std::task::{Context, Poll, Wake};
+        use std::thread;
+
+        struct ThreadWaker {
+            thread: thread::Thread,
+            wakes: AtomicUsize,
+        }
+        impl Wake for ThreadWaker {
+            fn wake(self: Arc<Self>) {
+                self.wakes.fetch_add(1, Ordering::SeqCst);
+                self.thread.unpark();
+            }
+        }
+
+        fn block_on<F: Future>(f: F, waker: &Arc<ThreadWaker>) -> F::Output {
+            let waker = Arc::clone(waker).into();
+            let mut cx = Context::from_waker(&waker);
+            let mut f = Box::pin(f);
+            loop {
+                match f.as_mut().poll(&mut cx) {
+                    Poll::Ready(output) => return output,
+                    Poll::Pending => thread::park(),
+                }
+            }
+        }
+
+        let mut w = crate::new::<CounterAddOp, _, _>(0, ());
+        let r = w.clone();
+        let waiter = w.clone();
+        w.append(CounterAddOp(1)).publish();
+
+        let (entered_tx, entered_rx) = mpsc::channel();
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        let reader = thread::spawn(move || {
+            let guard = r.enter().unwrap();
+            entered_tx.send(()).unwrap();
+            release_rx.recv().unwrap();
+            drop(guard);
+        });
+        entered_rx.recv().unwrap();
+
+        // this publish does not wait for the guard, so the guard ends up on the write copy
+        w.append(CounterAddOp(2)).publish();
+        w.append(CounterAddOp(3));
+        let visible_at = w.generation() + 1;
+
+        let generation_waiter = thread::spawn(move || {
+            let waker = Arc::new(ThreadWaker {
+                thread: thread::current(),
+                wakes: AtomicUsize::new(0),
+            });
+            let reached = block_on(waiter.wait_for_generation_async(visible_at), &waker);
+            (reached, *waiter.enter().unwrap())
+        });
+
+        let waker = Arc::new(ThreadWaker {
+            thread: thread::current(),
+            wakes: AtomicUsize::new(0),
+        });
+        {
+            let waker = Arc::clone(&waker).into();
+            let mut cx = Context::from_waker(&waker);
+            assert_eq!(w.poll_publish(&mut cx), Poll::Pending);
+        }
+        release_tx.send(()).unwrap();
+        block_on(w.publish_async(), &waker);
+        reader.join().unwrap();
+        // the departing reader woke us up
+        assert!(waker.wakes.load(Ordering::SeqCst) >= 1);
+
+        assert_eq!(generation_waiter.join().unwrap(), (true, 6));
+    }
+
+    #[test]
+    fn finalize_runs_once_per_batch() {
+        #[derive(Clone, Default)]
+        struct Totalled {
+            values: Vec<i32>,
+            total: i32,
+        }
+
+        // keeps the total up to date after every op
+        struct EagerPush(i32);
+        impl Apply<Totalled, usize> for EagerPush {
+            fn apply_first(&mut self, first: &mut Totalled, _: &Totalled, _: &mut usize) {
+                first.values.push(self.0);
+                first.total = first.values.iter().sum();
+            }
+        }
+
+        // recomputes the total once the whole batch has been applied
+        struct LazyPush(i32);
+        impl Apply<Totalled, usize> for LazyPush {
+            fn apply_first(&mut self, first: &mut Totalled, _: &Totalled, _: &mut usize) {
+                first.values.push(self.0);
+            }
+
+            fn finalize_first(first: &mut Totalled, _: &Totalled, finalized: &mut usize) {
+                first.total = first.values.iter().sum();
+                *finalized += 1;
+            }
+        }
+
+        let mut eager = crate::new::<EagerPush, _, _>(Totalled::default(), 0);
+        let mut lazy = crate::new::<LazyPush, _, _>(Totalled::default(), 0);
+        for batch in [&[1, 2, 3][..], &[4], &[5, 6]] {
+            eager.extend(batch.iter().copied().map(EagerPush));
+            lazy.extend(batch.iter().copied().map(LazyPush));
+            eager.publish();
+            lazy.publish();
+            let (e, l) = (eager.enter().unwrap(), lazy.enter().unwrap());
+            assert_eq!(e.values, l.values);
+            assert_eq!(e.total, l.total);
+        }
+        assert_eq!(lazy.enter().unwrap().total, 21);
+        // the first copy was finalized for each of the three batches, and the second copy for the
+        // first two so far
+        assert_eq!(lazy.auxiliary, 5);
+
+        // publishing an empty batch finalizes the second copy for the last batch only
+        lazy.publish();
+        assert_eq!(lazy.enter().unwrap().total, 21);
+        assert_eq!(lazy.auxiliary, 6);
+        lazy.publish();
+        assert_eq!(lazy.auxiliary, 6);
+    }
+
+    #[test]
+    fn priority_ops_jump_the_pending_queue() {
+        use crate::SharedOplog;
+        use std::sync::Arc;
+
+        #[derive(Clone, Debug)]
+        enum Op {
+            Push(u32),
+            Double,
+        }
+        impl Apply<Vec<u32>, ()> for Op {
+            fn apply_first(&mut self, first: &mut Vec<u32>, _: &Vec<u32>, _: &mut ()) {
+                match self {
+                    Op::Push(v) => first.push(*v),
+                    Op::Double => first.iter_mut().for_each(|v| *v *= 2),
+                }
+            }
+        }
+
+        let mut w = crate::new_checked_hash::<Op, _, _>(vec![], ());
+        w.append(Op::Push(1)).publish();
+        // the previously published op is still in the oplog, but the priority op goes after it
+        w.append(Op::Push(2)).append(Op::Push(3));
+        w.append_priority(Op::Double);
+        w.publish();
+        assert_eq!(*w.enter().unwrap(), [2, 2, 3]);
+
+        // ops applied to the write copy by `current` stay ahead of it too
+        w.append(Op::Push(4));
+        assert_eq!(*w.current(), [2, 2, 3, 4]);
+        w.append(Op::Push(5));
+        w.append_priority(Op::Double);
+        // the latest priority op goes first
+        w.append_priority(Op::Push(6));
+        w.publish();
+        assert_eq!(*w.enter().unwrap(), [4, 4, 6, 8, 12, 5]);
+        w.publish();
+        assert_eq!(*w.enter().unwrap(), [4, 4, 6, 8, 12, 5]);
+
+        // without anything pending, it is a regular append
+        w.append_priority(Op::Double).publish();
+        assert_eq!(*w.enter().unwrap(), [8, 8, 12, 16, 24, 10]);
+
+        // stores that split shared ranges insert in the middle of them
+        let ops = Arc::new(vec![Op::Push(1), Op::Push(2), Op::Push(3)]);
+        let mut w = crate::new_with_oplog::<Op, _, _, _>(vec![], (), SharedOplog::new());
+        w.append_range(Arc::clone(&ops), 0..1).publish();
+        w.append_range(Arc::clone(&ops), 1..3);
+        w.append_priority(Op::Double);
+        w.publish();
+        assert_eq!(*w.enter().unwrap(), [2, 2, 3]);
+        w.publish();
+        assert_eq!(*w.enter().unwrap(), [2, 2, 3]);
+    }
+
+    #[test]
+    fn rejected_publish_leaves_the_oplog_intact() {
+        use std::error::Error;
+
+        // the auxiliary is the largest value an op may add
+        struct Add(u64);
+        impl Apply<u64, u64> for Add {
+            fn apply_first(&mut self, first: &mut u64, _: &u64, _: &mut u64) {
+                *first += self.0;
+            }
+
+            fn validate(&self, _: &u64, max: &u64) -> Result<(), Box<dyn Error + Send + Sync>> {
+                if self.0 > *max {
+                    return Err(format!("{} is too large", self.0).into());
+                }
+                Ok(())
+            }
+        }
+
+        let mut w = crate::new::<Add, _, _>(0, 5);
+        let r = w.clone();
+        w.append(Add(1));
+        assert!(w.try_publish().is_ok());
+
+        // the previously published op is still in the oplog, but is not validated again
+        *w.auxiliary_mut() = 0;
+        assert!(w.try_publish().is_ok());
+        *w.auxiliary_mut() = 5;
+
+        w.extend(vec![Add(2), Add(3), Add(7)]);
+        // ops applied ahead of time to the write copy are not exposed either
+        assert_eq!(*w.current(), 13);
+        let rejected = w.try_publish().err().unwrap();
+        assert_eq!(rejected.index(), 2);
+        assert_eq!(
+            rejected.to_string(),
+            "pending operation 2 was rejected: 7 is too large"
+        );
+        assert_eq!(*r.enter().unwrap(), 1);
+        assert_eq!(w.oplog.len() - w.swap_index, 3);
+        let refreshes = w.refreshes;
+
+        let rejected = w.try_publish().err().unwrap();
+        assert_eq!(rejected.into_error().to_string(), "7 is too large");
+        assert_eq!(w.refreshes, refreshes);
+
+        *w.auxiliary_mut() = 10;
+        assert!(w.try_publish().is_ok());
+        assert_eq!(*r.enter().unwrap(), 13);
+        w.publish();
+        assert_eq!(*r.enter().unwrap(), 13);
+
+        // the index counts in the order the publish would apply the ops
+        w.append(Add(1)).append(Add(2));
+        w.append_priority(Add(50));
+        assert_eq!(w.try_publish().err().unwrap().index(), 0);
+    }
+
+    #[test]
+    fn first_enter_does_not_take_the_epochs_lock() {
+        use std::sync::mpsc;
+        use std::thread;
+        use std::time::Duration;
+
+        let mut w = crate::new::<CounterAddOp, _, _>(0, ());
+        w.append(CounterAddOp(1)).publish();
+        // cloning registers the epoch, which does take the lock
+        let r = w.clone();
+
+        // with the epochs lock held elsewhere, the reader must still get through
+        let held = crate::lock_epochs(&w.epochs);
+        let (tx, rx) = mpsc::channel();
+        let reader = thread::spawn(move || {
+            tx.send(*r.enter().unwrap()).unwrap();
+            // dropping the handle deregisters it, which does take the lock
+            r
+        });
+        assert_eq!(rx.recv_timeout(Duration::from_secs(10)), Ok(1));
+        drop(held);
+        drop(reader.join().unwrap());
+    }
+
+    #[test]
+    fn consistent_reads_see_a_single_version() {
+        use std::sync::atomic::AtomicBool;
+        use std::sync::Arc;
+        use std::thread;
+
+        // every op sets both halves, so a torn read shows up as mismatched halves
+        struct Set(u64);
+        impl Apply<(u64, u64), ()> for Set {
+            fn apply_first(&mut self, first: &mut (u64, u64), _: &(u64, u64), _: &mut ()) {
+                *first = (self.0, self.0);
+            }
+        }
+
+        let mut w = crate::new::<Set, _, _>((0, 0), ());
+        let r = w.clone();
+        let done = Arc::new(AtomicBool::new(false));
+        let reader = thread::spawn({
+            let r = r.clone();
+            let done = Arc::clone(&done);
+            move || loop {
+                let (a, b) = r
+                    .read_consistent(|pair| {
+                        let a = pair.0;
+                        thread::yield_now();
+                        (a, pair.1)
+                    })
+                    .unwrap();
+                assert_eq!(a, b);
+                if done.load(Ordering::Relaxed) {
+                    break;
+                }
+            }
+        });
+
+        for i in 1..=200 {
+            w.append(Set(i)).publish();
+        }
+        done.store(true, Ordering::Relaxed);
+        reader.join().unwrap();
+
+        drop(w);
+        assert_eq!(r.read_consistent(|pair| pair.0), None);
+    }
+
+    #[test]
+    fn derived_data_is_built_once_per_change() {
+        use crate::DerivedCache;
+        use std::sync::Arc;
+
+        #[derive(Clone, Debug, Default, PartialEq)]
+        struct Values {
+            values: Vec<u64>,
+            total: Option<Arc<u64>>,
+        }
+        // the cache, and the number of times it was built
+        type Aux = (DerivedCache<u64>, usize);
+        struct Push(u64);
+        impl Apply<Values, Aux> for Push {
+            fn apply_first(&mut self, first: &mut Values, _: &Values, aux: &mut Aux) {
+                first.values.push(self.0);
+                aux.0.invalidate();
+            }
+            fn finalize_first(first: &mut Values, _: &Values, (cache, builds): &mut Aux) {
+                first.total = Some(cache.refresh(|| {
+                    *builds += 1;
+                    first.values.iter().sum()
+                }));
+            }
+            fn finalize_second(_: &Values, second: &mut Values, (cache, _): &mut Aux) {
+                second.total = cache.current();
+            }
+        }
+
+        let mut w = crate::new_checked::<Push, _, _>(Values::default(), (DerivedCache::new(), 0));
+        let r = w.clone();
+        for i in 1..=10 {
+            w.append(Push(i)).append(Push(i));
+            if i % 3 == 0 {
+                w.current();
+            }
+            w.publish();
+            assert_eq!(**r.enter().unwrap().total.as_ref().unwrap(), i * (i + 1));
+        }
+        // publishes without changes build nothing, and bring the other copy up to date
+        w.publish();
+        w.publish();
+        assert_eq!(w.auxiliary().1, 10);
+
+        // both copies share the one build
+        let read = r.enter().unwrap().total.clone().unwrap();
+        let write = w.current().total.clone().unwrap();
+        assert!(Arc::ptr_eq(&read, &write));
+    }
+
+    #[test]
+    fn try_enter_tells_draining_from_dropped() {
+        use crate::WriterGone;
+        use std::thread;
+
+        let mut w = crate::new::<CounterAddOp, _, _>(0, ());
+        let r = w.clone();
+        let blocker = w.clone();
+        w.append(CounterAddOp(1)).publish().handoff();
+        assert_eq!(*r.try_enter().unwrap(), 1);
+
+        // a guard keeps the writer from finishing its teardown
+        let guard = blocker.enter().unwrap();
+        let writer = thread::spawn(move || drop(w));
+        let gone = loop {
+            match r.try_enter() {
+                Ok(_) => thread::yield_now(),
+                Err(gone) => break gone,
+            }
+        };
+        assert_eq!(gone, WriterGone::Draining);
+        assert!(!writer.is_finished());
+
+        drop(guard);
+        writer.join().unwrap();
+        assert_eq!(r.try_enter().err(), Some(WriterGone::Dropped));
+        assert_eq!(blocker.try_enter().err(), Some(WriterGone::Dropped));
+    }
+
+    #[test]
+    fn both_applications_report_the_assigned_id() {
+        use crate::ApplyWithId;
+
+        #[derive(Debug, PartialEq, Eq)]
+        enum Side {
+            First,
+            Second,
+        }
+        struct Add(i32);
+        impl ApplyWithId<i32, Vec<(u64, Side)>> for Add {
+            fn apply_first_with_id(
+                &mut self,
+                id: u64,
+                first: &mut i32,
+                _: &i32,
+                log: &mut Vec<(u64, Side)>,
+            ) {
+                *first += self.0;
+                log.push((id, Side::First));
+            }
+            fn apply_second_with_id(
+                self,
+                id: u64,
+                _: &i32,
+                second: &mut i32,
+                log: &mut Vec<(u64, Side)>,
+            ) {
+                *second += self.0;
+                log.push((id, Side::Second));
+            }
+        }
+
+        let mut w = crate::new_checked(0, vec![]);
+        let mut ids = vec![];
+        for batch in 0..4 {
+            for _ in 0..3 {
+                ids.push(w.append_with_id(Add(batch)));
+            }
+            w.publish();
+        }
+        w.publish();
+        assert_eq!(*w.enter().unwrap(), 18);
+
+        // ids increase with every append
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+        // and every id is applied once to each copy
+        let log = w.auxiliary();
+        for id in &ids {
+            let sides: Vec<_> = log
+                .iter()
+                .filter(|(i, _)| i == id)
+                .map(|(_, s)| s)
+                .collect();
+            assert_eq!(sides, [&Side::First, &Side::Second]);
+        }
+        assert_eq!(log.len(), 2 * ids.len());
+    }
+
+    #[test]
+    #[cfg(feature = "arc-swap-compat")]
+    fn arc_loader_hands_out_consistent_snapshots() {
+        use crate::{ArcGuard, ArcLoader};
+        use std::sync::Arc;
+
+        let mut w = crate::new::<CounterAddOp, _, _>(0, ());
+        let loader = ArcLoader::new(w.clone()).unwrap();
+        let first = loader.load();
+        assert_eq!(**first, 0);
+
+        w.append(CounterAddOp(1)).publish();
+        let second = loader.load_full();
+        assert_eq!(*second, 1);
+        // earlier snapshots are unaffected, and do not hold up the writer
+        w.append(CounterAddOp(1)).publish();
+        w.append(CounterAddOp(1)).publish();
+        assert_eq!(**first, 0);
+        assert_eq!(*second, 1);
+
+        // without a publish in between, loads share a single clone
+        let third = ArcGuard::into_inner(loader.load());
+        assert_eq!(*third, 3);
+        assert!(Arc::ptr_eq(&third, &loader.load_full()));
+
+        // and once the writer is gone, the last data is all there is
+        drop(w);
+        assert!(Arc::ptr_eq(&third, &loader.load_full()));
+        assert!(loader.into_inner().enter().is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "rkyv")]
+    fn archived_guards_read_archives_in_place() {
+        use crate::ReadGuard;
+        use rkyv::{AlignedVec, Archive, Serialize};
+
+        #[derive(Archive, Serialize)]
+        #[archive(check_bytes)]
+        struct Point {
+            x: u32,
+            tags: Vec<String>,
+        }
+
+        struct Replace(AlignedVec);
+        impl Apply<AlignedVec, ()> for Replace {
+            fn apply_first(&mut self, first: &mut AlignedVec, _: &AlignedVec, _: &mut ()) {
+                first.clone_from(&self.0);
+            }
+        }
+
+        let point = |x| Point {
+            x,
+            tags: vec!["a".to_string(), "b".to_string()],
+        };
+        let mut w = crate::new::<Replace, _, _>(AlignedVec::new(), ());
+        let r = w.clone();
+        w.append(Replace(rkyv::to_bytes::<_, 64>(&point(1)).unwrap()))
+            .publish();
+
+        let view = ReadGuard::archived::<Point>(r.enter().unwrap()).unwrap();
+        assert_eq!(view.x, 1);
+        assert_eq!(view.tags.len(), 2);
+        assert_eq!(view.tags[1], "b");
+        // the view pins the archive it points into, like any other guard
+        w.append(Replace(rkyv::to_bytes::<_, 64>(&point(2)).unwrap()))
+            .publish();
+        assert_eq!(view.x, 1);
+        drop(view);
+        // safety: the bytes were produced by rkyv for a Point
+        let view = unsafe { ReadGuard::archived_unchecked::<Point>(r.enter().unwrap()) };
+        assert_eq!(view.x, 2);
+        drop(view);
+
+        // truncated bytes are caught rather than read
+        let mut truncated = AlignedVec::new();
+        truncated.extend_from_slice(&rkyv::to_bytes::<_, 64>(&point(3)).unwrap()[1..]);
+        w.append(Replace(truncated)).publish();
+        assert!(ReadGuard::archived::<Point>(r.enter().unwrap()).is_err());
+    }
+
+    #[test]
+    fn bursts_of_publishes_without_reads_expose_every_publish() {
+        let mut w = crate::new_checked::<CounterAddOp, _, _>(0, ());
+        let r = w.clone();
+        for i in 1..=100 {
+            w.append(CounterAddOp(1)).publish();
+            // a reader that shows up at any point in the burst sees the latest publish
+            if i % 25 == 0 {
+                assert_eq!(*r.enter().unwrap(), i);
+            }
+        }
+        assert_eq!(w.refreshes, 100);
+        assert_eq!(*r.enter().unwrap(), 100);
+        w.publish();
+        assert_eq!(*r.enter().unwrap(), 100);
+    }
+
+    #[test]
+    fn apply_first_sees_what_readers_saw_before_the_publish() {
+        // records the copy it is handed as `second`
+        struct Observe(u32);
+        impl Apply<Vec<u32>, Vec<Vec<u32>>> for Observe {
+            fn apply_first(
+                &mut self,
+                first: &mut Vec<u32>,
+                second: &Vec<u32>,
+                seen: &mut Vec<Vec<u32>>,
+            ) {
+                seen.push(second.clone());
+                first.push(self.0);
+            }
+            fn apply_second(self, _: &Vec<u32>, second: &mut Vec<u32>, _: &mut Vec<Vec<u32>>) {
+                second.push(self.0);
+            }
+        }
+
+        let mut w = crate::new::<Observe, _, _>(vec![], vec![]);
+        let r = w.clone();
+        for batch in 0..5 {
+            let before = r.enter().unwrap().clone();
+            w.append(Observe(batch * 2));
+            if batch % 2 == 0 {
+                w.current();
+            }
+            w.append(Observe(batch * 2 + 1));
+            w.publish();
+            let seen = std::mem::take(w.auxiliary_mut());
+            assert_eq!(seen, [before.clone(), before]);
+        }
+    }
+
+    #[test]
+    fn copy_forward_ops_stay_in_sync() {
+        use crate::{ApplyWithPrev, WithPrev};
+
+        // copies the value readers last saw at `from` into `to`
+        struct CopyForward {
+            from: usize,
+            to: usize,
+        }
+        impl ApplyWithPrev<Vec<u64>, ()> for CopyForward {
+            type Computed = u64;
+            fn apply_first_with_prev(
+                &mut self,
+                first: &mut Vec<u64>,
+                prev_read: &Vec<u64>,
+                _: &mut (),
+            ) -> u64 {
+                first[self.to] = prev_read[self.from];
+                prev_read[self.from]
+            }
+            fn apply_second_with_computed(
+                self,
+                value: u64,
+                _: &Vec<u64>,
+                second: &mut Vec<u64>,
+                _: &mut (),
+            ) {
+                second[self.to] = value;
+            }
+        }
+        struct Set(usize, u64);
+        impl ApplyWithPrev<Vec<u64>, ()> for Set {
+            type Computed = ();
+            fn apply_first_with_prev(&mut self, first: &mut Vec<u64>, _: &Vec<u64>, _: &mut ()) {
+                first[self.0] = self.1;
+            }
+            fn apply_second_with_computed(
+                self,
+                _: (),
+                _: &Vec<u64>,
+                second: &mut Vec<u64>,
+                _: &mut (),
+            ) {
+                second[self.0] = self.1;
+            }
+        }
+        enum Op {
+            Copy(WithPrev<CopyForward, u64>),
+            Set(WithPrev<Set, ()>),
+        }
+        impl Apply<Vec<u64>, ()> for Op {
+            fn apply_first(&mut self, first: &mut Vec<u64>, second: &Vec<u64>, aux: &mut ()) {
+                match self {
+                    Op::Copy(op) => op.apply_first(first, second, aux),
+                    Op::Set(op) => op.apply_first(first, second, aux),
+                }
+            }
+            fn apply_second(self, first: &Vec<u64>, second: &mut Vec<u64>, aux: &mut ()) {
+                match self {
+                    Op::Copy(op) => op.apply_second(first, second, aux),
+                    Op::Set(op) => op.apply_second(first, second, aux),
+                }
+            }
+        }
+
+        let mut w = crate::new_checked::<Op, _, _>(vec![0; 4], ());
+        let r = w.clone();
+        for round in 1..=10 {
+            // slot 0 changes every round, and is copied forward along the other slots
+            w.append(Op::Set(WithPrev::new(Set(0, round))));
+            for to in 1..4 {
+                w.append(Op::Copy(WithPrev::new(CopyForward { from: to - 1, to })));
+            }
+            w.publish();
+        }
+        w.publish();
+        // every slot lags one round behind the one before it
+        assert_eq!(*r.enter().unwrap(), [10, 9, 8, 7]);
+    }
+
+    #[test]
+    fn restoring_mid_publish_state_matches_the_original() {
+        #[derive(Clone)]
+        struct Push(u32);
+        impl Apply<Vec<u32>, ()> for Push {
+            fn apply_first(&mut self, first: &mut Vec<u32>, _: &Vec<u32>, _: &mut ()) {
+                first.push(self.0);
+            }
+        }
+
+        let mut original = crate::new_checked::<Push, _, _>(vec![], ());
+        let r = original.clone();
+        original.extend((0..5).map(Push));
+        original.publish();
+        // the write copy runs ahead of the read copy by three operations, and two more are
+        // appended behind them
+        let unpublished: Vec<_> = (5..10).map(Push).collect();
+        original.extend(unpublished[..3].iter().cloned());
+        original.current();
+        original.extend(unpublished[3..].iter().cloned());
+
+        let read_copy = r.enter().unwrap().clone();
+        let mut restored = crate::restore_at_frontiers(read_copy, unpublished, 3, ())
+            .check_copies_with(|first, second| first == second);
+        let rr = restored.clone();
+
+        assert_eq!(*rr.enter().unwrap(), *r.enter().unwrap());
+        assert_eq!(restored.swap_index, original.swap_index);
+        assert_eq!(restored.applied, original.applied);
+        assert_eq!(restored.oplog.len(), original.oplog.len());
+        assert_eq!(
+            restored.has_pending_operations(),
+            original.has_pending_operations()
+        );
+        assert_eq!(*restored.current(), *original.current());
+
+        for w in [&mut original, &mut restored] {
+            w.publish();
+            w.append(Push(10)).publish();
+        }
+        assert_eq!(*rr.enter().unwrap(), *r.enter().unwrap());
+        assert_eq!(*rr.enter().unwrap(), (0..=10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn unsync_publishes_skip_the_reader_bookkeeping() {
+        use crate::UnsyncWriteHandle;
+
+        let w = crate::new::<CounterAddOp, _, _>(0, ());
+        let epochs = std::sync::Arc::clone(&w.epochs);
+        let published = std::sync::Arc::clone(&w.r_handle.published);
+        let mut w = UnsyncWriteHandle::new(w);
+
+        // a publish that tried to lock the epochs, let alone wait on them, would deadlock. so
+        // would one that updated the generation readers wait on.
+        let lock = crate::lock_epochs(&epochs);
+        let state = published.lock();
+        for frame in 1..=1000 {
+            assert_eq!(*w.enter(), frame - 1);
+            w.append(CounterAddOp(1));
+            assert_eq!(*w.enter(), frame - 1);
+            w.publish();
+            assert_eq!(*w.enter(), frame);
+        }
+        assert_eq!(state.generation, 0);
+        drop(state);
+        drop(lock);
+
+        w.append(CounterAddOp(1));
+        assert_eq!(*w.take(), 1001);
+    }
+
+    #[test]
+    fn lifo_with_supersession_matches_fifo() {
+        use crate::ApplyOrder;
+        use std::collections::BTreeMap;
+
+        type Map = BTreeMap<u32, u64>;
+        // last-writer-wins assignments, with the auxiliary counting applications
+        struct Set(u32, u64);
+        impl Apply<Map, usize> for Set {
+            fn apply_first(&mut self, first: &mut Map, _: &Map, applied: &mut usize) {
+                first.insert(self.0, self.1);
+                *applied += 1;
+            }
+            fn conflicts_with(&self, other: &Self) -> bool {
+                self.0 == other.0
+            }
+            fn supersedes(&self, earlier: &Self) -> bool {
+                self.0 == earlier.0
+            }
+        }
+
+        let mut fifo = crate::new_checked::<Set, _, _>(Map::new(), 0);
+        let mut lifo = crate::new_checked::<Set, _, _>(Map::new(), 0);
+        lifo.set_apply_order(ApplyOrder::Lifo);
+        for batch in 0..10u64 {
+            for w in [&mut fifo, &mut lifo] {
+                w.extend(
+                    (0..100).map(|i| Set((i * 7 + batch as u32) % 13, batch * 100 + i as u64)),
+                );
+                if batch % 3 == 0 {
+                    // apply part of the batch to the write copy ahead of the publish
+                    w.current();
+                    w.extend((0..20).map(|i| Set(i % 5, batch)));
+                }
+                w.publish();
+            }
+            assert_eq!(*fifo.enter().unwrap(), *lifo.enter().unwrap());
+        }
+        // one more publish brings the other copies up to date, and checks them
+        fifo.publish();
+        lifo.publish();
+        assert!(*lifo.auxiliary() < *fifo.auxiliary() / 4);
+        assert_eq!(fifo.take(), lifo.take());
+    }
+
+    #[test]
+    fn expired_ops_are_skipped_on_both_copies() {
+        use crate::Expiring;
+        use std::time::{Duration, Instant};
+
+        let mut w = crate::new_checked::<Expiring<CounterAddOp>, _, _>(0, ());
+        let r = w.clone();
+        let stale = Expiring::new(CounterAddOp(1), Instant::now());
+        let fresh = Expiring::within(CounterAddOp(2), Duration::from_secs(3600));
+        w.append(stale).append(fresh).publish();
+        assert_eq!(*r.enter().unwrap(), 2);
+
+        // the next publish brings the other copy up to date at a later time, and checks that it
+        // skipped the same operation
+        w.append(Expiring::within(CounterAddOp(4), Duration::from_secs(3600)));
+        w.publish();
+        assert_eq!(*r.enter().unwrap(), 6);
+        assert_eq!(*w.take(), 6);
+    }
+
+    #[test]
+    fn expiring_ops_keep_the_hooks_of_the_inner_op() {
+        use crate::{Expiring, Reclaim};
+        use std::time::{Duration, Instant};
+
+        #[derive(Clone, Debug, Default, PartialEq)]
+        struct Totalled {
+            values: Vec<i32>,
+            total: i32,
+        }
+
+        #[derive(Default)]
+        struct Counts {
+            batches: usize,
+            finalized: usize,
+        }
+
+        struct Push(i32);
+        impl Apply<Totalled, Counts> for Push {
+            fn apply_first(&mut self, first: &mut Totalled, _: &Totalled, _: &mut Counts) {
+                first.values.push(self.0);
+            }
+
+            fn is_homogeneous_with(&self, _: &Self) -> bool {
+                true
+            }
+
+            fn apply_first_homogeneous(
+                ops: &mut [Self],
+                first: &mut Totalled,
+                _: &Totalled,
+                counts: &mut Counts,
+                _: &mut Reclaim,
+            ) {
+                first.values.extend(ops.iter().map(|op| op.0));
+                counts.batches += 1;
+            }
+
+            fn finalize_first(first: &mut Totalled, _: &Totalled, counts: &mut Counts) {
+                first.total = first.values.iter().sum();
+                counts.finalized += 1;
+            }
+        }
+
+        let within = |v| Expiring::within(Push(v), Duration::from_secs(3600));
+        let mut w =
+            crate::new_checked::<Expiring<Push>, _, _>(Totalled::default(), Counts::default());
+        w.append(within(1))
+            .append(Expiring::new(Push(2), Instant::now()))
+            .append(within(3))
+            .publish();
+        {
+            let data = w.enter().unwrap();
+            assert_eq!(data.values, [1, 3]);
+            // the first copy was finalized after the batch
+            assert_eq!(data.total, 4);
+        }
+        // the three operations went to the inner op as a single run
+        assert_eq!(w.auxiliary.batches, 1);
+        assert_eq!(w.auxiliary.finalized, 1);
+
+        // the second copy skips the same operation, and is checked against the first
+        w.append(within(4)).publish();
+        assert_eq!(w.enter().unwrap().total, 8);
+        assert_eq!(w.take().values, [1, 3, 4]);
+    }
+
+    #[test]
+    fn guards_can_be_read_from_scoped_threads() {
+        use std::sync::atomic::AtomicBool;
+        use std::thread;
+
+        let mut w = crate::new::<CounterAddOp, _, _>(0, ());
+        let r = w.clone();
+        w.append(CounterAddOp(21)).publish().handoff();
+
+        let published = AtomicBool::new(false);
+        thread::scope(|outer| {
+            let guard = r.enter().unwrap();
+            let data = &*guard;
+            // the writer cannot publish over the copy while the workers read it
+            let writer = outer.spawn(|| {
+                w.append(CounterAddOp(1)).publish();
+                w.append(CounterAddOp(1)).publish();
+                published.store(true, Ordering::SeqCst);
+            });
+            let sum: i32 = thread::scope(|scope| {
+                let workers: Vec<_> = (0..4)
+                    .map(|_| {
+                        scope.spawn(|| {
+                            thread::sleep(std::time::Duration::from_millis(20));
+                            assert!(!published.load(Ordering::SeqCst));
+                            *data
+                        })
+                    })
+                    .collect();
+                workers.into_iter().map(|w| w.join().unwrap()).sum()
+            });
+            assert_eq!(sum, 84);
+            drop(guard);
+            writer.join().unwrap();
+        });
+        assert!(published.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn send_guard_crosses_threads() {
+        let mut w = crate::new::<CounterAddOp, _, _>(0, ());
+        let mut r = w.clone();
+        w.append(CounterAddOp(1));
+        w.publish();
+
+        let guard = r.enter_send().unwrap();
+        let epoch = w
+            .epochs
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(_, e)| e.load(Ordering::Acquire))
+            .max();
+        assert_eq!(epoch.map(|e| e % 2), Some(1));
+        let seen = std::thread::scope(|s| s.spawn(move || *guard).join().unwrap());
+        assert_eq!(seen, 1);
+
+        // the guard released the epoch from the other thread
+        assert!(w
+            .epochs
+            .lock()
+            .unwrap()
+            .iter()
+            .all(|(_, e)| e.load(Ordering::Acquire) % 2 == 0));
+        w.append(CounterAddOp(1));
+        w.publish();
+        assert_eq!(*r.enter().unwrap(), 2);
+    }
+
+    #[test]
+    fn batch_pins_epoch_once() {
+        struct Push(u32);
+        impl Apply<Vec<u32>, ()> for Push {
+            fn apply_first(&mut self, first: &mut Vec<u32>, _: &Vec<u32>, _: &mut ()) {
+                first.push(self.0);
+            }
+        }
+
+        let mut w = crate::new::<Push, _, _>(vec![], ());
+        let r = w.clone();
+        w.extend((0..1000).map(Push));
+        w.publish();
+
+        // every epoch bump is one atomic write by a reader
+        let epochs = w.epochs.clone();
+        let epoch_writes = || -> usize {
+            crate::lock_epochs(&epochs)
+                .iter()
+                .map(|(_, epoch)| epoch.load(Ordering::Acquire))
+                .sum()
+        };
+
+        let before = epoch_writes();
+        let mut sum = 0;
+        for i in 0..1000 {
+            sum += r.enter().unwrap()[i];
+        }
+        let unbatched = epoch_writes() - before;
+
+        let before = epoch_writes();
+        let mut batched_sum = 0;
+        let batch = r.enter_batch().unwrap();
+        for i in 0..1000 {
+            batched_sum += batch.read(|v| v[i]);
+        }
+        drop(batch);
+        let batched = epoch_writes() - before;
+
+        assert_eq!(sum, batched_sum);
+        assert_eq!(unbatched, 2000);
+        assert_eq!(batched, 2);
+    }
+
+    #[test]
+    fn owned_guard_holds_up_writer_from_another_thread() {
+        use crate::ReadGuard;
+        use std::sync::mpsc;
+
+        let mut w = crate::new::<CounterAddOp, _, _>(0, ());
+        let r = w.clone();
+        w.append(CounterAddOp(1));
+        w.publish();
+
+        let owned = ReadGuard::into_owned(r.enter().unwrap());
+        let (tx, rx) = mpsc::channel();
+        let (done_tx, done_rx) = mpsc::channel();
+        let reader = std::thread::spawn(move || {
+            let owned: crate::OwnedReadGuard<i32> = rx.recv().unwrap();
+            let seen = *owned;
+            // hold on to the guard until the main thread has tried to publish twice
+            done_rx.recv().unwrap();
+            assert_eq!(*owned, seen);
+            seen
+        });
+        tx.send(owned).unwrap();
+
+        // the handle the guard came from is free to enter again
+        assert_eq!(*r.enter().unwrap(), 1);
+
+        // the first publish moves readers off the pinned copy...
+        w.append(CounterAddOp(1));
+        w.publish();
+        assert_eq!(*r.enter().unwrap(), 2);
+
+        // ...and the second has to wait for the owned guard to go away before reusing it
+        let is_waiting = std::sync::Arc::clone(&w.is_waiting);
+        w.handoff();
+        std::thread::scope(|s| {
+            let publisher = s.spawn(|| {
+                w.append(CounterAddOp(1));
+                w.publish();
+            });
+            while !is_waiting.load(Ordering::Relaxed) && !publisher.is_finished() {
+                std::thread::yield_now();
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            assert!(!publisher.is_finished());
+            done_tx.send(()).unwrap();
+        });
+        assert_eq!(reader.join().unwrap(), 1);
+        assert_eq!(*r.enter().unwrap(), 3);
+    }
+
+    #[test]
+    fn backoff_waits_for_slow_reader() {
+        use crate::ExponentialBackoff;
+        use std::sync::mpsc;
+        use std::thread;
+        use std::time::{Duration, Instant};
+
+        let mut w = crate::new::<CounterAddOp, _, _>(0, ());
+        w.set_wait_strategy(ExponentialBackoff {
+            max_spin: 4,
+            max_sleep: Duration::from_millis(2),
+        });
+        let r = w.clone();
+        w.append(CounterAddOp(1));
+        w.publish();
+
+        let (tx, rx) = mpsc::channel();
+        let stall = Duration::from_millis(20);
+        let reader = thread::spawn(move || {
+            let guard = r.enter().unwrap();
+            tx.send(()).unwrap();
+            thread::sleep(stall);
+            *guard
+        });
+        rx.recv().unwrap();
+
+        // the reader holds the copy the second publish has to modify
+        let start = Instant::now();
+        w.append(CounterAddOp(1));
+        w.publish();
+        w.publish();
+        assert!(start.elapsed() >= stall);
+        assert_eq!(reader.join().unwrap(), 1);
+        assert_eq!(*w.enter().unwrap(), 2);
+    }
+
+    #[test]
+    fn strict_drop_flags_unpublished_ops() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        // published and flushed handles drop quietly
+        let mut w = crate::new_strict::<CounterAddOp, _, _>(0, ());
+        w.append(CounterAddOp(1)).publish();
+        drop(w);
+        let mut w = crate::new_strict::<CounterAddOp, _, _>(0, ());
+        w.append(CounterAddOp(1));
+        w.flush();
+        drop(w);
+
+        let mut w = crate::new_strict::<CounterAddOp, _, _>(0, ());
+        let r = w.clone();
+        w.append(CounterAddOp(1)).publish();
+        w.append(CounterAddOp(2));
+        assert!(catch_unwind(AssertUnwindSafe(move || drop(w))).is_err());
+        // the handle was still torn down
+        assert!(r.enter().is_none());
+
+        // take asks for the up-to-date data explicitly
+        let mut w = crate::new_strict::<CounterAddOp, _, _>(0, ());
+        w.append(CounterAddOp(3));
+        assert_eq!(*w.take(), 3);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn cross_thread_write_panics() {
+        use std::thread;
+
+        let mut w = crate::new::<CounterAddOp, _, _>(0, ());
+        w.append(CounterAddOp(1));
+        let res = thread::spawn(move || {
+            w.append(CounterAddOp(1));
+        })
+        .join();
+        assert!(res.is_err());
+
+        // publishing counts as a write too
+        let mut w = crate::new::<CounterAddOp, _, _>(0, ());
+        w.publish();
+        let res = thread::spawn(move || {
+            w.publish();
+        })
+        .join();
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn handoff_allows_cross_thread_write() {
+        use std::thread;
+
+        let mut w = crate::new::<CounterAddOp, _, _>(0, ());
+        w.append(CounterAddOp(1));
+        w.handoff();
+        let mut w = thread::spawn(move || {
+            w.append(CounterAddOp(1)).publish();
+            w.handoff();
+            w
+        })
+        .join()
+        .unwrap();
+        w.append(CounterAddOp(1)).publish();
+        assert_eq!(*w.enter().unwrap(), 3);
+
+        // dropping on another thread is not a write
+        thread::spawn(move || drop(w)).join().unwrap();
+    }
+
+    #[test]
+    fn checked_hash_accepts_deterministic_ops() {
+        let mut w = crate::new_checked_hash::<CounterAddOp, _, _>(0, ());
+        for i in 0..4 {
+            w.append(CounterAddOp(i));
+            w.publish();
+        }
+        assert_eq!(*w.take(), 6);
+    }
+
+    #[test]
+    #[should_panic(expected = "the two copies diverged")]
+    fn checked_hash_flags_broken_ops() {
+        struct Broken(i32);
+        impl Apply<i32, ()> for Broken {
+            fn apply_first(&mut self, first: &mut i32, _: &i32, _: &mut ()) {
+                *first += self.0;
+            }
+
+            fn apply_second(self, _: &i32, second: &mut i32, _: &mut ()) {
+                *second -= self.0;
+            }
+        }
+
+        let mut w = crate::new_checked_hash::<Broken, _, _>(0, ());
+        w.append(Broken(1));
+        w.publish();
+        // this publish replays the op onto the other copy, which then differs
+        w.publish();
+    }
+
+    #[test]
+    #[cfg(feature = "parallel-apply")]
+    fn partitioned_publishes_match_sequential_application() {
+        use crate::{ApplyPartitioned, Partitioned};
+        use std::collections::BTreeMap;
+
+        type Shard = BTreeMap<u64, u64>;
+        #[derive(Clone, Debug, PartialEq, Hash)]
+        struct Sharded(Vec<Shard>);
+        impl Partitioned for Sharded {
+            type Partition = Shard;
+            fn partitions(&self) -> Vec<&Self::Partition> {
+                self.0.iter().collect()
+            }
+            fn partitions_mut(&mut self) -> Vec<&mut Self::Partition> {
+                self.0.iter_mut().collect()
+            }
+        }
+
+        // adds to a key, so the order within a partition matters for `Double`
+        enum Op {
+            Add(u64, u64),
+            Double(u64),
+        }
+        impl Op {
+            fn key(&self) -> u64 {
+                match *self {
+                    Op::Add(k, _) | Op::Double(k) => k,
+                }
+            }
+
+            fn apply(&self, shard: &mut Shard) {
+                match *self {
+                    Op::Add(k, n) => *shard.entry(k).or_default() += n,
+                    Op::Double(k) => *shard.entry(k).or_default() *= 2,
+                }
+            }
+        }
+        impl Apply<Sharded, ()> for Op {
+            fn apply_first(&mut self, first: &mut Sharded, _: &Sharded, _: &mut ()) {
+                self.apply(&mut first.0[self.partition()]);
+            }
+        }
+        impl ApplyPartitioned<Sharded, ()> for Op {
+            fn partition(&self) -> usize {
+                (self.key() % 16) as usize
+            }
+            fn apply_first_partitioned(&mut self, first: &mut Shard, _: &Shard) {
+                self.apply(first);
+            }
+        }
+
+        let ops = |round: u64| {
+            (0..1000).map(move |i| {
+                if i % 7 == 0 {
+                    Op::Double(i % 50)
+                } else {
+                    Op::Add(i % 50, i + round)
+                }
+            })
+        };
+        let init = Sharded(vec![BTreeMap::new(); 16]);
+        let mut sequential = crate::new::<Op, _, _>(init.clone(), ());
+        let mut parallel = crate::new_partitioned::<Op, _, _>(init, ())
+            .check_copies_with(|first, second| first == second);
+        for round in 0..5 {
+            sequential.extend(ops(round));
+            sequential.publish();
+            parallel.extend(ops(round));
+            parallel.publish();
+            assert_eq!(*parallel.enter().unwrap(), *sequential.enter().unwrap());
+        }
+        parallel.publish();
+        assert_eq!(*parallel.enter().unwrap(), *sequential.enter().unwrap());
+    }
+
+    #[test]
+    fn diverged_copies_report_their_diff() {
+        use crate::DiffEntry;
+        use std::collections::BTreeMap;
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        type Map = BTreeMap<&'static str, i32>;
+        struct Insert(&'static str, i32);
+        impl Apply<Map, ()> for Insert {
+            fn apply_first(&mut self, first: &mut Map, _: &Map, _: &mut ()) {
+                first.insert(self.0, self.1);
+            }
+
+            // broken for a single key
+            fn apply_second(self, _: &Map, second: &mut Map, _: &mut ()) {
+                let v = if self.0 == "b" { self.1 + 1 } else { self.1 };
+                second.insert(self.0, v);
+            }
+
+            fn diff(first: &Map, second: &Map) -> Vec<DiffEntry> {
+                first
+                    .iter()
+                    .filter(|(k, v)| second.get(*k) != Some(*v))
+                    .map(|(k, v)| DiffEntry::new(k, Some(v), second.get(k)))
+                    .collect()
+            }
+        }
+
+        let mut w = crate::new_checked::<Insert, _, _>(BTreeMap::new(), ());
+        w.extend(vec![Insert("a", 1), Insert("b", 2), Insert("c", 3)]);
+        w.publish();
+        let panic = catch_unwind(AssertUnwindSafe(|| {
+            w.publish();
+        }))
+        .unwrap_err();
+        let message = panic.downcast_ref::<String>().unwrap();
+        assert!(message.starts_with("the two copies diverged"));
+        assert!(
+            message.ends_with("\n  at b: Some(2) != Some(3)"),
+            "{}",
+            message
+        );
+        drop(w);
+
+        // without a diff, the panic says as much
+        let mut w = crate::new_checked::<CounterAddOp, _, _>(0, ());
+        w.append(CounterAddOp(1)).publish();
+        w.publish();
+        assert_eq!(
+            crate::diff::describe(&[]),
+            "copies differ (implement Apply::diff for details)"
+        );
+    }
+
+    #[test]
+    fn publish_group_never_shows_derived_behind_primary() {
+        let mut primary = crate::new::<CounterAddOp, _, _>(0, ());
+        let mut derived = crate::new::<CounterAddOp, _, _>(0, ());
+        let rp = primary.clone();
+        let rd = derived.clone();
+
+        let done = std::sync::atomic::AtomicBool::new(false);
+        std::thread::scope(|s| {
+            let (rp, rd, done) = (rp.clone(), rd.clone(), &done);
+            s.spawn(move || {
+                while !done.load(Ordering::Acquire) {
+                    let p = *rp.enter().unwrap();
+                    let d = *rd.enter().unwrap();
+                    assert!(d >= 2 * p, "derived {} is behind primary {}", d, p);
+                }
+            });
+
+            for _ in 0..1000 {
+                primary.append(CounterAddOp(1));
+                derived.append(CounterAddOp(2));
+                super::PublishGroup::new(&mut primary)
+                    .add(&mut derived)
+                    .publish_all();
+            }
+            done.store(true, Ordering::Release);
+        });
+
+        assert_eq!(*rp.enter().unwrap(), 1000);
+        assert_eq!(*rd.enter().unwrap(), 2000);
+        assert_eq!(primary.refreshes, 1000);
+        assert_eq!(derived.refreshes, 1000);
+    }
+
+    #[test]
+    fn batch_trend_flags_sustained_growth() {
+        let mut w = crate::new::<CounterAddOp, _, _>(0, ());
+
+        // a writer that keeps up publishes the same number of ops every time
+        for _ in 0..10 {
+            w.extend((0..8).map(CounterAddOp));
+            w.publish();
+            assert!(!w.batch_trend().sustained(2));
+        }
+        assert_eq!(w.batch_trend().size(), 8);
+
+        // a writer that falls behind publishes ever larger batches
+        for i in 1..=5 {
+            w.extend((0..8 + i).map(CounterAddOp));
+            w.publish();
+            assert_eq!(w.batch_trend().growing_for(), i as usize);
+        }
+        assert!(w.batch_trend().sustained(5));
+        assert!(!w.batch_trend().sustained(6));
+
+        // and catching up resets the trend
+        w.publish();
+        assert_eq!(w.batch_trend().size(), 0);
+        assert_eq!(w.batch_trend().growing_for(), 0);
+    }
+
+    #[test]
+    fn flush_noblock() {
+        let mut w = crate::new::<CounterAddOp, _, _>(0, ());
+        let r = w.clone();
+        w.append(CounterAddOp(42));
+        w.publish();
+        assert_eq!(*r.enter().unwrap(), 42);
+
+        // pin the epoch
+        let _count = r.enter();
+        // refresh would hang here
+        assert_eq!(w.oplog.iter().skip(w.swap_index).count(), 0);
+        assert!(!w.has_pending_operations());
+    }
+
+    #[test]
+    fn panicking_apply_poisons_the_writer_but_not_the_handles() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        // adds to the data, and panics instead the first time it is armed
+        struct Add(i32);
+        impl Apply<i32, bool> for Add {
+            fn apply_first(&mut self, first: &mut i32, _: &i32, armed: &mut bool) {
+                if self.0 < 0 && std::mem::take(armed) {
+                    panic!("boom");
+                }
+                *first += self.0;
+            }
+        }
+
+        let mut w = crate::new::<Add, _, _>(0, true);
+        let r = w.clone();
+        w.append(Add(1)).append(Add(-1)).append(Add(1));
+        // the publish panics while it holds the epoch lock, after the first op was applied
+        let publish = panic::catch_unwind(AssertUnwindSafe(|| {
+            w.publish();
+        }));
+        assert!(publish.is_err());
+
+        // applying the batch again would add the first op twice to one of the copies
+        let publish = panic::catch_unwind(AssertUnwindSafe(|| {
+            w.publish();
+        }));
+        assert!(publish.is_err());
+        assert_eq!(*r.enter().unwrap(), 0);
+
+        // handles can still be created and dropped, and so can the writer
+        let r2 = r.clone();
+        drop(r2);
+        drop(w);
+        assert!(r.enter().is_none());
+    }
+
+    #[test]
+    fn flush_no_refresh() {
+        let mut w = crate::new::<CounterAddOp, _, _>(0, ());
+
+        // Until we refresh, writes are written directly instead of going to the
+        // oplog (because there can't be any readers on the w_handle table).
+        assert!(!w.has_pending_operations());
+        w.publish();
+        assert!(!w.has_pending_operations());
+        assert_eq!(w.refreshes, 1);
+
+        w.append(CounterAddOp(42));
+        assert!(w.has_pending_operations());
+        w.publish();
+        assert!(!w.has_pending_operations());
+        assert_eq!(w.refreshes, 2);
+
+        w.append(CounterAddOp(42));
+        assert!(w.has_pending_operations());
+        w.publish();
+        assert!(!w.has_pending_operations());
+        assert_eq!(w.refreshes, 3);
+
+        // Sanity check that a refresh would have been visible
+        assert!(!w.has_pending_operations());
+        w.publish();
+        assert_eq!(w.refreshes, 4);
+    }
+
+    #[test]
+    fn fresh_reads_see_what_current_applied() {
+        use std::thread;
+
+        // fresh reads are opt-in
+        let plain = crate::new::<CounterAddOp, _, _>(0, ());
+        assert!(plain.enter_fresh().is_none());
+
+        let mut w = crate::new_with_fresh_reads::<CounterAddOp, _, _>(0, ());
+        let r = w.clone();
+        let other = w.factory().handle();
+        assert_eq!(*r.enter_fresh().unwrap(), 0);
+
+        // appending alone applies nothing, so neither mode sees it
+        w.append(CounterAddOp(1));
+        assert_eq!(*r.enter().unwrap(), 0);
+        assert_eq!(*r.enter_fresh().unwrap(), 0);
+
+        // once `current` has applied it, only fresh reads see it
+        w.current();
+        assert_eq!(*r.enter().unwrap(), 0);
+        assert_eq!(*r.enter_fresh().unwrap(), 1);
+        assert_eq!(*other.enter_fresh().unwrap(), 1);
+
+        // after a publish, both modes agree, also across several publishes
+        w.publish();
+        assert_eq!(*r.enter().unwrap(), 1);
+        assert_eq!(*r.enter_fresh().unwrap(), 1);
+        w.append(CounterAddOp(2)).publish();
+        w.append(CounterAddOp(3)).publish();
+        assert_eq!(*r.enter().unwrap(), 6);
+        assert_eq!(*r.enter_fresh().unwrap(), 6);
+
+        // a fresh guard keeps the writer from modifying the copy it refers to
+        w.append(CounterAddOp(4));
+        w.current();
+        let fresh = r.enter_fresh().unwrap();
+        assert_eq!(*fresh, 10);
+        w.handoff();
+        let writer = thread::spawn(move || {
+            w.append(CounterAddOp(5)).publish();
+            w
+        });
+        thread::sleep(std::time::Duration::from_millis(50));
+        assert!(!writer.is_finished());
+        assert_eq!(*fresh, 10);
+        drop(fresh);
+        let w = writer.join().unwrap();
+        assert_eq!(*r.enter().unwrap(), 15);
+        assert_eq!(*r.enter_fresh().unwrap(), 15);
+
+        drop(w);
+        assert!(r.enter_fresh().is_none());
+        assert!(other.enter_fresh().is_none());
+    }
+
+    #[test]
+    fn fresh_guards_do_not_block_polled_publishes() {
+        use std::sync::atomic::AtomicUsize;
+        use std::sync::Arc;
+        use std::task::{Context, Poll, Wake};
+
+        struct CountWakes(AtomicUsize);
+        impl Wake for CountWakes {
+            fn wake(self: Arc<Self>) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+        let wakes = Arc::new(CountWakes(AtomicUsize::new(0)));
+        let waker = Arc::clone(&wakes).into();
+        let mut cx = Context::from_waker(&waker);
+
+        let mut w = crate::new_with_fresh_reads::<CounterAddOp, _, _>(0, ());
+        let r = w.clone();
+        w.append(CounterAddOp(1));
+
+        // a fresh guard holds up the publish, but polling it does not block
+        let fresh = r.enter_fresh().unwrap();
+        assert_eq!(w.poll_publish(&mut cx), Poll::Pending);
+        assert_eq!(*fresh, 0);
+        // and letting go of the guard wakes the writer
+        drop(fresh);
+        assert_eq!(wakes.0.load(Ordering::SeqCst), 1);
+        assert_eq!(w.poll_publish(&mut cx), Poll::Ready(()));
+        assert_eq!(*r.enter_fresh().unwrap(), 1);
+
+        // the same goes for a publish that accepts appends while it waits
+        w.append(CounterAddOp(2));
+        let fresh = r.enter_fresh().unwrap();
+        let mut publish = w.begin_publish();
+        assert!(!publish.try_complete());
+        drop(fresh);
+        assert!(publish.try_complete());
+        drop(publish);
+        assert_eq!(*r.enter().unwrap(), 3);
     }
 }
@@ -0,0 +1,64 @@
+/// How the size of published batches changed over recent publishes.
+///
+/// Every call to [`WriteHandle::publish`](crate::WriteHandle::publish) records the number of
+/// operations it publishes. This is not how stale readers are, but if the number keeps growing
+/// from one publish to the next, the writer is appending faster than it publishes (or its
+/// publishes are held up by slow readers), and the oplog will eventually grow without bound.
+/// `BatchTrend` makes that visible early.
+///
+/// # Examples
+///
+/// ```
+/// use reft_light::Apply;
+///
+/// struct Add(u64);
+/// impl Apply<u64, ()> for Add {
+///     fn apply_first(&mut self, first: &mut u64, _: &u64, _: &mut ()) {
+///         *first += self.0;
+///     }
+/// }
+///
+/// let mut w = reft_light::new::<Add, _, _>(0, ());
+/// for batch in 1..=4 {
+///     w.extend((0..batch).map(Add));
+///     w.publish();
+/// }
+/// assert_eq!(w.batch_trend().size(), 4);
+/// assert!(w.batch_trend().sustained(3));
+///
+/// // a smaller batch breaks the streak
+/// w.append(Add(1)).publish();
+/// assert!(!w.batch_trend().sustained(1));
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BatchTrend {
+    size: usize,
+    growing_for: usize,
+}
+
+impl BatchTrend {
+    pub(crate) fn record(&mut self, size: usize) {
+        if size > self.size {
+            self.growing_for += 1;
+        } else {
+            self.growing_for = 0;
+        }
+        self.size = size;
+    }
+
+    /// Returns the number of operations published by the most recent publish.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the number of consecutive publishes, up to and including the most recent one, that
+    /// each published more operations than the one before.
+    pub fn growing_for(&self) -> usize {
+        self.growing_for
+    }
+
+    /// Returns true if the batch size has grown for at least `publishes` consecutive publishes.
+    pub fn sustained(&self, publishes: usize) -> bool {
+        self.growing_for >= publishes
+    }
+}
@@ -0,0 +1,123 @@
+use crate::sync::Arc;
+use crate::{Apply, OplogStore, WriteHandle};
+use std::fmt;
+
+/// The halves of [`WriteHandle::publish`] that [`PublishGroup`] interleaves across handles with
+/// different operation and data types.
+trait Member {
+    fn apply(&mut self);
+    fn flip(&mut self);
+}
+
+impl<O, T, A, S> Member for WriteHandle<O, T, A, S>
+where
+    O: Apply<T, A>,
+    S: OplogStore<O>,
+{
+    fn apply(&mut self) {
+        self.assert_owner();
+        let epochs = Arc::clone(&self.epochs);
+        let mut epochs = crate::lock_epochs(&epochs);
+        self.wait(&mut epochs);
+        let fresh = self.fresh.clone();
+        let _fresh = fresh.as_ref().map(crate::read::lock_fresh);
+        self.apply_oplog();
+    }
+
+    fn flip(&mut self) {
+        let epochs = Arc::clone(&self.epochs);
+        let mut epochs = crate::lock_epochs(&epochs);
+        let fresh = self.fresh.clone();
+        let mut fresh = fresh.as_ref().map(crate::read::lock_fresh);
+        WriteHandle::flip(self, Some(&mut epochs), fresh.as_deref_mut());
+    }
+}
+
+/// Publishes a primary left-right together with left-rights derived from it.
+///
+/// Publishing the handles one after the other with [`WriteHandle::publish`] lets a reader observe
+/// the primary's new state alongside a derived handle's old state. A `PublishGroup` instead first
+/// brings the write copies of all of its handles up to date, and only then swaps them in, in the
+/// reverse of the order they were added. The primary, which is added first, is thus swapped in
+/// last.
+///
+/// This gives readers the following guarantee: a reader that enters the primary and *then* enters
+/// a derived handle never sees a derived state older than the primary state it saw. The reverse
+/// does not hold; a reader that enters a derived handle first may still see the primary move ahead
+/// of it. As with [`WriteHandle::publish`], waiting for readers to depart from the old copies
+/// happens at the start of the next publish of each handle.
+///
+/// # Examples
+///
+/// ```
+/// use reft_light::{Apply, PublishGroup};
+///
+/// struct Set(u64);
+/// impl Apply<u64, ()> for Set {
+///     fn apply_first(&mut self, first: &mut u64, _: &u64, _: &mut ()) {
+///         *first = self.0;
+///     }
+/// }
+///
+/// let mut primary = reft_light::new::<Set, _, _>(0, ());
+/// let mut doubled = reft_light::new::<Set, _, _>(0, ());
+///
+/// primary.append(Set(21));
+/// doubled.append(Set(42));
+/// PublishGroup::new(&mut primary).add(&mut doubled).publish_all();
+///
+/// assert_eq!(*primary.enter().unwrap(), 21);
+/// assert_eq!(*doubled.enter().unwrap(), 42);
+/// ```
+pub struct PublishGroup<'a> {
+    members: Vec<&'a mut dyn Member>,
+}
+
+impl<'a> PublishGroup<'a> {
+    /// Start a group with the given primary handle.
+    pub fn new<O, T, A, S>(primary: &'a mut WriteHandle<O, T, A, S>) -> Self
+    where
+        O: Apply<T, A> + 'a,
+        S: OplogStore<O> + 'a,
+        T: 'a,
+        A: 'a,
+    {
+        PublishGroup {
+            members: vec![primary],
+        }
+    }
+
+    /// Add a handle derived from the handles already in the group.
+    pub fn add<O, T, A, S>(&mut self, derived: &'a mut WriteHandle<O, T, A, S>) -> &mut Self
+    where
+        O: Apply<T, A> + 'a,
+        S: OplogStore<O> + 'a,
+        T: 'a,
+        A: 'a,
+    {
+        self.members.push(derived);
+        self
+    }
+
+    /// Publish every handle in the group.
+    ///
+    /// Like [`WriteHandle::publish`], this always swaps the copies of every handle, even of those
+    /// without pending operations.
+    pub fn publish_all(&mut self) -> &mut Self {
+        for member in self.members.iter_mut() {
+            member.apply();
+        }
+        for member in self.members.iter_mut().rev() {
+            member.flip();
+        }
+        self
+    }
+}
+
+impl fmt::Debug for PublishGroup<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PublishGroup")
+            .field("members", &self.members.len())
+            .finish()
+    }
+}
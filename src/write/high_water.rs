@@ -0,0 +1,39 @@
+/// The largest values a [`WriteHandle`](crate::WriteHandle) has observed since it was created, or
+/// since the last call to [`reset_high_water_marks`](crate::WriteHandle::reset_high_water_marks).
+///
+/// Useful for capacity planning, for example to decide how much to
+/// [`reserve`](std::collections::VecDeque::reserve) up front, or how many reader slots to expect.
+///
+/// Only available with the `metrics` feature.
+///
+/// # Examples
+///
+/// ```
+/// use reft_light::Apply;
+///
+/// struct Add(u64);
+/// impl Apply<u64, ()> for Add {
+///     fn apply_first(&mut self, first: &mut u64, _: &u64, _: &mut ()) {
+///         *first += self.0;
+///     }
+/// }
+///
+/// let mut w = reft_light::new::<Add, _, _>(0, ());
+/// let readers: Vec<_> = (0..3).map(|_| w.clone()).collect();
+/// w.extend((0..100).map(Add));
+/// w.publish();
+///
+/// let marks = w.high_water_marks();
+/// assert_eq!(marks.oplog_len, 100);
+/// // the write handle has a reader of its own
+/// assert_eq!(marks.readers, 4);
+/// # drop(readers);
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct HighWaterMarks {
+    /// The largest number of operations the oplog has held at once, including operations that
+    /// were already published but not yet applied to the second copy.
+    pub oplog_len: usize,
+    /// The largest number of registered readers a publish has had to wait for.
+    pub readers: usize,
+}
@@ -0,0 +1,228 @@
+use crate::{Apply, WriteHandle};
+use std::fmt;
+
+/// A writer handle to a left-right whose data is only ever read through the writer itself.
+///
+/// Produced by [`new_local`](crate::new_local). A regular [`WriteHandle`] can hand out
+/// [`ReadHandle`](crate::ReadHandle)s, which may live on other threads and read the data while
+/// the writer modifies the other copy. That is why a `WriteHandle` can only be sent to another
+/// thread if `T` is `Sync`. A `LocalWriteHandle` never hands out a `ReadHandle`, so all reads
+/// happen through [`enter`](Self::enter) on whichever thread currently owns the handle. It
+/// therefore only needs `T: Send` to be sent to another thread, which makes it usable with data
+/// that uses non-thread-safe interior mutability, like `Cell` or `RefCell`.
+///
+/// What remains of left-right is the double buffering: operations can be appended while a
+/// snapshot of the data is being read, and only become visible once [`publish`](Self::publish) is
+/// called. Since nobody else can be reading the stale copy, publishing never waits.
+///
+/// # Examples
+///
+/// ```
+/// use reft_light::Apply;
+/// use std::cell::Cell;
+///
+/// // a cache that can only be shared within a single thread
+/// #[derive(Clone, Default)]
+/// struct Counted {
+///     value: u64,
+///     reads: Cell<usize>,
+/// }
+///
+/// struct Add(u64);
+/// impl Apply<Counted, ()> for Add {
+///     fn apply_first(&mut self, first: &mut Counted, _: &Counted, _: &mut ()) {
+///         first.value += self.0;
+///     }
+/// }
+///
+/// let mut w = reft_light::new_local::<Add, _, _>(Counted::default(), ());
+/// w.append(Add(1)).publish();
+///
+/// let worker = std::thread::spawn(move || {
+///     let snapshot = w.enter();
+///     snapshot.reads.set(snapshot.reads.get() + 1);
+///     snapshot.value
+/// });
+/// assert_eq!(worker.join().unwrap(), 1);
+/// ```
+pub struct LocalWriteHandle<O, T, A>
+where
+    O: Apply<T, A>,
+{
+    inner: WriteHandle<O, T, A>,
+}
+
+// safety: the only ReadHandle to the data is the one inside `inner`, which moves along with us,
+// and is only ever used through &self or &mut self. all accesses to either copy therefore happen
+// on the thread that currently owns us, so it is enough that T can be moved between threads.
+unsafe impl<O, T, A> Send for LocalWriteHandle<O, T, A>
+where
+    O: Apply<T, A>,
+    T: Send,
+    O: Send,
+    A: Send,
+{
+}
+
+impl<O, T, A> LocalWriteHandle<O, T, A>
+where
+    O: Apply<T, A>,
+{
+    pub(crate) fn new(inner: WriteHandle<O, T, A>) -> Self {
+        Self { inner }
+    }
+
+    /// Returns a reference to the published copy of the `T`.
+    ///
+    /// The reference borrows the handle, so no operations can be published while it lives. This
+    /// is a plain reference rather than a [`ReadGuard`](crate::ReadGuard), since a guard could be
+    /// turned into an [`OwnedReadGuard`](crate::OwnedReadGuard) that stays behind on this thread
+    /// while the handle moves on to another.
+    pub fn enter(&self) -> &T {
+        self.inner.read_copy()
+    }
+
+    /// Append the given operation to the operational log.
+    ///
+    /// See [`WriteHandle::append`].
+    pub fn append(&mut self, op: O) -> &mut Self {
+        self.inner.append(op);
+        self
+    }
+
+    /// Publish all operations appended to the log.
+    ///
+    /// See [`WriteHandle::publish`]. Since there are no other readers, this never has to wait.
+    pub fn publish(&mut self) -> &mut Self {
+        self.inner.publish();
+        self
+    }
+
+    /// Publish if there are pending operations.
+    ///
+    /// See [`WriteHandle::flush`].
+    pub fn flush(&mut self) {
+        self.inner.flush();
+    }
+
+    /// Returns true if there are operations in the operational log that have not yet been
+    /// published.
+    pub fn has_pending_operations(&self) -> bool {
+        self.inner.has_pending_operations()
+    }
+
+    /// Hand this handle off to be used from another thread.
+    ///
+    /// See [`WriteHandle::handoff`].
+    pub fn handoff(&mut self) -> &mut Self {
+        self.inner.handoff();
+        self
+    }
+
+    /// Returns a reference to the auxiliary data.
+    pub fn auxiliary(&self) -> &A {
+        self.inner.auxiliary()
+    }
+
+    /// Returns a mutable reference to the auxiliary data.
+    pub fn auxiliary_mut(&mut self) -> &mut A {
+        self.inner.auxiliary_mut()
+    }
+
+    /// Returns the backing data structure, with all pending operations applied.
+    pub fn take(self) -> Box<T> {
+        self.inner.take()
+    }
+}
+
+impl<O, T, A> Extend<O> for LocalWriteHandle<O, T, A>
+where
+    O: Apply<T, A>,
+{
+    fn extend<I>(&mut self, ops: I)
+    where
+        I: IntoIterator<Item = O>,
+    {
+        self.inner.extend(ops);
+    }
+}
+
+impl<O, T, A> fmt::Debug for LocalWriteHandle<O, T, A>
+where
+    O: Apply<T, A> + fmt::Debug,
+    A: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LocalWriteHandle")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+/// `LocalWriteHandle` can be sent across threads with data that is not `Sync`:
+///
+/// ```
+/// use reft_light::LocalWriteHandle;
+/// use std::cell::Cell;
+///
+/// struct Data(Cell<()>);
+/// impl reft_light::Apply<Data, ()> for () {
+///     fn apply_first(&mut self, _: &mut Data, _: &Data, _: &mut ()) {}
+/// }
+///
+/// fn is_send<T: Send>() {}
+///
+/// is_send::<LocalWriteHandle<(), Data, ()>>()
+/// ```
+///
+/// But the data still has to be `Send`:
+///
+/// ```compile_fail
+/// use reft_light::LocalWriteHandle;
+/// use std::rc::Rc;
+///
+/// struct Data(Rc<()>);
+/// impl reft_light::Apply<Data, ()> for () {
+///     fn apply_first(&mut self, _: &mut Data, _: &Data, _: &mut ()) {}
+/// }
+///
+/// fn is_send<T: Send>() {}
+///
+/// is_send::<LocalWriteHandle<(), Data, ()>>()
+/// ```
+///
+/// And it never hands out a `ReadHandle` that could be sent elsewhere:
+///
+/// ```compile_fail
+/// struct Add(u64);
+/// impl reft_light::Apply<u64, ()> for Add {
+///     fn apply_first(&mut self, first: &mut u64, _: &u64, _: &mut ()) {
+///         *first += self.0;
+///     }
+/// }
+///
+/// let w = reft_light::new_local::<Add, _, _>(0, ());
+/// let r: reft_light::ReadHandle<u64> = w.clone();
+/// ```
+///
+/// Nor can a read stay behind on one thread while the handle moves to another:
+///
+/// ```compile_fail
+/// use reft_light::ReadGuard;
+///
+/// struct Add(u64);
+/// impl reft_light::Apply<u64, ()> for Add {
+///     fn apply_first(&mut self, first: &mut u64, _: &u64, _: &mut ()) {
+///         *first += self.0;
+///     }
+/// }
+///
+/// let mut w = reft_light::new_local::<Add, _, _>(0, ());
+/// let snapshot = ReadGuard::into_owned(w.enter());
+/// let writer = std::thread::spawn(move || {
+///     w.append(Add(1)).publish();
+/// });
+/// assert_eq!(*snapshot, 0);
+/// ```
+#[allow(dead_code)]
+struct CheckLocalWriteHandleSend;
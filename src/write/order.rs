@@ -0,0 +1,99 @@
+use crate::{Apply, Reclaim};
+
+/// The order in which a [`WriteHandle`](crate::WriteHandle) applies each batch of operations.
+///
+/// Set it with [`WriteHandle::set_apply_order`](crate::WriteHandle::set_apply_order).
+///
+/// # Examples
+///
+/// ```
+/// use reft_light::{Apply, ApplyOrder};
+/// use std::collections::BTreeMap;
+///
+/// // last-writer-wins assignments
+/// struct Set(u32, u32);
+/// impl Apply<BTreeMap<u32, u32>, ()> for Set {
+///     fn apply_first(&mut self, first: &mut BTreeMap<u32, u32>, _: &BTreeMap<u32, u32>, _: &mut ()) {
+///         first.insert(self.0, self.1);
+///     }
+///     fn conflicts_with(&self, other: &Self) -> bool {
+///         self.0 == other.0
+///     }
+///     fn supersedes(&self, earlier: &Self) -> bool {
+///         self.0 == earlier.0
+///     }
+/// }
+///
+/// let mut w = reft_light::new::<Set, _, _>(BTreeMap::new(), ());
+/// w.set_apply_order(ApplyOrder::Lifo);
+/// w.extend((0..1000).map(|i| Set(i % 4, i)));
+/// w.publish();
+///
+/// // only the last assignment to each key was applied
+/// assert_eq!(w.enter().unwrap().values().copied().collect::<Vec<_>>(), [996, 997, 998, 999]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApplyOrder {
+    /// Apply operations in the order they were appended. This is the default.
+    Fifo,
+    /// Apply the most recently appended operation first, and skip every operation that a later,
+    /// already applied operation [supersedes](crate::Apply::supersedes).
+    ///
+    /// For last-writer-wins operations, this saves applying the operations that would be
+    /// overwritten anyway. Since the operations of a batch are applied in reverse, any two of them
+    /// that do not supersede one another must [not conflict](crate::Apply::conflicts_with), and
+    /// the result is then the same as with [`Fifo`](Self::Fifo). Each copy skips what it can
+    /// among the operations it applies in one go, so the two copies may skip different
+    /// operations, and only end up the same if `supersedes` holds up its contract.
+    ///
+    /// Oplog stores that cannot hand out their pending operations as a single slice apply them
+    /// to the first copy in order, as with `Fifo`. Checking for superseding operations costs up
+    /// to quadratic time in the number of operations of a batch.
+    Lifo,
+}
+
+// `#[default]` on enum variants needs a newer compiler than the crate supports
+#[allow(clippy::derivable_impls)]
+impl Default for ApplyOrder {
+    fn default() -> Self {
+        ApplyOrder::Fifo
+    }
+}
+
+/// Apply `ops` to the first copy latest first, skipping the ones that an applied operation
+/// supersedes. `kept` is scratch space for the indices of the applied operations.
+pub(super) fn apply_first_lifo<O, T, A>(
+    ops: &mut [O],
+    kept: &mut Vec<usize>,
+    first: &mut T,
+    second: &T,
+    auxiliary: &mut A,
+    reclaim: &mut Reclaim,
+) where
+    O: Apply<T, A>,
+{
+    kept.clear();
+    for i in (0..ops.len()).rev() {
+        if kept.iter().any(|&k| ops[k].supersedes(&ops[i])) {
+            continue;
+        }
+        ops[i].apply_first_deferred(first, second, auxiliary, reclaim);
+        kept.push(i);
+    }
+}
+
+/// Reverse `ops`, and drop the ones that a later operation that is kept supersedes.
+pub(super) fn drop_superseded<O, T, A>(ops: &mut Vec<O>)
+where
+    O: Apply<T, A>,
+{
+    ops.reverse();
+    let mut kept = 0;
+    for i in 0..ops.len() {
+        if !ops[..kept].iter().any(|later| later.supersedes(&ops[i])) {
+            ops.swap(kept, i);
+            kept += 1;
+        }
+    }
+    ops.truncate(kept);
+}
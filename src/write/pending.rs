@@ -0,0 +1,178 @@
+use crate::sync::Arc;
+use crate::{Apply, OplogStore, WriteHandle};
+use std::collections::VecDeque;
+use std::{fmt, thread};
+
+/// A publish that is waiting for readers to depart, while still accepting new operations.
+///
+/// Produced by [`WriteHandle::begin_publish`]. A regular [`publish`](WriteHandle::publish) blocks
+/// the writer until every reader has moved off the stale copy. If the writer is also the one
+/// producing operations, that stalls the whole pipeline behind the slowest reader. A
+/// `PendingPublish` instead lets the writer keep appending while the publish is in flight, and
+/// check back with [`try_complete`](Self::try_complete) whenever it is convenient.
+///
+/// The publish exposes exactly the operations that were appended before it began. Operations
+/// appended to the `PendingPublish` are staged separately, and move to the oplog once the
+/// publish completes, to be exposed by the next one.
+///
+/// Note that only the wait for readers is interleaved with appends. Once the readers have
+/// departed, the oplog is applied and the copies are swapped within the call that noticed, just
+/// like in a regular publish. If the `PendingPublish` is dropped before it completes, the drop
+/// blocks until the publish has completed.
+///
+/// # Examples
+///
+/// ```
+/// use reft_light::Apply;
+///
+/// struct Add(u64);
+/// impl Apply<u64, ()> for Add {
+///     fn apply_first(&mut self, first: &mut u64, _: &u64, _: &mut ()) {
+///         *first += self.0;
+///     }
+/// }
+///
+/// let mut w = reft_light::new::<Add, _, _>(0, ());
+/// let r = w.clone();
+/// w.append(Add(1));
+///
+/// let mut publish = w.begin_publish();
+/// let mut next = 2;
+/// while !publish.try_complete() {
+///     // keep producing operations while readers move off the stale copy
+///     publish.append(Add(next));
+///     next += 1;
+/// }
+/// drop(publish);
+///
+/// assert_eq!(*r.enter().unwrap(), 1);
+/// ```
+pub struct PendingPublish<'w, O, T, A, S = VecDeque<O>>
+where
+    O: Apply<T, A>,
+    S: OplogStore<O>,
+{
+    writer: &'w mut WriteHandle<O, T, A, S>,
+    staged: Vec<O>,
+    complete: bool,
+}
+
+impl<'w, O, T, A, S> PendingPublish<'w, O, T, A, S>
+where
+    O: Apply<T, A>,
+    S: OplogStore<O>,
+{
+    pub(super) fn new(writer: &'w mut WriteHandle<O, T, A, S>) -> Self {
+        writer.assert_owner();
+        PendingPublish {
+            writer,
+            staged: Vec::new(),
+            complete: false,
+        }
+    }
+
+    /// Append the given operation to be exposed by the next publish after this one.
+    pub fn append(&mut self, op: O) -> &mut Self {
+        self.extend(std::iter::once(op));
+        self
+    }
+
+    /// Complete the publish if all readers have departed from the stale copy, without blocking
+    /// otherwise.
+    ///
+    /// Returns true if the publish has completed, either now or in an earlier call.
+    pub fn try_complete(&mut self) -> bool {
+        if !self.complete {
+            let epochs = Arc::clone(&self.writer.epochs);
+            let mut epochs = crate::lock_epochs(&epochs);
+            if !self.writer.has_departed(&mut epochs, false) {
+                return false;
+            }
+            // fresh readers hold a lock rather than an epoch, and must not make us block either
+            let fresh = self.writer.fresh.clone();
+            let mut fresh = match &fresh {
+                Some(fresh) => match crate::read::try_lock_fresh(fresh) {
+                    Some(fresh) => Some(fresh),
+                    None => return false,
+                },
+                None => None,
+            };
+            self.writer.apply_oplog();
+            self.writer.flip(Some(&mut epochs), fresh.as_deref_mut());
+            self.finish();
+        }
+        true
+    }
+
+    /// Returns true if the publish has completed.
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// Wait for the publish to complete.
+    pub fn complete(mut self) {
+        self.block();
+    }
+
+    fn block(&mut self) {
+        if !self.complete {
+            let epochs = Arc::clone(&self.writer.epochs);
+            let mut epochs = crate::lock_epochs(&epochs);
+            self.writer.wait(&mut epochs);
+            let fresh = self.writer.fresh.clone();
+            let mut fresh = fresh.as_ref().map(crate::read::lock_fresh);
+            self.writer.apply_oplog();
+            self.writer.flip(Some(&mut epochs), fresh.as_deref_mut());
+            self.finish();
+        }
+    }
+
+    fn finish(&mut self) {
+        self.complete = true;
+        let staged = std::mem::take(&mut self.staged);
+        self.writer.extend(staged);
+    }
+}
+
+impl<'w, O, T, A, S> Extend<O> for PendingPublish<'w, O, T, A, S>
+where
+    O: Apply<T, A>,
+    S: OplogStore<O>,
+{
+    fn extend<I>(&mut self, ops: I)
+    where
+        I: IntoIterator<Item = O>,
+    {
+        if self.complete {
+            self.writer.extend(ops);
+        } else {
+            self.staged.extend(ops);
+        }
+    }
+}
+
+impl<'w, O, T, A, S> Drop for PendingPublish<'w, O, T, A, S>
+where
+    O: Apply<T, A>,
+    S: OplogStore<O>,
+{
+    fn drop(&mut self) {
+        // don't pile a second panic onto one that is already unwinding
+        if !thread::panicking() {
+            self.block();
+        }
+    }
+}
+
+impl<'w, O, T, A, S> fmt::Debug for PendingPublish<'w, O, T, A, S>
+where
+    O: Apply<T, A> + fmt::Debug,
+    S: OplogStore<O>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PendingPublish")
+            .field("staged", &self.staged)
+            .field("complete", &self.complete)
+            .finish()
+    }
+}
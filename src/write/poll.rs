@@ -0,0 +1,40 @@
+use crate::{Apply, OplogStore, WriteHandle};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// A future that publishes once all readers have departed from the stale copy.
+///
+/// Produced by [`WriteHandle::publish_async`], and driven by
+/// [`WriteHandle::poll_publish`]. Any executor can drive it, since it relies on nothing but the
+/// [`Waker`](std::task::Waker) it is polled with.
+pub struct PublishFuture<'w, O, T, A, S>
+where
+    O: Apply<T, A>,
+    S: OplogStore<O>,
+{
+    pub(super) writer: &'w mut WriteHandle<O, T, A, S>,
+}
+
+impl<'w, O, T, A, S> Future for PublishFuture<'w, O, T, A, S>
+where
+    O: Apply<T, A>,
+    S: OplogStore<O>,
+{
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.writer.poll_publish(cx)
+    }
+}
+
+impl<'w, O, T, A, S> fmt::Debug for PublishFuture<'w, O, T, A, S>
+where
+    O: Apply<T, A>,
+    S: OplogStore<O>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PublishFuture").finish()
+    }
+}
@@ -0,0 +1,58 @@
+use std::error::Error;
+use std::fmt;
+
+/// The error returned by [`WriteHandle::try_publish`](crate::WriteHandle::try_publish) when a
+/// pending operation fails [validation](crate::Apply::validate).
+///
+/// Nothing was published, and every pending operation is still in the oplog. The rejected
+/// operation can be found at position [`index`](Self::index) among them, counted in the order in
+/// which the publish would have applied them.
+#[derive(Debug)]
+pub struct Rejected {
+    index: usize,
+    error: Box<dyn Error + Send + Sync>,
+}
+
+impl Rejected {
+    pub(crate) fn new(index: usize, error: Box<dyn Error + Send + Sync>) -> Self {
+        Self { index, error }
+    }
+
+    /// Returns the position of the rejected operation among the pending operations, in the
+    /// order in which the publish would have applied them.
+    ///
+    /// That is the order they were appended in, with two exceptions. An operation appended with
+    /// [`append_priority`](crate::WriteHandle::append_priority) is counted ahead of every pending
+    /// operation that [`current`](crate::WriteHandle::current) has not applied yet. And the
+    /// operations that `current` has applied may have been grouped into
+    /// [homogeneous runs](crate::Apply::is_homogeneous_with) as they were applied.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the error the operation was rejected with.
+    pub fn error(&self) -> &(dyn Error + Send + Sync + 'static) {
+        &*self.error
+    }
+
+    /// Returns the error the operation was rejected with, by value.
+    pub fn into_error(self) -> Box<dyn Error + Send + Sync> {
+        self.error
+    }
+}
+
+impl fmt::Display for Rejected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "pending operation {} was rejected: {}",
+            self.index, self.error
+        )
+    }
+}
+
+impl Error for Rejected {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&*self.error)
+    }
+}
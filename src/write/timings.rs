@@ -0,0 +1,43 @@
+use std::time::Duration;
+
+/// How long each phase of the most recent [`WriteHandle::publish`](crate::WriteHandle::publish)
+/// took.
+///
+/// The phases have quite different costs. Waiting depends only on how long readers hold on to the
+/// stale copy. [`Apply::apply_second`](crate::Apply::apply_second) consumes the operations of the
+/// previous publish, while [`Apply::apply_first`](crate::Apply::apply_first) only borrows the
+/// operations of this one, so an implementation that clones in one but moves in the other shows
+/// up here.
+///
+/// Only available with the `metrics` feature.
+///
+/// # Examples
+///
+/// ```
+/// use reft_light::Apply;
+/// use std::time::Duration;
+///
+/// struct Add(u64);
+/// impl Apply<u64, ()> for Add {
+///     fn apply_first(&mut self, first: &mut u64, _: &u64, _: &mut ()) {
+///         *first += self.0;
+///     }
+/// }
+///
+/// let mut w = reft_light::new::<Add, _, _>(0, ());
+/// w.extend((0..1000).map(Add));
+/// w.publish();
+/// let timings = w.phase_timings();
+/// // nothing was published before, so there was nothing to apply to the second copy
+/// assert_eq!(timings.apply_second, Duration::ZERO);
+/// assert!(timings.apply_first > Duration::ZERO);
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PhaseTimings {
+    /// Time spent waiting for readers to depart from the stale copy.
+    pub wait: Duration,
+    /// Time spent applying the operations of the previous publish to the stale copy.
+    pub apply_second: Duration,
+    /// Time spent applying the pending operations to the stale copy.
+    pub apply_first: Duration,
+}
@@ -0,0 +1,109 @@
+use crate::{Apply, OplogStore, WriteHandle};
+use std::collections::VecDeque;
+use std::fmt;
+
+/// A group of operations that is either appended as a whole, or not at all.
+///
+/// Produced by [`WriteHandle::transaction`]. Operations appended to a `Transaction` are staged
+/// outside the oplog until [`commit`](Self::commit) moves them into it, to be exposed by the next
+/// publish like any other operation. [`rollback`](Self::rollback) discards them instead, as does
+/// dropping the transaction without committing it. Either way, operations that were appended to
+/// the `WriteHandle` before the transaction started are left alone.
+///
+/// # Examples
+///
+/// ```
+/// use reft_light::Apply;
+///
+/// struct Push(i32);
+/// impl Apply<Vec<i32>, ()> for Push {
+///     fn apply_first(&mut self, first: &mut Vec<i32>, _: &Vec<i32>, _: &mut ()) {
+///         first.push(self.0);
+///     }
+/// }
+///
+/// let mut w = reft_light::new::<Push, _, _>(vec![], ());
+/// w.append(Push(1));
+///
+/// let mut tx = w.transaction();
+/// tx.append(Push(2)).append(Push(3));
+/// tx.rollback();
+///
+/// let mut tx = w.transaction();
+/// tx.append(Push(4));
+/// tx.commit();
+///
+/// w.publish();
+/// assert_eq!(*w.enter().unwrap(), [1, 4]);
+/// ```
+pub struct Transaction<'w, O, T, A, S = VecDeque<O>>
+where
+    O: Apply<T, A>,
+    S: OplogStore<O>,
+{
+    writer: &'w mut WriteHandle<O, T, A, S>,
+    staged: Vec<O>,
+}
+
+impl<'w, O, T, A, S> Transaction<'w, O, T, A, S>
+where
+    O: Apply<T, A>,
+    S: OplogStore<O>,
+{
+    pub(super) fn new(writer: &'w mut WriteHandle<O, T, A, S>) -> Self {
+        Transaction {
+            writer,
+            staged: Vec::new(),
+        }
+    }
+
+    /// Stage the given operation as part of the transaction.
+    pub fn append(&mut self, op: O) -> &mut Self {
+        self.staged.push(op);
+        self
+    }
+
+    /// Returns the number of operations staged so far.
+    pub fn len(&self) -> usize {
+        self.staged.len()
+    }
+
+    /// Returns true if no operations have been staged.
+    pub fn is_empty(&self) -> bool {
+        self.staged.is_empty()
+    }
+
+    /// Append all staged operations to the writer's oplog.
+    pub fn commit(mut self) {
+        let staged = std::mem::take(&mut self.staged);
+        self.writer.extend(staged);
+    }
+
+    /// Discard all staged operations.
+    pub fn rollback(self) {}
+}
+
+impl<'w, O, T, A, S> Extend<O> for Transaction<'w, O, T, A, S>
+where
+    O: Apply<T, A>,
+    S: OplogStore<O>,
+{
+    fn extend<I>(&mut self, ops: I)
+    where
+        I: IntoIterator<Item = O>,
+    {
+        self.staged.extend(ops);
+    }
+}
+
+impl<'w, O, T, A, S> fmt::Debug for Transaction<'w, O, T, A, S>
+where
+    O: Apply<T, A> + fmt::Debug,
+    S: OplogStore<O>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Transaction")
+            .field("staged", &self.staged)
+            .finish()
+    }
+}
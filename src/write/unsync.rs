@@ -0,0 +1,171 @@
+use crate::{Apply, WriteHandle};
+use std::fmt;
+use std::marker::PhantomData;
+
+/// A writer handle to a left-right that lives on a single thread, and whose data is only read
+/// through the writer.
+///
+/// Produced by [`new_unsync`](crate::new_unsync). Most of what a [`WriteHandle`] does during
+/// [`publish`](WriteHandle::publish) exists to keep concurrent readers safe, or to tell them about
+/// the swap: it locks the list of reader epochs, waits for readers to depart from the stale copy,
+/// records which readers were active at the swap, timestamps the new copy, and updates the
+/// generation that readers wait on. An `UnsyncWriteHandle` has no concurrent readers. It never
+/// hands out a [`ReadHandle`](crate::ReadHandle), reads through [`enter`](Self::enter) borrow the
+/// handle, and it cannot be sent to another thread. Its publishes therefore skip all of that, and
+/// only replay the oplog and swap the copies.
+///
+/// What remains is a cheap double buffer: operations can be appended while the published
+/// snapshot is read, and only become visible once `publish` is called, for example at the end of
+/// each frame of a game loop.
+///
+/// # Examples
+///
+/// ```
+/// use reft_light::Apply;
+///
+/// struct Step(i64);
+/// impl Apply<Vec<i64>, ()> for Step {
+///     fn apply_first(&mut self, first: &mut Vec<i64>, _: &Vec<i64>, _: &mut ()) {
+///         for x in first.iter_mut() {
+///             *x += self.0;
+///         }
+///     }
+/// }
+///
+/// let mut w = reft_light::new_unsync::<Step, _, _>(vec![0, 10, 20], ());
+/// for frame in 1..=3 {
+///     // the update for the next frame is computed from the current one
+///     let step = w.enter().iter().sum::<i64>() % 7;
+///     w.append(Step(step)).publish();
+///     assert_eq!(w.enter().len(), 3, "frame {}", frame);
+/// }
+/// assert_eq!(*w.enter(), [7, 17, 27]);
+/// ```
+pub struct UnsyncWriteHandle<O, T, A>
+where
+    O: Apply<T, A>,
+{
+    inner: WriteHandle<O, T, A>,
+    // publishing without waiting is only sound while every read happens on this thread
+    _not_send: PhantomData<*const ()>,
+}
+
+impl<O, T, A> UnsyncWriteHandle<O, T, A>
+where
+    O: Apply<T, A>,
+{
+    pub(crate) fn new(inner: WriteHandle<O, T, A>) -> Self {
+        Self {
+            inner,
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Returns a reference to the published copy of the `T`.
+    ///
+    /// The reference borrows the handle, so no operations can be published while it lives.
+    pub fn enter(&self) -> &T {
+        self.inner.read_copy()
+    }
+
+    /// Append the given operation to the operational log.
+    ///
+    /// See [`WriteHandle::append`].
+    pub fn append(&mut self, op: O) -> &mut Self {
+        self.inner.append(op);
+        self
+    }
+
+    /// Publish all operations appended to the log.
+    ///
+    /// Unlike [`WriteHandle::publish`], this neither looks at nor waits for readers.
+    pub fn publish(&mut self) -> &mut Self {
+        self.inner.publish_unsync();
+        self
+    }
+
+    /// Publish if there are pending operations.
+    pub fn flush(&mut self) {
+        if self.has_pending_operations() {
+            self.publish();
+        }
+    }
+
+    /// Returns true if there are operations in the operational log that have not yet been
+    /// published.
+    pub fn has_pending_operations(&self) -> bool {
+        self.inner.has_pending_operations()
+    }
+
+    /// Returns a reference to the auxiliary data.
+    pub fn auxiliary(&self) -> &A {
+        self.inner.auxiliary()
+    }
+
+    /// Returns a mutable reference to the auxiliary data.
+    pub fn auxiliary_mut(&mut self) -> &mut A {
+        self.inner.auxiliary_mut()
+    }
+
+    /// Returns the backing data structure, with all pending operations applied.
+    pub fn take(self) -> Box<T> {
+        self.inner.take()
+    }
+}
+
+impl<O, T, A> Extend<O> for UnsyncWriteHandle<O, T, A>
+where
+    O: Apply<T, A>,
+{
+    fn extend<I>(&mut self, ops: I)
+    where
+        I: IntoIterator<Item = O>,
+    {
+        self.inner.extend(ops);
+    }
+}
+
+impl<O, T, A> fmt::Debug for UnsyncWriteHandle<O, T, A>
+where
+    O: Apply<T, A> + fmt::Debug,
+    A: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UnsyncWriteHandle")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+/// `UnsyncWriteHandle` cannot be sent across threads, even if the data could be:
+///
+/// ```compile_fail
+/// use reft_light::UnsyncWriteHandle;
+///
+/// struct Add(u64);
+/// impl reft_light::Apply<u64, ()> for Add {
+///     fn apply_first(&mut self, first: &mut u64, _: &u64, _: &mut ()) {
+///         *first += self.0;
+///     }
+/// }
+///
+/// fn is_send<T: Send>() {}
+///
+/// is_send::<UnsyncWriteHandle<Add, u64, ()>>()
+/// ```
+///
+/// And it never hands out a `ReadHandle` that could read concurrently:
+///
+/// ```compile_fail
+/// struct Add(u64);
+/// impl reft_light::Apply<u64, ()> for Add {
+///     fn apply_first(&mut self, first: &mut u64, _: &u64, _: &mut ()) {
+///         *first += self.0;
+///     }
+/// }
+///
+/// let w = reft_light::new_unsync::<Add, _, _>(0, ());
+/// let r: reft_light::ReadHandle<u64> = w.clone();
+/// ```
+#[allow(dead_code)]
+struct CheckUnsyncWriteHandleSend;
@@ -11,7 +11,8 @@ mod loom_tests {
     #[test]
     fn read_before_publish() {
         loom::model(|| {
-            let (mut w, r) = reft_light::new::<i32, _>();
+            let mut w = reft_light::new::<CounterAddOp, _, _>(0, ());
+            let r = w.clone();
 
             w.append(CounterAddOp(1));
             w.publish();
@@ -26,4 +27,47 @@ mod loom_tests {
             assert_eq!(1, val);
         });
     }
+
+    #[test]
+    fn read_during_drop() {
+        loom::model(|| {
+            let mut w = reft_light::new::<CounterAddOp, _, _>(0, ());
+            let r = w.clone();
+
+            w.append(CounterAddOp(1));
+            w.publish();
+
+            // the reader enters after the publish recorded its epoch, and may still be reading
+            // when the writer frees both copies.
+            let jh = thread::spawn(move || r.enter().map(|v| *v));
+
+            drop(w);
+
+            let val = jh.join().unwrap();
+
+            assert!(val.is_none() || val == Some(1));
+        });
+    }
+
+    #[test]
+    fn publish_async_is_woken_by_departing_reader() {
+        loom::model(|| {
+            let mut w = reft_light::new::<CounterAddOp, _, _>(0, ());
+            let r = w.clone();
+
+            w.append(CounterAddOp(1));
+            w.publish();
+
+            let jh = thread::spawn(move || r.enter().map(|v| *v));
+
+            // the first publish moves the reader's copy out, the second one may have to wait
+            // for the reader to leave it, and must then be woken when it does.
+            loom::future::block_on(w.publish_async());
+            loom::future::block_on(w.publish_async());
+
+            let val = jh.join().unwrap();
+
+            assert_eq!(val, Some(1));
+        });
+    }
 }